@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS review_log (
+    id SERIAL PRIMARY KEY,
+    flashcard_id INTEGER NOT NULL,
+    rating card_rating NOT NULL,
+    reviewed TIMESTAMP NOT NULL,
+    scheduled TIMESTAMP NOT NULL,
+    stability REAL NOT NULL,
+    difficulty REAL NOT NULL,
+    FOREIGN KEY (flashcard_id) REFERENCES flashcard(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_log_flashcard ON review_log(flashcard_id, reviewed);
+
+CREATE TABLE IF NOT EXISTS user_settings (
+    user_id TEXT PRIMARY KEY,
+    max_cards_per_session SMALLINT NOT NULL DEFAULT 20,
+    desired_retention SMALLINT NOT NULL DEFAULT 75
+);
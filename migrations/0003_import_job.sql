@@ -0,0 +1,16 @@
+CREATE TYPE import_job_status AS ENUM ('running', 'completed', 'failed', 'cancelled');
+
+CREATE TABLE IF NOT EXISTS import_job (
+    id SERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    deck_id INTEGER NOT NULL,
+    status import_job_status NOT NULL DEFAULT 'running',
+    total INTEGER NOT NULL,
+    processed INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (deck_id) REFERENCES deck(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_job_user ON import_job(user_id, deck_id);
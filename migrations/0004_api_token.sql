@@ -0,0 +1,18 @@
+CREATE TYPE api_token_scope AS ENUM ('read', 'write');
+
+CREATE TABLE IF NOT EXISTS api_token (
+    id SERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    -- SHA-256 of the token; the token itself is only shown once
+    token_hash TEXT NOT NULL UNIQUE,
+    -- The start of the token, to recognize it in the list
+    prefix TEXT NOT NULL,
+    scope api_token_scope NOT NULL,
+    created TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires TIMESTAMP,
+    last_used TIMESTAMP,
+    revoked TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_token_user ON api_token(user_id);
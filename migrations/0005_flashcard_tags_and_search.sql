@@ -0,0 +1,13 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+ALTER TABLE flashcard ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+
+-- The 'simple' configuration doesn't stem, as cards mix languages
+ALTER TABLE flashcard ADD COLUMN IF NOT EXISTS search_vector tsvector
+    GENERATED ALWAYS AS (to_tsvector('simple', front || ' ' || back)) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_flashcard_search ON flashcard USING GIN (search_vector);
+-- Substring and similarity matches, for CJK text (which isn't split into words) and short words
+CREATE INDEX IF NOT EXISTS idx_flashcard_front_trgm ON flashcard USING GIN (front gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_flashcard_back_trgm ON flashcard USING GIN (back gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_flashcard_tags ON flashcard USING GIN (tags);
@@ -0,0 +1,4 @@
+-- Suspended cards are kept (with their history) but not studied
+ALTER TABLE flashcard ADD COLUMN IF NOT EXISTS suspended BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS idx_flashcard_deck_scheduled ON flashcard (deck_id, last_scheduled);
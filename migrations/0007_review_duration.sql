@@ -0,0 +1,4 @@
+-- Time from showing the card to rating it (unknown for older reviews)
+ALTER TABLE review_log ADD COLUMN IF NOT EXISTS duration_ms INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_review_log_reviewed ON review_log(reviewed);
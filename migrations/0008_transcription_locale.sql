@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS transcription_locale TEXT NOT NULL DEFAULT 'en-US';
@@ -0,0 +1,7 @@
+-- Whether spoken answers are checked against the back of the cards, and how
+CREATE TYPE answer_mode AS ENUM ('off', 'exact', 'fuzzy', 'regex');
+
+ALTER TABLE deck ADD COLUMN IF NOT EXISTS answer_mode answer_mode NOT NULL DEFAULT 'off';
+
+-- A regular expression for the accepted spoken answers, e.g. for transliterations
+ALTER TABLE flashcard ADD COLUMN IF NOT EXISTS accepted_answers TEXT;
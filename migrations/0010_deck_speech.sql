@@ -0,0 +1,7 @@
+-- Reading the cards aloud on the glasses, with a voice and a language for each side
+ALTER TABLE deck ADD COLUMN IF NOT EXISTS speak_front BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE deck ADD COLUMN IF NOT EXISTS speak_back BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE deck ADD COLUMN IF NOT EXISTS front_voice TEXT;
+ALTER TABLE deck ADD COLUMN IF NOT EXISTS front_language TEXT;
+ALTER TABLE deck ADD COLUMN IF NOT EXISTS back_voice TEXT;
+ALTER TABLE deck ADD COLUMN IF NOT EXISTS back_language TEXT;
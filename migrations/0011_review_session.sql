@@ -0,0 +1,21 @@
+-- Minutes within which an interrupted session on the glasses can be resumed (0 never offers it)
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS resume_window_minutes SMALLINT NOT NULL DEFAULT 60;
+
+-- The review sessions on the glasses, with their progress
+CREATE TABLE IF NOT EXISTS review_session (
+    id SERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    -- The cards still to review, the current one first
+    card_ids INTEGER[] NOT NULL DEFAULT '{}',
+    easy INTEGER NOT NULL DEFAULT 0,
+    good INTEGER NOT NULL DEFAULT 0,
+    difficult INTEGER NOT NULL DEFAULT 0,
+    again INTEGER NOT NULL DEFAULT 0,
+    duration_ms BIGINT NOT NULL DEFAULT 0,
+    started TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated TIMESTAMP NOT NULL DEFAULT NOW(),
+    -- When the queue was emptied or a new session was started instead of resuming this one
+    finished TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_session_user ON review_session(user_id, updated);
@@ -0,0 +1,3 @@
+-- The cards rated "again" in the session, and when the next review was due once it finished
+ALTER TABLE review_session ADD COLUMN IF NOT EXISTS failed_card_ids INTEGER[] NOT NULL DEFAULT '{}';
+ALTER TABLE review_session ADD COLUMN IF NOT EXISTS next_due TIMESTAMP;
@@ -0,0 +1,8 @@
+-- The review actions of the button presses and head movements on the glasses
+CREATE TYPE input_action AS ENUM ('none', 'reveal', 'good', 'again', 'skip', 'undo', 'pause');
+
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS short_press_action input_action NOT NULL DEFAULT 'reveal';
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS long_press_action input_action NOT NULL DEFAULT 'reveal';
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS double_press_action input_action NOT NULL DEFAULT 'none';
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS head_up_action input_action NOT NULL DEFAULT 'reveal';
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS head_down_action input_action NOT NULL DEFAULT 'none';
@@ -0,0 +1,306 @@
+//! Checking spoken answers against the back of the cards, for the decks with an answer mode.
+//!
+//! Answers are compared word by word (with each CJK character as a word, see
+//! `voice::tokenize`), ignoring case and punctuation. The diff shows the missing words in
+//! brackets and the extra words in parentheses, e.g. "the [big] (small) house".
+use std::{fmt::Display, time::Duration};
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::{
+    models::{AnswerMode, CardRating, Flashcard},
+    voice::{BOUNDARY, edit_distance, is_cjk, tokenize},
+};
+
+/// Correct answers given faster are proposed as easy
+const EASY_ANSWER_TIME: Duration = Duration::from_secs(6);
+
+/// Correct answers given slower are proposed as difficult
+const SLOW_ANSWER_TIME: Duration = Duration::from_secs(20);
+
+/// Characters per allowed typo for close answers, e.g. 2 typos in 10 characters
+const CHARACTERS_PER_TYPO: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Correctness {
+    Correct,
+    /// Within a few typos, e.g. a misheard word
+    Close,
+    Wrong,
+}
+
+impl Display for Correctness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Correctness::Correct => write!(f, "Correct"),
+            Correctness::Close => write!(f, "Almost"),
+            Correctness::Wrong => write!(f, "Wrong"),
+        }
+    }
+}
+
+/// A word of the diff between the expected and the spoken answer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "word", rename_all = "lowercase")]
+pub enum DiffWord {
+    Same(String),
+    /// Expected but not said
+    Missing(String),
+    /// Said but not expected
+    Extra(String),
+}
+
+impl DiffWord {
+    fn word(&self) -> &str {
+        match self {
+            DiffWord::Same(word) | DiffWord::Missing(word) | DiffWord::Extra(word) => word,
+        }
+    }
+}
+
+/// A spoken answer with the rating proposed for it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnswerCheck {
+    pub spoken: String,
+    pub correctness: Correctness,
+    pub diff: Vec<DiffWord>,
+    pub proposed: CardRating,
+}
+
+impl AnswerCheck {
+    /// The diff as text for the glasses, e.g. "the [big] (small) house"
+    pub fn diff_text(&self) -> String {
+        join(self.diff.iter().map(|part| {
+            let text = match part {
+                DiffWord::Same(word) => word.clone(),
+                DiffWord::Missing(word) => format!("[{word}]"),
+                DiffWord::Extra(word) => format!("({word})"),
+            };
+            (part.word(), text)
+        }))
+    }
+}
+
+/// The words of a text, lowercase and without punctuation
+pub fn normalize(text: &str) -> Vec<String> {
+    tokenize(text)
+        .into_iter()
+        .filter(|token| token != BOUNDARY)
+        .collect()
+}
+
+fn is_cjk_word(word: &str) -> bool {
+    word.chars().all(is_cjk)
+}
+
+/// Joins words (with their display text) with spaces, except between CJK characters
+fn join<'a>(words: impl Iterator<Item = (&'a str, String)>) -> String {
+    let mut joined = String::new();
+    let mut previous: Option<&str> = None;
+    for (word, text) in words {
+        if previous.is_some_and(|previous| !(is_cjk_word(previous) && is_cjk_word(word))) {
+            joined.push(' ');
+        }
+        joined.push_str(&text);
+        previous = Some(word);
+    }
+    joined
+}
+
+fn join_words(words: &[String]) -> String {
+    join(words.iter().map(|word| (word.as_str(), word.clone())))
+}
+
+/// The accepted answers as a case-insensitive regular expression for the whole answer
+pub fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(&format!("^(?:{})$", pattern.trim()))
+        .case_insensitive(true)
+        .build()
+}
+
+/// Whether the answer matches the accepted answers, as said or without punctuation
+fn matches_pattern(pattern: &Regex, spoken: &str) -> bool {
+    let spoken = spoken.trim();
+    let without_punctuation = spoken.trim_end_matches(|c: char| !c.is_alphanumeric());
+    [spoken, without_punctuation, &join_words(&normalize(spoken))]
+        .into_iter()
+        .any(|text| pattern.is_match(text))
+}
+
+/// The word diff of the spoken answer, from the longest common subsequence
+fn diff(expected: &[String], spoken: &[String]) -> Vec<DiffWord> {
+    let (n, m) = (expected.len(), spoken.len());
+    // common[i][j] is the length of the common subsequence of expected[i..] and spoken[j..]
+    let mut common = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if expected[i] == spoken[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut diff = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == spoken[j] {
+            diff.push(DiffWord::Same(expected[i].clone()));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(DiffWord::Missing(expected[i].clone()));
+            i += 1;
+        } else {
+            diff.push(DiffWord::Extra(spoken[j].clone()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// How close the spoken words are to the expected ones
+fn compare(mode: AnswerMode, expected: &[String], spoken: &[String]) -> Correctness {
+    if expected == spoken {
+        return Correctness::Correct;
+    }
+    if mode == AnswerMode::Exact {
+        return Correctness::Wrong;
+    }
+    let (expected, spoken) = (join_words(expected), join_words(spoken));
+    let tolerance = (expected.chars().count() / CHARACTERS_PER_TYPO).max(1);
+    if edit_distance(&expected, &spoken) <= tolerance {
+        Correctness::Close
+    } else {
+        Correctness::Wrong
+    }
+}
+
+/// The rating for an answer: the correct ones by how long they took
+pub fn propose_rating(correctness: Correctness, elapsed: Duration) -> CardRating {
+    match correctness {
+        Correctness::Wrong => CardRating::Again,
+        Correctness::Close => CardRating::Difficult,
+        Correctness::Correct if elapsed <= EASY_ANSWER_TIME => CardRating::Easy,
+        Correctness::Correct if elapsed >= SLOW_ANSWER_TIME => CardRating::Difficult,
+        Correctness::Correct => CardRating::Good,
+    }
+}
+
+/// Checks a spoken answer against the back of the card, or its accepted answers in the
+/// `Regex` mode, given the time since the card was shown
+pub fn check_answer(
+    mode: AnswerMode,
+    spoken: &str,
+    card: &Flashcard,
+    elapsed: Duration,
+) -> AnswerCheck {
+    let spoken_words = normalize(spoken);
+    let pattern = match mode {
+        AnswerMode::Regex => card
+            .accepted_answers
+            .as_deref()
+            .and_then(|pattern| compile_pattern(pattern).ok()),
+        _ => None,
+    };
+    let (correctness, diff) = match pattern {
+        Some(pattern) if matches_pattern(&pattern, spoken) => (
+            Correctness::Correct,
+            spoken_words.into_iter().map(DiffWord::Same).collect(),
+        ),
+        _ => {
+            let expected = normalize(&card.back_text());
+            (
+                compare(mode, &expected, &spoken_words),
+                diff(&expected, &spoken_words),
+            )
+        }
+    };
+    AnswerCheck {
+        spoken: spoken.trim().to_string(),
+        correctness,
+        diff,
+        proposed: propose_rating(correctness, elapsed),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{Correctness, check_answer, compile_pattern, propose_rating};
+    use crate::models::{AnswerMode, CardRating, Flashcard};
+
+    const QUICKLY: Duration = Duration::from_secs(3);
+    const SLOWLY: Duration = Duration::from_secs(10);
+
+    fn card(back: &str, accepted_answers: Option<&str>) -> Flashcard {
+        Flashcard {
+            back: back.to_string(),
+            accepted_answers: accepted_answers.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_answer() {
+        let house = card("The big house", None);
+        let check = check_answer(AnswerMode::Exact, "The big house.", &house, QUICKLY);
+        assert_eq!(check.correctness, Correctness::Correct);
+        assert_eq!(check.proposed, CardRating::Easy);
+        assert_eq!(check.diff_text(), "the big house");
+
+        let check = check_answer(AnswerMode::Exact, "The small house.", &house, SLOWLY);
+        assert_eq!(check.correctness, Correctness::Wrong);
+        assert_eq!(check.proposed, CardRating::Again);
+        assert_eq!(check.diff_text(), "the [big] (small) house");
+
+        let check = check_answer(AnswerMode::Fuzzy, "The big hose.", &house, SLOWLY);
+        assert_eq!(check.correctness, Correctness::Close);
+        assert_eq!(check.proposed, CardRating::Difficult);
+        let check = check_answer(AnswerMode::Fuzzy, "A house.", &house, SLOWLY);
+        assert_eq!(check.correctness, Correctness::Wrong);
+        assert_eq!(check.diff_text(), "[the] [big] (a) house");
+
+        let check = check_answer(AnswerMode::Fuzzy, "你号", &card("你好", None), SLOWLY);
+        assert_eq!(check.correctness, Correctness::Close);
+        assert_eq!(check.diff_text(), "你[好](号)");
+    }
+
+    #[test]
+    fn test_accepted_answers() {
+        let come = card("lei4 laa1", Some(r"(嚟|黎|來|lei4?)\s*(啦|喇|laa1?)"));
+        for spoken in ["嚟啦。", "Lei laa.", "lei4 laa1"] {
+            let check = check_answer(AnswerMode::Regex, spoken, &come, SLOWLY);
+            assert_eq!(check.correctness, Correctness::Correct, "{spoken}");
+            assert_eq!(check.proposed, CardRating::Good);
+        }
+        // Anchored to the whole answer, then compared with the back
+        let check = check_answer(AnswerMode::Regex, "Lei laa laa.", &come, SLOWLY);
+        assert_eq!(check.correctness, Correctness::Wrong);
+        // Only checked in the regex mode
+        let check = check_answer(AnswerMode::Fuzzy, "嚟啦", &come, SLOWLY);
+        assert_eq!(check.correctness, Correctness::Wrong);
+
+        assert!(compile_pattern("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_propose_rating() {
+        let slow = Duration::from_secs(30);
+        assert_eq!(
+            propose_rating(Correctness::Correct, slow),
+            CardRating::Difficult
+        );
+        assert_eq!(
+            propose_rating(Correctness::Close, QUICKLY),
+            CardRating::Difficult
+        );
+        assert_eq!(
+            propose_rating(Correctness::Wrong, QUICKLY),
+            CardRating::Again
+        );
+    }
+}
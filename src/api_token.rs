@@ -0,0 +1,106 @@
+//! Personal access tokens for scripts and other automation using the JSON API.
+//! Only the SHA-256 hash of a token is stored; the token is shown once when it's created.
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::{
+    models::{ApiToken, ApiTokenNew, ApiTokenScope},
+    sdk::app_session::UserId,
+};
+
+/// Start of all personal access tokens, to tell them apart from the MentraOS tokens
+pub const TOKEN_PREFIX: &str = "mc_pat_";
+
+/// Number of characters (after `TOKEN_PREFIX`) stored to recognize a token
+const DISPLAYED_CHARS: usize = 6;
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    format!("{TOKEN_PREFIX}{}", hex::encode(rand::random::<[u8; 32]>()))
+}
+
+/// Creates a token for the user and returns it in plain text
+pub async fn create_token(
+    pool: &PgPool,
+    user_id: &str,
+    new_token: &ApiTokenNew,
+) -> Result<String, sqlx::Error> {
+    let token = generate_token();
+    let prefix = &token[..TOKEN_PREFIX.len() + DISPLAYED_CHARS];
+    let expires = (new_token.expires_in_days > 0)
+        .then(|| Utc::now().naive_utc() + Duration::days(new_token.expires_in_days.into()));
+    sqlx::query(
+        r#"
+        INSERT INTO api_token (user_id, name, token_hash, prefix, scope, expires)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(user_id)
+    .bind(new_token.name.trim())
+    .bind(hash_token(&token))
+    .bind(prefix)
+    .bind(new_token.scope)
+    .bind(expires)
+    .execute(pool)
+    .await?;
+    Ok(token)
+}
+
+/// Returns the user and scope of a valid (not expired or revoked) token, and records its use
+pub async fn verify_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<(UserId, ApiTokenScope)>, sqlx::Error> {
+    let row: Option<(String, ApiTokenScope)> = sqlx::query_as(
+        r#"
+        UPDATE api_token SET last_used = NOW()
+        WHERE token_hash = $1 AND revoked IS NULL AND (expires IS NULL OR expires > NOW())
+        RETURNING user_id, scope
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(user_id, scope)| (user_id.into(), scope)))
+}
+
+/// The tokens of the user that haven't been revoked, newest first
+pub async fn list_tokens(pool: &PgPool, user_id: &str) -> Result<Vec<ApiToken>, sqlx::Error> {
+    sqlx::query_as::<_, ApiToken>(
+        r#"
+        SELECT id, name, prefix, scope, created, expires, last_used FROM api_token
+        WHERE user_id = $1 AND revoked IS NULL
+        ORDER BY id DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn revoke_token(pool: &PgPool, user_id: &str, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE api_token SET revoked = NOW() WHERE id = $1 AND user_id = $2 AND revoked IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_generate_token() {
+        let token = super::generate_token();
+        assert!(token.starts_with(super::TOKEN_PREFIX));
+        assert_eq!(token.len(), super::TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, super::generate_token());
+        assert_eq!(super::hash_token(&token).len(), 64);
+    }
+}
@@ -0,0 +1,437 @@
+//! Module for exporting all of a user's data into a single versioned JSON file
+//! and restoring it (into the same or another instance).
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    models::{
+        AnswerMode, CardRating, Deck, DeckSpeech, Flashcard, FlashcardReview, StoredUserSettings,
+    },
+    srs::MAX_RESUME_WINDOW_MINUTES,
+    vocabulary::{DEFAULT_LOCALE, is_supported},
+};
+
+/// The current backup format version; bump it when the format changes
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub settings: Option<StoredUserSettings>,
+    pub decks: Vec<BackupDeck>,
+    pub flashcards: Vec<BackupFlashcard>,
+    pub reviews: Vec<BackupReview>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupDeck {
+    pub id: i32,
+    pub name: String,
+    #[serde(default)]
+    pub answer_mode: AnswerMode,
+    #[serde(default, flatten)]
+    pub speech: DeckSpeech,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupFlashcard {
+    pub id: i32,
+    pub deck_id: i32,
+    pub front: String,
+    pub back: String,
+    pub last_rating: Option<CardRating>,
+    pub last_reviewed: Option<NaiveDateTime>,
+    pub last_scheduled: Option<NaiveDateTime>,
+    pub last_stability: Option<f32>,
+    pub last_difficulty: Option<f32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub suspended: bool,
+    #[serde(default)]
+    pub accepted_answers: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupReview {
+    pub flashcard_id: i32,
+    pub rating: CardRating,
+    pub reviewed: NaiveDateTime,
+    pub scheduled: NaiveDateTime,
+    pub stability: f32,
+    pub difficulty: f32,
+    #[serde(default)]
+    pub duration_ms: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RestoreSummary {
+    pub decks: usize,
+    pub flashcards: usize,
+    pub reviews: usize,
+    pub settings: bool,
+}
+
+impl RestoreSummary {
+    pub fn message(&self) -> String {
+        format!(
+            "Restored {} decks, {} flashcards and {} reviews{}.",
+            self.decks,
+            self.flashcards,
+            self.reviews,
+            if self.settings { " with settings" } else { "" }
+        )
+    }
+}
+
+impl From<Deck> for BackupDeck {
+    fn from(deck: Deck) -> Self {
+        Self {
+            id: deck.id,
+            name: deck.name,
+            answer_mode: deck.answer_mode,
+            speech: deck.speech,
+        }
+    }
+}
+
+impl From<Flashcard> for BackupFlashcard {
+    fn from(card: Flashcard) -> Self {
+        Self {
+            id: card.id,
+            deck_id: card.deck_id,
+            front: card.front,
+            back: card.back,
+            last_rating: card.last_rating,
+            last_reviewed: card.last_reviewed,
+            last_scheduled: card.last_scheduled,
+            last_stability: card.last_stability,
+            last_difficulty: card.last_difficulty,
+            tags: card.tags,
+            suspended: card.suspended,
+            accepted_answers: card.accepted_answers,
+        }
+    }
+}
+
+impl From<FlashcardReview> for BackupReview {
+    fn from(review: FlashcardReview) -> Self {
+        Self {
+            flashcard_id: review.flashcard_id,
+            rating: review.rating,
+            reviewed: review.reviewed,
+            scheduled: review.scheduled,
+            stability: review.stability,
+            difficulty: review.difficulty,
+            duration_ms: review.duration_ms,
+        }
+    }
+}
+
+pub async fn export_backup(pool: &PgPool, user_id: &str) -> Result<Backup, sqlx::Error> {
+    let settings = sqlx::query_as::<_, StoredUserSettings>(
+        r#"
+        SELECT max_cards_per_session, desired_retention, transcription_locale, resume_window_minutes,
+            short_press_action, long_press_action, double_press_action, head_up_action,
+            head_down_action
+        FROM user_settings WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let decks = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 ORDER BY id")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    let flashcards = sqlx::query_as::<_, Flashcard>(
+        r#"
+        SELECT f.* FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1
+        ORDER BY f.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let reviews = sqlx::query_as::<_, FlashcardReview>(
+        r#"
+        SELECT r.* FROM review_log r
+        INNER JOIN flashcard f ON r.flashcard_id = f.id
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1
+        ORDER BY r.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Backup {
+        version: BACKUP_VERSION,
+        exported_at: Utc::now().naive_utc(),
+        settings,
+        decks: decks.into_iter().map(Into::into).collect(),
+        flashcards: flashcards.into_iter().map(Into::into).collect(),
+        reviews: reviews.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// The items that refer to a restored deck or flashcard of the backup, with the id it was
+/// restored as; the others are skipped
+fn remap<T>(items: Vec<T>, ids: &HashMap<i32, i32>, old_id: impl Fn(&T) -> i32) -> Vec<(i32, T)> {
+    items
+        .into_iter()
+        .filter_map(|item| ids.get(&old_id(&item)).map(|new_id| (*new_id, item)))
+        .collect()
+}
+
+/// Restores a backup for the given user in a single transaction.
+/// Deck and flashcard ids from the backup are remapped to the ids in this instance;
+/// decks with the same name and cards with the same front are merged rather than duplicated.
+pub async fn restore_backup(
+    pool: &PgPool,
+    user_id: &str,
+    backup: Backup,
+) -> Result<RestoreSummary, sqlx::Error> {
+    let mut summary = RestoreSummary::default();
+    let mut tx = pool.begin().await?;
+
+    if let Some(settings) = backup.settings {
+        sqlx::query(
+            r#"
+            INSERT INTO user_settings
+                (user_id, max_cards_per_session, desired_retention, transcription_locale,
+                 resume_window_minutes, short_press_action, long_press_action,
+                 double_press_action, head_up_action, head_down_action)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (user_id) DO UPDATE
+            SET max_cards_per_session = EXCLUDED.max_cards_per_session,
+                desired_retention = EXCLUDED.desired_retention,
+                transcription_locale = EXCLUDED.transcription_locale,
+                resume_window_minutes = EXCLUDED.resume_window_minutes,
+                short_press_action = EXCLUDED.short_press_action,
+                long_press_action = EXCLUDED.long_press_action,
+                double_press_action = EXCLUDED.double_press_action,
+                head_up_action = EXCLUDED.head_up_action,
+                head_down_action = EXCLUDED.head_down_action
+            "#,
+        )
+        .bind(user_id)
+        .bind(settings.max_cards_per_session.clamp(1, 100))
+        .bind(settings.desired_retention.clamp(1, 100))
+        .bind(if is_supported(&settings.transcription_locale) {
+            settings.transcription_locale.as_str()
+        } else {
+            DEFAULT_LOCALE
+        })
+        .bind(
+            settings
+                .resume_window_minutes
+                .clamp(0, MAX_RESUME_WINDOW_MINUTES),
+        )
+        .bind(settings.input_mapping.short_press)
+        .bind(settings.input_mapping.long_press)
+        .bind(settings.input_mapping.double_press)
+        .bind(settings.input_mapping.head_up)
+        .bind(settings.input_mapping.head_down)
+        .execute(&mut *tx)
+        .await?;
+        summary.settings = true;
+    }
+
+    let mut deck_ids = HashMap::new();
+    for deck in backup.decks {
+        let new_id: i32 = sqlx::query_scalar(
+            r#"
+            INSERT INTO deck
+                (name, user_id, answer_mode, speak_front, speak_back, front_voice, front_language, back_voice, back_language)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (name, user_id) DO UPDATE
+            SET answer_mode = EXCLUDED.answer_mode,
+                speak_front = EXCLUDED.speak_front,
+                speak_back = EXCLUDED.speak_back,
+                front_voice = EXCLUDED.front_voice,
+                front_language = EXCLUDED.front_language,
+                back_voice = EXCLUDED.back_voice,
+                back_language = EXCLUDED.back_language
+            RETURNING id
+            "#,
+        )
+        .bind(deck.name)
+        .bind(user_id)
+        .bind(deck.answer_mode)
+        .bind(deck.speech.speak_front)
+        .bind(deck.speech.speak_back)
+        .bind(deck.speech.front_voice)
+        .bind(deck.speech.front_language)
+        .bind(deck.speech.back_voice)
+        .bind(deck.speech.back_language)
+        .fetch_one(&mut *tx)
+        .await?;
+        deck_ids.insert(deck.id, new_id);
+        summary.decks += 1;
+    }
+
+    let mut flashcard_ids = HashMap::new();
+    for (deck_id, card) in remap(backup.flashcards, &deck_ids, |card| card.deck_id) {
+        let new_id: i32 = sqlx::query_scalar(
+            r#"
+            INSERT INTO flashcard
+                (deck_id, front, back, last_rating, last_reviewed, last_scheduled, last_stability, last_difficulty, tags, suspended, accepted_answers)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (front, deck_id) DO UPDATE
+            SET back = EXCLUDED.back,
+                tags = EXCLUDED.tags,
+                suspended = EXCLUDED.suspended,
+                accepted_answers = EXCLUDED.accepted_answers,
+                last_rating = EXCLUDED.last_rating,
+                last_reviewed = EXCLUDED.last_reviewed,
+                last_scheduled = EXCLUDED.last_scheduled,
+                last_stability = EXCLUDED.last_stability,
+                last_difficulty = EXCLUDED.last_difficulty
+            RETURNING id
+            "#,
+        )
+        .bind(deck_id)
+        .bind(card.front)
+        .bind(card.back)
+        .bind(card.last_rating)
+        .bind(card.last_reviewed)
+        .bind(card.last_scheduled)
+        .bind(card.last_stability)
+        .bind(card.last_difficulty)
+        .bind(card.tags)
+        .bind(card.suspended)
+        .bind(card.accepted_answers)
+        .fetch_one(&mut *tx)
+        .await?;
+        flashcard_ids.insert(card.id, new_id);
+        summary.flashcards += 1;
+    }
+
+    for (flashcard_id, review) in
+        remap(backup.reviews, &flashcard_ids, |review| review.flashcard_id)
+    {
+        // Skip reviews that were already restored earlier
+        let result = sqlx::query(
+            r#"
+            INSERT INTO review_log
+                (flashcard_id, rating, reviewed, scheduled, stability, difficulty, duration_ms)
+            SELECT $1, $2, $3, $4, $5, $6, $7
+            WHERE NOT EXISTS (
+                SELECT 1 FROM review_log WHERE flashcard_id = $1 AND reviewed = $3
+            )
+            "#,
+        )
+        .bind(flashcard_id)
+        .bind(review.rating)
+        .bind(review.reviewed)
+        .bind(review.scheduled)
+        .bind(review.stability)
+        .bind(review.difficulty)
+        .bind(review.duration_ms)
+        .execute(&mut *tx)
+        .await?;
+        summary.reviews += result.rows_affected() as usize;
+    }
+
+    tx.commit().await?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use chrono::NaiveDate;
+
+    use super::{BACKUP_VERSION, Backup, RestoreSummary, remap};
+    use crate::models::{AnswerMode, CardRating, Deck, Flashcard, FlashcardReview};
+
+    #[test]
+    fn test_backup_round_trip() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let deck = Deck {
+            id: 7,
+            name: "Cantonese".to_string(),
+            user_id: "user".to_string(),
+            answer_mode: AnswerMode::Fuzzy,
+            speech: Default::default(),
+        };
+        let card = |id: i32, deck_id: i32| Flashcard {
+            id,
+            deck_id,
+            front: format!("front {id}"),
+            back: "back".to_string(),
+            last_rating: Some(CardRating::Good),
+            last_reviewed: Some(date),
+            last_scheduled: Some(date),
+            last_stability: Some(2.5),
+            last_difficulty: Some(5.0),
+            tags: vec!["verbs".to_string()],
+            suspended: false,
+            accepted_answers: None,
+        };
+        let review = |flashcard_id: i32| FlashcardReview {
+            id: 1,
+            flashcard_id,
+            rating: CardRating::Good,
+            reviewed: date,
+            scheduled: date,
+            stability: 2.5,
+            difficulty: 5.0,
+            duration_ms: Some(3_000),
+        };
+        let backup = Backup {
+            version: BACKUP_VERSION,
+            exported_at: date,
+            settings: None,
+            decks: vec![deck.into()],
+            // A card of a deck missing from the backup
+            flashcards: vec![card(11, 7).into(), card(12, 8).into()],
+            reviews: vec![review(11).into(), review(12).into()],
+        };
+        let json = serde_json::to_string(&backup).unwrap();
+        let backup: Backup = serde_json::from_str(&json).unwrap();
+        assert_eq!(backup.decks[0].answer_mode, AnswerMode::Fuzzy);
+
+        let deck_ids = HashMap::from([(7, 100)]);
+        let flashcards = remap(backup.flashcards, &deck_ids, |card| card.deck_id);
+        assert_eq!(flashcards.len(), 1);
+        let (deck_id, card) = &flashcards[0];
+        assert_eq!((*deck_id, card.id), (100, 11));
+        assert_eq!(card.tags, ["verbs"]);
+
+        let flashcard_ids = HashMap::from([(11, 200)]);
+        let reviews = remap(backup.reviews, &flashcard_ids, |review| review.flashcard_id);
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].0, 200);
+        assert_eq!(reviews[0].1.duration_ms, Some(3_000));
+    }
+
+    #[test]
+    fn test_restore_summary() {
+        let summary = RestoreSummary {
+            decks: 2,
+            flashcards: 30,
+            reviews: 120,
+            settings: true,
+        };
+        assert_eq!(
+            summary.message(),
+            "Restored 2 decks, 30 flashcards and 120 reviews with settings."
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! The card browser: all flashcards of a user in a table, filtered by scheduling state, rating,
+//! due date and difficulty, and sorted by any column.
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    models::{CardRating, Flashcard},
+    search::{CardState, empty_as_none},
+};
+
+/// Number of cards per page
+pub const PAGE_SIZE: u32 = 100;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortColumn {
+    Front,
+    Back,
+    Deck,
+    Rating,
+    Reviewed,
+    #[default]
+    Due,
+    Stability,
+    Difficulty,
+    /// Creation order
+    Added,
+}
+
+impl SortColumn {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortColumn::Front => "front",
+            SortColumn::Back => "back",
+            SortColumn::Deck => "deck",
+            SortColumn::Rating => "rating",
+            SortColumn::Reviewed => "reviewed",
+            SortColumn::Due => "due",
+            SortColumn::Stability => "stability",
+            SortColumn::Difficulty => "difficulty",
+            SortColumn::Added => "added",
+        }
+    }
+
+    /// The SQL expression to order by (never user input)
+    fn sql(&self) -> &'static str {
+        match self {
+            SortColumn::Front => "LOWER(f.front)",
+            SortColumn::Back => "LOWER(f.back)",
+            SortColumn::Deck => "LOWER(d.name)",
+            SortColumn::Rating => "f.last_rating",
+            SortColumn::Reviewed => "f.last_reviewed",
+            SortColumn::Due => "f.last_scheduled",
+            SortColumn::Stability => "f.last_stability",
+            SortColumn::Difficulty => "f.last_difficulty",
+            SortColumn::Added => "f.id",
+        }
+    }
+}
+
+impl Display for SortColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+
+    fn sql(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct BrowseQuery {
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub deck_id: Option<i32>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub state: Option<CardState>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub rating: Option<CardRating>,
+    /// First due date (inclusive)
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub due_from: Option<NaiveDate>,
+    /// Last due date (inclusive)
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub due_to: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub difficulty_min: Option<f32>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub difficulty_max: Option<f32>,
+    #[serde(default)]
+    pub sort: SortColumn,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Page number, starting at 0
+    #[serde(default)]
+    pub page: u32,
+}
+
+impl BrowseQuery {
+    pub fn is_deck(&self, deck_id: &i32) -> bool {
+        self.deck_id == Some(*deck_id)
+    }
+
+    pub fn is_state(&self, state: &CardState) -> bool {
+        self.state == Some(*state)
+    }
+
+    pub fn is_rating(&self, rating: &CardRating) -> bool {
+        self.rating == Some(*rating)
+    }
+
+    /// The arrow shown in the header of the sorted column
+    pub fn sort_indicator(&self, column: &SortColumn) -> &'static str {
+        match self.order {
+            _ if self.sort != *column => "",
+            SortOrder::Asc => "▲",
+            SortOrder::Desc => "▼",
+        }
+    }
+
+    /// The order after clicking a column header: toggled on the sorted column, else ascending
+    pub fn next_order(&self, column: &SortColumn) -> &'static str {
+        if self.sort == *column && self.order == SortOrder::Asc {
+            "desc"
+        } else {
+            "asc"
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct BrowseRow {
+    #[sqlx(flatten)]
+    pub flashcard: Flashcard,
+    pub deck_name: String,
+    /// Number of cards matching the filters (on all pages)
+    total: i64,
+}
+
+impl BrowseRow {
+    pub fn state(&self) -> CardState {
+        CardState::of(&self.flashcard)
+    }
+}
+
+#[derive(Debug)]
+pub struct BrowsePage {
+    pub rows: Vec<BrowseRow>,
+    pub page: u32,
+    pub total: i64,
+}
+
+impl BrowsePage {
+    pub fn has_more(&self) -> bool {
+        (i64::from(self.page) + 1) * i64::from(PAGE_SIZE) < self.total
+    }
+
+    /// The 1-based number of the first and last card on the page
+    pub fn range(&self) -> (i64, i64) {
+        let first = i64::from(self.page) * i64::from(PAGE_SIZE);
+        (first + 1, first + self.rows.len() as i64)
+    }
+}
+
+/// Lists the flashcards of the user matching the filters, in the requested order
+pub async fn browse_flashcards(
+    pool: &PgPool,
+    user_id: &str,
+    query: &BrowseQuery,
+) -> Result<BrowsePage, sqlx::Error> {
+    let state = query.state.map_or("TRUE", |state| state.condition());
+    let order = query.order.sql();
+    let rows = sqlx::query_as::<_, BrowseRow>(&format!(
+        r#"
+        SELECT f.*, d.name AS deck_name, COUNT(*) OVER () AS total
+        FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1
+            AND ($2::INTEGER IS NULL OR f.deck_id = $2)
+            AND ({state})
+            AND ($3::card_rating IS NULL OR f.last_rating = $3)
+            AND ($4::DATE IS NULL OR f.last_scheduled >= $4)
+            AND ($5::DATE IS NULL OR f.last_scheduled < $5 + 1)
+            AND ($6::REAL IS NULL OR f.last_difficulty >= $6)
+            AND ($7::REAL IS NULL OR f.last_difficulty <= $7)
+        ORDER BY {sort} {order} NULLS LAST, f.id {order}
+        LIMIT $8 OFFSET $9
+        "#,
+        sort = query.sort.sql(),
+    ))
+    .bind(user_id)
+    .bind(query.deck_id)
+    .bind(query.rating)
+    .bind(query.due_from)
+    .bind(query.due_to)
+    .bind(query.difficulty_min)
+    .bind(query.difficulty_max)
+    .bind(i64::from(PAGE_SIZE))
+    .bind(i64::from(query.page) * i64::from(PAGE_SIZE))
+    .fetch_all(pool)
+    .await?;
+
+    let total = rows.first().map_or(0, |row| row.total);
+    Ok(BrowsePage {
+        rows,
+        page: query.page,
+        total,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use axum::extract::Query;
+
+    use super::{BrowseQuery, SortColumn, SortOrder};
+    use crate::{models::CardRating, search::CardState};
+
+    #[test]
+    fn test_browse_query() {
+        let uri = "/browse?deck_id=&state=overdue&rating=again&due_from=2026-01-31&due_to=\
+            &difficulty_min=5.5&difficulty_max=&sort=difficulty&order=desc"
+            .parse()
+            .unwrap();
+        let Query(query) = Query::<BrowseQuery>::try_from_uri(&uri).unwrap();
+        assert_eq!(query.deck_id, None);
+        assert_eq!(query.state, Some(CardState::Overdue));
+        assert_eq!(query.rating, Some(CardRating::Again));
+        assert_eq!(query.due_from.unwrap().to_string(), "2026-01-31");
+        assert_eq!(query.due_to, None);
+        assert_eq!(query.difficulty_min, Some(5.5));
+        assert_eq!(query.sort, SortColumn::Difficulty);
+        assert_eq!(query.order, SortOrder::Desc);
+        assert_eq!(query.next_order(&SortColumn::Difficulty), "asc");
+        assert_eq!(query.next_order(&SortColumn::Front), "asc");
+
+        let Query(query) = Query::<BrowseQuery>::try_from_uri(&"/browse".parse().unwrap()).unwrap();
+        assert_eq!(query.sort, SortColumn::Due);
+        assert_eq!(query.next_order(&SortColumn::Due), "desc");
+    }
+}
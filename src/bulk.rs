@@ -0,0 +1,239 @@
+//! Bulk operations on flashcards selected in the card browser. Every operation runs in one
+//! transaction and only touches the cards of the user.
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{errors::ApiError, models::parse_tags};
+
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    /// Move the cards to `deck_id`
+    Move,
+    Delete,
+    /// Forget the scheduling state, keeping the review history
+    Reset,
+    Suspend,
+    Unsuspend,
+    /// Add the space-separated `tags`
+    AddTags,
+    /// Remove the space-separated `tags`
+    RemoveTags,
+}
+
+impl BulkAction {
+    /// The past tense, for the summary
+    fn done(&self) -> &'static str {
+        match self {
+            BulkAction::Move => "Moved",
+            BulkAction::Delete => "Deleted",
+            BulkAction::Reset => "Reset",
+            BulkAction::Suspend => "Suspended",
+            BulkAction::Unsuspend => "Unsuspended",
+            BulkAction::AddTags => "Tagged",
+            BulkAction::RemoveTags => "Untagged",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct BulkRequest {
+    pub action: BulkAction,
+    /// The selected flashcards
+    #[serde(default)]
+    pub ids: Vec<i32>,
+    /// The target deck of `move`
+    #[serde(default)]
+    pub deck_id: Option<i32>,
+    /// Space-separated tags for `add_tags` and `remove_tags`
+    #[serde(default)]
+    pub tags: String,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct BulkSummary {
+    pub action: BulkAction,
+    /// Number of changed cards
+    pub affected: u64,
+    /// Selected cards that were not changed: not found, or already in the target deck
+    /// with the same front
+    pub skipped: u64,
+    /// The target deck name of `move`
+    pub deck_name: Option<String>,
+}
+
+impl BulkSummary {
+    pub fn message(&self) -> String {
+        let cards = if self.affected == 1 { "card" } else { "cards" };
+        let mut message = format!("{} {} {cards}", self.action.done(), self.affected);
+        if let Some(deck_name) = &self.deck_name {
+            message.push_str(&format!(" to \"{deck_name}\""));
+        }
+        if self.skipped > 0 {
+            message.push_str(&format!(", skipped {}", self.skipped));
+        }
+        message
+    }
+}
+
+/// Applies the action to the selected cards of the user in one transaction
+pub async fn apply_bulk(
+    pool: &PgPool,
+    user_id: &str,
+    request: &BulkRequest,
+) -> Result<BulkSummary, ApiError> {
+    if request.ids.is_empty() {
+        return Err(ApiError::BadRequest("No flashcards selected".to_string()));
+    }
+    let tags = parse_tags(&request.tags);
+    if matches!(request.action, BulkAction::AddTags | BulkAction::RemoveTags) && tags.is_empty() {
+        return Err(ApiError::BadRequest("No tags given".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    // Only the cards of the user, which all statements below are restricted to
+    let ids: Vec<i32> = sqlx::query_scalar(
+        r#"
+        SELECT f.id FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1 AND f.id = ANY($2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(&request.ids)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut deck_name = None;
+    let result = match request.action {
+        BulkAction::Move => {
+            let deck_id = request
+                .deck_id
+                .ok_or_else(|| ApiError::BadRequest("No target deck given".to_string()))?;
+            let name: String =
+                sqlx::query_scalar("SELECT name FROM deck WHERE id = $1 AND user_id = $2")
+                    .bind(deck_id)
+                    .bind(user_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            deck_name = Some(name);
+            // Fronts are unique per deck, so cards whose front is already in the target deck stay
+            sqlx::query(
+                r#"
+                UPDATE flashcard SET deck_id = $2
+                WHERE id IN (
+                    SELECT DISTINCT ON (f.front) f.id FROM flashcard f
+                    WHERE f.id = ANY($1) AND f.deck_id <> $2
+                        AND NOT EXISTS (
+                            SELECT 1 FROM flashcard t WHERE t.deck_id = $2 AND t.front = f.front
+                        )
+                    ORDER BY f.front, f.id
+                )
+                "#,
+            )
+            .bind(&ids)
+            .bind(deck_id)
+            .execute(&mut *tx)
+            .await?
+        }
+        BulkAction::Delete => {
+            sqlx::query("DELETE FROM flashcard WHERE id = ANY($1)")
+                .bind(&ids)
+                .execute(&mut *tx)
+                .await?
+        }
+        BulkAction::Reset => {
+            sqlx::query(
+                r#"
+                UPDATE flashcard
+                SET last_rating = NULL, last_reviewed = NULL, last_scheduled = NULL,
+                    last_stability = NULL, last_difficulty = NULL
+                WHERE id = ANY($1)
+                "#,
+            )
+            .bind(&ids)
+            .execute(&mut *tx)
+            .await?
+        }
+        BulkAction::Suspend | BulkAction::Unsuspend => {
+            sqlx::query("UPDATE flashcard SET suspended = $2 WHERE id = ANY($1)")
+                .bind(&ids)
+                .bind(request.action == BulkAction::Suspend)
+                .execute(&mut *tx)
+                .await?
+        }
+        BulkAction::AddTags => {
+            // Appends the new tags, keeping the order and dropping duplicates
+            sqlx::query(
+                r#"
+                UPDATE flashcard
+                SET tags = ARRAY(
+                    SELECT tag FROM UNNEST(tags || $2::TEXT[]) WITH ORDINALITY AS t(tag, n)
+                    GROUP BY tag ORDER BY MIN(n)
+                )
+                WHERE id = ANY($1)
+                "#,
+            )
+            .bind(&ids)
+            .bind(&tags)
+            .execute(&mut *tx)
+            .await?
+        }
+        BulkAction::RemoveTags => {
+            sqlx::query(
+                r#"
+                UPDATE flashcard
+                SET tags = ARRAY(SELECT tag FROM UNNEST(tags) AS tag WHERE tag <> ALL($2))
+                WHERE id = ANY($1) AND tags && $2
+                "#,
+            )
+            .bind(&ids)
+            .bind(&tags)
+            .execute(&mut *tx)
+            .await?
+        }
+    };
+    tx.commit().await?;
+
+    let affected = result.rows_affected();
+    Ok(BulkSummary {
+        action: request.action,
+        affected,
+        skipped: (request.ids.len() as u64).saturating_sub(affected),
+        deck_name,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::Body,
+        extract::{FromRequest, Request},
+        http::header::CONTENT_TYPE,
+    };
+    use axum_extra::extract::Form;
+
+    use super::{BulkAction, BulkRequest, BulkSummary};
+
+    #[tokio::test]
+    async fn test_bulk_request() {
+        let request = Request::post("/browse/bulk")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from("action=add_tags&ids=3&ids=5&tags=verbs+hsk1"))
+            .unwrap();
+        let Form(request) = Form::<BulkRequest>::from_request(request, &())
+            .await
+            .unwrap();
+        assert_eq!(request.action, BulkAction::AddTags);
+        assert_eq!(request.ids, [3, 5]);
+
+        let summary = BulkSummary {
+            action: BulkAction::Move,
+            affected: 1,
+            skipped: 2,
+            deck_name: Some("Verbs".to_string()),
+        };
+        assert_eq!(summary.message(), "Moved 1 card to \"Verbs\", skipped 2");
+    }
+}
@@ -0,0 +1,45 @@
+//! In-process feed of changes to the cards, so that the sessions on the glasses don't show
+//! stale or deleted cards after edits in the webview or the API
+use tokio::sync::broadcast;
+
+use crate::router::AppState;
+
+/// Number of changes buffered for a session before it misses some
+pub const CHANGE_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CardChange {
+    /// Cards were edited, moved, rescheduled, suspended or deleted
+    Cards { user_id: String, ids: Vec<i32> },
+    /// A deck was renamed or deleted
+    Deck { user_id: String, deck_id: i32 },
+}
+
+impl CardChange {
+    pub fn user_id(&self) -> &str {
+        match self {
+            CardChange::Cards { user_id, .. } | CardChange::Deck { user_id, .. } => user_id,
+        }
+    }
+}
+
+pub fn card_changes() -> broadcast::Sender<CardChange> {
+    broadcast::Sender::new(CHANGE_CAPACITY)
+}
+
+impl AppState {
+    pub fn notify_cards(&self, user_id: &str, ids: Vec<i32>) {
+        // Fails only when no session is listening
+        let _ = self.card_changes.send(CardChange::Cards {
+            user_id: user_id.to_string(),
+            ids,
+        });
+    }
+
+    pub fn notify_deck(&self, user_id: &str, deck_id: i32) {
+        let _ = self.card_changes.send(CardChange::Deck {
+            user_id: user_id.to_string(),
+            deck_id,
+        });
+    }
+}
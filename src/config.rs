@@ -10,4 +10,6 @@ pub struct AppConfig {
     pub user_token_public_key: String,
     pub cloud_api_url: String,
     pub cloud_domain: String,
+    /// Maximum size of uploaded import files in bytes
+    pub max_upload_bytes: usize,
 }
@@ -7,11 +7,17 @@ pub enum ApiError {
     HTTPError(axum::http::Error),
     TemplateError(askama::Error),
     UserNotFoundOrUnauthorized,
+    BadRequest(String),
+    SchedulingError(anyhow::Error),
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         match self {
+            // Rows are looked up with the user id, so a missing row may also be another user's
+            Self::SQLError(sqlx::Error::RowNotFound) => {
+                (StatusCode::NOT_FOUND, "Not found".to_string()).into_response()
+            }
             Self::SQLError(e) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, format!("SQL error: {e}")).into_response()
             }
@@ -30,6 +36,12 @@ impl IntoResponse for ApiError {
                 "User not found or unauthorized".to_string(),
             )
                 .into_response(),
+            Self::BadRequest(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+            Self::SchedulingError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Scheduling error: {e}"),
+            )
+                .into_response(),
         }
     }
 }
@@ -0,0 +1,309 @@
+//! Conversion of (Anki-exported) card HTML into glasses-safe plain text,
+//! and sanitization of the rich version shown in the webview.
+
+/// Tags that are kept (without attributes) when rendering cards in the webview
+const ALLOWED_TAGS: &[&str] = &[
+    "b", "strong", "i", "em", "u", "s", "sub", "sup", "br", "ruby", "rt", "rp", "p", "div", "ul",
+    "ol", "li",
+];
+
+/// Tags that end a line when rendered as plain text
+const BLOCK_TAGS: &[&str] = &[
+    "p", "div", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "table",
+];
+
+/// Tags whose content is never displayed
+const SKIPPED_CONTENT_TAGS: &[&str] = &["script", "style", "rp"];
+
+const VOID_TAGS: &[&str] = &[
+    "br", "img", "hr", "audio", "video", "source", "input", "wbr",
+];
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Text(&'a str),
+    Tag { name: String, closing: bool },
+}
+
+/// Splits the input into text runs and tags. A `<` that doesn't start a tag
+/// (e.g. "a < b") is kept as text.
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    let bytes = input.as_bytes();
+    while i < bytes.len() {
+        if bytes[i] == b'<'
+            && let Some(next) = bytes.get(i + 1)
+            && (next.is_ascii_alphabetic() || *next == b'/' || *next == b'!')
+            && let Some(end) = input[i..].find('>')
+        {
+            if text_start < i {
+                tokens.push(Token::Text(&input[text_start..i]));
+            }
+            let inner = &input[i + 1..i + end];
+            let closing = inner.starts_with('/');
+            let name = inner
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            // Comments and doctypes are dropped
+            if !name.starts_with('!') {
+                tokens.push(Token::Tag { name, closing });
+            }
+            i += end + 1;
+            text_start = i;
+        } else {
+            i += 1;
+        }
+    }
+    if text_start < input.len() {
+        tokens.push(Token::Text(&input[text_start..]));
+    }
+    tokens
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(num) = entity.strip_prefix('#') {
+        let code = if let Some(hex) = num.strip_prefix('x').or(num.strip_prefix('X')) {
+            u32::from_str_radix(hex, 16).ok()?
+        } else {
+            num.parse().ok()?
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "nbsp" | "ensp" | "emsp" | "thinsp" => ' ',
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "laquo" => '«',
+        "raquo" => '»',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "middot" => '·',
+        "times" => '×',
+        "divide" => '÷',
+        "deg" => '°',
+        "copy" => '©',
+        "reg" => '®',
+        _ => return None,
+    })
+}
+
+/// Decodes HTML character references; unknown or malformed ones are left as they are
+pub fn decode_entities(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let decoded = after
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&after[..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Removes Anki media references such as `[sound:hello.mp3]`
+fn strip_media_references(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("[sound:") {
+        result.push_str(&rest[..start]);
+        match rest[start..].find(']') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn escape(text: &str, output: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            '\n' => output.push_str("<br>"),
+            _ => output.push(c),
+        }
+    }
+}
+
+/// Converts card HTML into plain text for the glasses display:
+/// line breaks and block elements become newlines, entities are decoded,
+/// ruby annotations are shown in parentheses and media references are dropped.
+pub fn to_display_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut skip_depth = 0usize;
+    for token in tokenize(html) {
+        match token {
+            Token::Text(t) => {
+                if skip_depth == 0 {
+                    text.push_str(&decode_entities(&strip_media_references(t)));
+                }
+            }
+            Token::Tag { name, closing } => {
+                if SKIPPED_CONTENT_TAGS.contains(&name.as_str()) {
+                    skip_depth = if closing {
+                        skip_depth.saturating_sub(1)
+                    } else {
+                        skip_depth + 1
+                    };
+                } else if name == "br" || BLOCK_TAGS.contains(&name.as_str()) {
+                    text.push('\n');
+                } else if name == "rt" {
+                    text.push(if closing { ')' } else { '(' });
+                } else if name == "td" || name == "th" {
+                    text.push(' ');
+                }
+            }
+        }
+    }
+
+    // Collapse whitespace within lines and drop blank lines
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Sanitizes card HTML for the webview: only a small set of formatting tags
+/// (without attributes) is kept, everything else is escaped or dropped.
+pub fn sanitize(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut skip_depth = 0usize;
+    for token in tokenize(html) {
+        match token {
+            Token::Text(t) => {
+                if skip_depth == 0 {
+                    escape(&decode_entities(t), &mut output);
+                }
+            }
+            Token::Tag { name, closing } => {
+                if name == "script" || name == "style" {
+                    skip_depth = if closing {
+                        skip_depth.saturating_sub(1)
+                    } else {
+                        skip_depth + 1
+                    };
+                } else if skip_depth == 0 && ALLOWED_TAGS.contains(&name.as_str()) {
+                    if !closing {
+                        output.push_str(&format!("<{name}>"));
+                    } else if !VOID_TAGS.contains(&name.as_str()) {
+                        output.push_str(&format!("</{name}>"));
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Escapes plain text for the webview, marking the case-insensitive occurrences
+/// of the search terms with `<mark>`
+pub fn highlight(text: &str, terms: &[String]) -> String {
+    // Lowercased copy of the text, with the offset in `text` of each of its bytes
+    let mut lower = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    for (i, c) in text.char_indices() {
+        let start = lower.len();
+        lower.extend(c.to_lowercase());
+        offsets.resize(offsets.len() + lower.len() - start, i);
+    }
+    offsets.push(text.len());
+
+    let mut ranges = Vec::new();
+    for term in terms {
+        let term = term.to_lowercase();
+        if term.is_empty() {
+            continue;
+        }
+        for (start, _) in lower.match_indices(&term) {
+            let (start, end) = (offsets[start], offsets[start + term.len()]);
+            if start < end {
+                ranges.push((start, end));
+            }
+        }
+    }
+    ranges.sort_unstable();
+
+    let mut output = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (start, end) in ranges {
+        // Overlapping matches are merged into the previous mark
+        if end <= pos {
+            continue;
+        }
+        let start = start.max(pos);
+        escape(&text[pos..start], &mut output);
+        if start == pos && output.ends_with("</mark>") {
+            output.truncate(output.len() - "</mark>".len());
+        } else {
+            output.push_str("<mark>");
+        }
+        escape(&text[start..end], &mut output);
+        output.push_str("</mark>");
+        pos = end;
+    }
+    escape(&text[pos..], &mut output);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_display_text() {
+        let html = "Hello&nbsp;<b>world</b><br>second&amp;line<div>third [sound:a.mp3]</div>\
+            <ruby>漢<rp>(</rp><rt>kan</rt><rp>)</rp></ruby> a < b";
+        assert_eq!(
+            super::to_display_text(html),
+            "Hello world\nsecond&line\nthird\n漢(kan) a < b"
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let html =
+            r#"<b onclick="x()">bold</b><script>alert(1)</script><img src=x onerror=y>1 &lt; 2"#;
+        assert_eq!(super::sanitize(html), "<b>bold</b>1 &lt; 2");
+    }
+
+    #[test]
+    fn test_highlight() {
+        let terms = ["dan".to_string(), "ANCE".to_string(), "跳".to_string()];
+        assert_eq!(
+            super::highlight("He Dances <b>\n佢跳舞", &terms),
+            "He <mark>Dance</mark>s &lt;b&gt;<br>佢<mark>跳</mark>舞"
+        );
+    }
+}
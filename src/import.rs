@@ -1,101 +1,256 @@
-//! Module for importing flashcards from Anki exported text files into the database.
-//! ref: https://docs.ankiweb.net/exporting.html#text-files
-use std::collections::HashMap;
-
-use anyhow::Result;
-use sqlx::PgPool;
-
-fn import_anki_text(front_idx: usize, back_idx: usize, file: String) -> HashMap<String, String> {
-    let lines = file.lines();
-    let mut separator = '\t';
-    let sep = "#separator:";
-    let mut flashcards = HashMap::new();
-    for line in lines {
-        if line.starts_with('#') {
-            if line.starts_with(sep) {
-                let trimmed = line.trim_start_matches(sep);
-                /*
-                Comma, Semicolon, Tab, Space, Pipe, Colon, or the according literal characters
-                */
-                if trimmed.starts_with("tab") {
-                    separator = '\t';
-                } else if trimmed.starts_with("comma") {
-                    separator = ',';
-                } else if trimmed.starts_with("semicolon") {
-                    separator = ';';
-                } else if trimmed.starts_with("space") {
-                    separator = ' ';
-                } else if trimmed.starts_with("pipe") {
-                    separator = '|';
-                } else if trimmed.starts_with("colon") {
-                    separator = ':';
-                } else if trimmed.starts_with("'") {
-                    separator = trimmed.chars().nth(1).unwrap_or('\t');
-                }
-            }
-            continue;
-        } else if line.trim().is_empty() {
-            continue; // Skip empty lines
+//! Module for importing flashcards into the database from Anki exports and other formats
+//! (Markdown notes, Quizlet and Mnemosyne exports).
+//! All formats are parsed by an `Importer` into the same list of `ImportedCard`s,
+//! which are then stored by a background import job.
+mod anki;
+mod apkg;
+mod decode;
+mod job;
+mod markdown;
+mod mnemosyne;
+mod quizlet;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub(crate) use anki::AnkiTextParser;
+pub(crate) use apkg::read_apkg_notes;
+pub(crate) use decode::StreamingDecoder;
+pub(crate) use job::{cancel_import_job, fail_interrupted_jobs, start_import_job};
+
+/// Number of lines used for detecting the format and previewing an import
+pub(crate) const PREVIEW_LINES: usize = 64;
+
+/// Number of cards shown in an import preview
+const PREVIEW_CARDS: usize = 5;
+
+/// A card parsed from an import, before it's stored
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportedCard {
+    pub front: String,
+    pub back: String,
+    /// The deck named in the import (e.g. by a Markdown heading); the target deck if `None`
+    pub deck: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ImportedCard {
+    pub fn new(front: String, back: String) -> Self {
+        Self {
+            front,
+            back,
+            deck: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn in_deck(mut self, deck: Option<String>) -> Self {
+        self.deck = deck;
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+}
+
+/// Common interface of the import parsers: they are fed the input one line at a time
+/// (so that uploads can be parsed while streaming) and return the parsed cards at the end
+pub(crate) trait Importer: Send {
+    fn feed_line(&mut self, line: &str);
+    fn finish(self: Box<Self>) -> Vec<ImportedCard>;
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Anki,
+    Markdown,
+    Quizlet,
+    Mnemosyne,
+}
+
+impl ImportFormat {
+    /// Parses the `format` form value; "auto" (or an unknown value) means detection
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "anki" => Some(Self::Anki),
+            "markdown" => Some(Self::Markdown),
+            "quizlet" => Some(Self::Quizlet),
+            "mnemosyne" => Some(Self::Mnemosyne),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the first lines of the input, defaulting to Anki
+    pub fn detect(sample: &str) -> Self {
+        if mnemosyne::detect(sample) {
+            Self::Mnemosyne
+        } else if anki::detect(sample) {
+            Self::Anki
+        } else if markdown::detect(sample) {
+            Self::Markdown
+        } else if quizlet::detect(sample) {
+            Self::Quizlet
         } else {
-            let parts = line.split(separator);
-            let mut front = None;
-            let mut back = None;
-            for (i, part) in parts.enumerate() {
-                if i == front_idx {
-                    front = Some(part.trim().to_string());
-                } else if i == back_idx {
-                    back = Some(part.trim().to_string());
-                }
-                if i > back_idx && i > front_idx {
-                    break;
-                }
-            }
-            if let (Some(front), Some(back)) = (front, back) {
-                flashcards.insert(front, back);
-            }
+            Self::Anki
         }
     }
 
-    flashcards
+    /// The field indices are only used by the Anki importer
+    pub fn importer(self, front_idx: usize, back_idx: usize) -> Box<dyn Importer> {
+        match self {
+            Self::Anki => Box::new(AnkiTextParser::new(front_idx, back_idx)),
+            Self::Markdown => Box::<markdown::MarkdownParser>::default(),
+            Self::Quizlet => Box::<quizlet::QuizletParser>::default(),
+            Self::Mnemosyne => Box::<mnemosyne::MnemosyneParser>::default(),
+        }
+    }
 }
 
-pub async fn import_anki_text_to_db(
-    pool: &PgPool,
-    deck_id: i32,
+/// What an import would produce: the fields of the first row (for choosing
+/// the front and back fields of Anki exports) and the first parsed cards
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportPreview {
+    pub format: ImportFormat,
+    pub fields: Vec<String>,
+    pub cards: Vec<ImportedCard>,
+}
+
+impl ImportPreview {
+    pub fn new(format: Option<ImportFormat>, lines: &[String]) -> Self {
+        let format = format.unwrap_or_else(|| ImportFormat::detect(&lines.join("\n")));
+        let mut anki = AnkiTextParser::new(0, 1);
+        let fields = if format == ImportFormat::Anki {
+            lines
+                .iter()
+                .find_map(|line| anki.split_line(line))
+                .unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let mut importer = format.importer(0, 1);
+        for line in lines {
+            importer.feed_line(line);
+        }
+        let mut cards = importer.finish();
+        cards.truncate(PREVIEW_CARDS);
+        Self {
+            format,
+            fields,
+            cards,
+        }
+    }
+}
+
+/// Parses a streamed input line by line, detecting the format from the first lines if needed
+pub(crate) struct StreamingImport {
+    format: Option<ImportFormat>,
     front_idx: usize,
     back_idx: usize,
-    file: String,
-) -> Result<(), sqlx::Error> {
-    let flashcards = import_anki_text(front_idx, back_idx, file);
-    if flashcards.is_empty() {
-        return Ok(());
-    } else {
-        let mut tx = pool.begin().await?;
-        for (front, back) in flashcards {
-            sqlx::query("INSERT INTO flashcard (deck_id, front, back) VALUES ($1, $2, $3)")
-                .bind(deck_id)
-                .bind(front)
-                .bind(back)
-                .execute(&mut *tx)
-                .await?;
+    buffered: Vec<String>,
+    importer: Option<Box<dyn Importer>>,
+}
+
+impl StreamingImport {
+    pub fn new(format: Option<ImportFormat>, front_idx: usize, back_idx: usize) -> Self {
+        Self {
+            format,
+            front_idx,
+            back_idx,
+            buffered: Vec::new(),
+            importer: None,
+        }
+    }
+
+    fn start(&mut self) {
+        let lines = std::mem::take(&mut self.buffered);
+        let format = self
+            .format
+            .unwrap_or_else(|| ImportFormat::detect(&lines.join("\n")));
+        let importer = self
+            .importer
+            .insert(format.importer(self.front_idx, self.back_idx));
+        for line in &lines {
+            importer.feed_line(line);
+        }
+    }
+
+    pub fn feed_line(&mut self, line: String) {
+        if let Some(importer) = self.importer.as_mut() {
+            importer.feed_line(&line);
+        } else {
+            self.buffered.push(line);
+            if self.buffered.len() >= PREVIEW_LINES {
+                self.start();
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<ImportedCard> {
+        match self.importer.take() {
+            Some(importer) => importer.finish(),
+            None => {
+                self.start();
+                self.importer
+                    .take()
+                    .map(|importer| importer.finish())
+                    .unwrap_or_default()
+            }
         }
-        tx.commit().await?;
     }
-    Ok(())
+}
+
+/// Parses a whole (pasted) text
+pub fn parse_text(
+    format: Option<ImportFormat>,
+    front_idx: usize,
+    back_idx: usize,
+    text: &str,
+) -> Vec<ImportedCard> {
+    let mut import = StreamingImport::new(format, front_idx, back_idx);
+    for line in text.lines() {
+        import.feed_line(line.to_string());
+    }
+    import.finish()
 }
 
 #[cfg(test)]
 mod test {
+    use super::ImportFormat;
+
     #[test]
-    fn test_importer() {
-        let sample = r#"#separator:tab
-#html:false
-.	Come on!	[Cc]ome +on[ \!]?		嚟 啦 。	嚟 啦 。	le̖i lā.	lei4 laa1.	leih4 la1.	lei4 laa1.	((嚟|[o口]黎|來)|[Ll]ei4?|[Ll]eih4?)\W*(啦|[Ll]aa1?|[Ll]a1?)\W*			come !	lei4 laa1. / leih4 la1.	le̖ʲ lāː 	1
-.	He dances.	[Hh]e +dances[ \.]?		佢 跳舞 。	佢 跳舞 。	kö̗ü tiu̟ mo̗u.	keoi5 tiu3 mou5.	keuih5 tiu3 mouh5.	keoi5 tiu3 mou5.	((佢|人巨|他)|[Kk]eoi5?|[Kk]euih5?)\W*(跳|[Tt]iu3?)\W*(舞|[Mm]ou5?|[Mm]ouh5?)\W*			s/he dance	keoi5 tiu3 mou5. / keuih5 tiu3 mouh5.	kʰø̗ᶣ tʰi̟ːʷ mo̗ʷ 	2"#;
-
-        let cards = super::import_anki_text(1, 7, sample.to_string());
-        assert_eq!(cards.len(), 2);
-        assert_eq!(cards["Come on!"], "lei4 laa1.");
-        assert_eq!(cards["He dances."], "keoi5 tiu3 mou5.");
+    fn test_detect() {
+        assert_eq!(
+            ImportFormat::detect("#separator:tab\na\tb"),
+            ImportFormat::Anki
+        );
+        assert_eq!(ImportFormat::detect("a\tb\tc"), ImportFormat::Anki);
+        assert_eq!(
+            ImportFormat::detect("# Deck\na :: b"),
+            ImportFormat::Markdown
+        );
+        assert_eq!(
+            ImportFormat::detect("casa::house\nperro::dog"),
+            ImportFormat::Markdown
+        );
+        assert_eq!(ImportFormat::detect("a\tb\nc\td"), ImportFormat::Quizlet);
+        assert_eq!(
+            ImportFormat::detect("casa,house\nperro,dog\ngato,cat"),
+            ImportFormat::Quizlet
+        );
+        assert_eq!(
+            ImportFormat::detect("to run\tpaau2 bou6\n(jogging)\nto dance\ttiu3 mou5"),
+            ImportFormat::Quizlet
+        );
+        assert_eq!(
+            ImportFormat::detect("vector\tstd::vector\nmap\tstd::map"),
+            ImportFormat::Quizlet
+        );
+        assert_eq!(
+            ImportFormat::detect("<?xml version=\"1.0\"?>\n<mnemosyne core_version=\"1\">"),
+            ImportFormat::Mnemosyne
+        );
     }
 }
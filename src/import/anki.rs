@@ -0,0 +1,179 @@
+//! Importer for Anki exported text files.
+//! ref: https://docs.ankiweb.net/exporting.html#text-files
+use crate::{
+    html::sanitize,
+    import::{ImportedCard, Importer},
+    models::parse_tags,
+};
+
+/// Incremental parser for Anki text exports, fed one line at a time
+pub(crate) struct AnkiTextParser {
+    front_idx: usize,
+    back_idx: usize,
+    separator: char,
+    is_html: bool,
+    /// Index of the tags field (the `#tags column:` header is 1-based)
+    tags_idx: Option<usize>,
+    flashcards: Vec<ImportedCard>,
+}
+
+impl AnkiTextParser {
+    pub fn new(front_idx: usize, back_idx: usize) -> Self {
+        Self {
+            front_idx,
+            back_idx,
+            separator: '\t',
+            is_html: false,
+            tags_idx: None,
+            flashcards: Vec::new(),
+        }
+    }
+
+    /// Handles header lines and returns the fields of a data line
+    pub fn split_line(&mut self, line: &str) -> Option<Vec<String>> {
+        let sep = "#separator:";
+        if line.starts_with('#') {
+            if let Some(value) = line.strip_prefix("#html:") {
+                self.is_html = value.trim() == "true";
+            } else if let Some(value) = line.strip_prefix("#tags column:") {
+                self.tags_idx = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|column| column.checked_sub(1));
+            } else if line.starts_with(sep) {
+                let trimmed = line.trim_start_matches(sep);
+                /*
+                Comma, Semicolon, Tab, Space, Pipe, Colon, or the according literal characters
+                */
+                if trimmed.starts_with("tab") {
+                    self.separator = '\t';
+                } else if trimmed.starts_with("comma") {
+                    self.separator = ',';
+                } else if trimmed.starts_with("semicolon") {
+                    self.separator = ';';
+                } else if trimmed.starts_with("space") {
+                    self.separator = ' ';
+                } else if trimmed.starts_with("pipe") {
+                    self.separator = '|';
+                } else if trimmed.starts_with("colon") {
+                    self.separator = ':';
+                } else if trimmed.starts_with("'") {
+                    self.separator = trimmed.chars().nth(1).unwrap_or('\t');
+                }
+            }
+            None
+        } else if line.trim().is_empty() {
+            None // Skip empty lines
+        } else {
+            Some(
+                line.split(self.separator)
+                    .map(|part| part.trim().to_string())
+                    .collect(),
+            )
+        }
+    }
+
+    /// Adds a card from a row of fields, if it has both the front and back field
+    pub fn add_fields(&mut self, mut fields: Vec<String>) {
+        if self.front_idx >= fields.len()
+            || self.back_idx >= fields.len()
+            || self.front_idx == self.back_idx
+        {
+            return;
+        }
+        let tags = self
+            .tags_idx
+            .and_then(|idx| fields.get(idx))
+            .map(|tags| parse_tags(tags))
+            .unwrap_or_default();
+        let back = std::mem::take(&mut fields[self.back_idx]);
+        let front = std::mem::take(&mut fields[self.front_idx]);
+        let card = if self.is_html {
+            // Keep the rich version (for the webview) without unsafe markup
+            ImportedCard::new(sanitize(&front), sanitize(&back))
+        } else {
+            ImportedCard::new(front, back)
+        };
+        self.flashcards.push(card.with_tags(tags));
+    }
+
+    /// Treat the fields as HTML (e.g. for Anki packages, where notes are always HTML)
+    pub fn set_html(&mut self, is_html: bool) {
+        self.is_html = is_html;
+    }
+}
+
+impl Importer for AnkiTextParser {
+    fn feed_line(&mut self, line: &str) {
+        if let Some(fields) = self.split_line(line) {
+            self.add_fields(fields);
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Vec<ImportedCard> {
+        self.flashcards
+    }
+}
+
+/// Whether the text has Anki export headers or more columns than a plain two-sided list
+pub(super) fn detect(sample: &str) -> bool {
+    let mut lines = sample.lines().filter(|line| !line.trim().is_empty());
+    lines.clone().any(|line| {
+        line.starts_with("#separator:")
+            || line.starts_with("#html:")
+            || line.starts_with("#notetype")
+    }) || lines
+        .next()
+        .is_some_and(|line| line.split('\t').count() > 2)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::import::Importer;
+
+    fn parse(front_idx: usize, back_idx: usize, text: &str) -> HashMap<String, String> {
+        let mut parser = Box::new(super::AnkiTextParser::new(front_idx, back_idx));
+        for line in text.lines() {
+            parser.feed_line(line);
+        }
+        parser
+            .finish()
+            .into_iter()
+            .map(|card| (card.front, card.back))
+            .collect()
+    }
+
+    #[test]
+    fn test_importer() {
+        let sample = r#"#separator:tab
+#html:false
+.	Come on!	[Cc]ome +on[ \!]?		嚟 啦 。	嚟 啦 。	le̖i lā.	lei4 laa1.	leih4 la1.	lei4 laa1.	((嚟|[o口]黎|來)|[Ll]ei4?|[Ll]eih4?)\W*(啦|[Ll]aa1?|[Ll]a1?)\W*			come !	lei4 laa1. / leih4 la1.	le̖ʲ lāː 	1
+.	He dances.	[Hh]e +dances[ \.]?		佢 跳舞 。	佢 跳舞 。	kö̗ü tiu̟ mo̗u.	keoi5 tiu3 mou5.	keuih5 tiu3 mouh5.	keoi5 tiu3 mou5.	((佢|人巨|他)|[Kk]eoi5?|[Kk]euih5?)\W*(跳|[Tt]iu3?)\W*(舞|[Mm]ou5?|[Mm]ouh5?)\W*			s/he dance	keoi5 tiu3 mou5. / keuih5 tiu3 mouh5.	kʰø̗ᶣ tʰi̟ːʷ mo̗ʷ 	2"#;
+
+        let cards = parse(1, 7, sample);
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards["Come on!"], "lei4 laa1.");
+        assert_eq!(cards["He dances."], "keoi5 tiu3 mou5.");
+    }
+
+    #[test]
+    fn test_importer_html() {
+        let sample = "#separator:tab\n#html:true\n<div style=\"x\">Hi&nbsp;there</div>\tA<br>B";
+        let cards = parse(0, 1, sample);
+        assert_eq!(cards["<div>Hi there</div>"], "A<br>B");
+    }
+
+    #[test]
+    fn test_importer_tags() {
+        let sample = "#separator:tab\n#tags column:3\nfront\tback\tverbs  hsk1 verbs";
+        let mut parser = Box::new(super::AnkiTextParser::new(0, 1));
+        for line in sample.lines() {
+            parser.feed_line(line);
+        }
+        let cards = parser.finish();
+        assert_eq!(cards[0].tags, ["verbs", "hsk1"]);
+    }
+}
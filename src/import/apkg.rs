@@ -0,0 +1,54 @@
+//! Reading notes from Anki package (`.apkg`) files.
+//! ref: https://docs.ankiweb.net/exporting.html#packaged-decks
+use std::{fs::File, io, path::Path};
+
+use anyhow::{Context, Result, bail};
+use zip::ZipArchive;
+
+/// The legacy collection formats that can be read with plain SQLite
+/// (newer Anki versions add a zstd-compressed `collection.anki21b`)
+const COLLECTION_NAMES: &[&str] = &["collection.anki21", "collection.anki2"];
+
+/// Returns the fields of every note in the package, in creation order.
+/// This is blocking and should be run with `spawn_blocking`.
+pub(crate) fn read_apkg_notes(path: &Path) -> Result<Vec<Vec<String>>> {
+    let file = File::open(path).context("Failed to open package")?;
+    let mut archive = ZipArchive::new(file).context("Not a valid .apkg file")?;
+
+    let has_legacy_collection = archive.index_for_name(COLLECTION_NAMES[0]).is_some();
+    let has_new_collection = archive.index_for_name("collection.anki21b").is_some();
+    if !has_legacy_collection && has_new_collection {
+        bail!(
+            "This package uses the latest Anki format; please export it again with 'Support older Anki versions' enabled"
+        );
+    }
+    let Some(name) = COLLECTION_NAMES
+        .iter()
+        .find(|name| archive.index_for_name(name).is_some())
+    else {
+        bail!("No Anki collection found in package");
+    };
+
+    let collection_path = path.with_extension("anki2");
+    {
+        let mut entry = archive.by_name(name)?;
+        let mut collection = File::create(&collection_path)?;
+        io::copy(&mut entry, &mut collection)?;
+    }
+
+    let result = read_collection_notes(&collection_path);
+    let _ = std::fs::remove_file(&collection_path);
+    result
+}
+
+fn read_collection_notes(path: &Path) -> Result<Vec<Vec<String>>> {
+    let connection =
+        rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context("Failed to open Anki collection")?;
+    let mut statement = connection.prepare("SELECT flds FROM notes ORDER BY id")?;
+    let notes = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .map(|fields| fields.map(|f| f.split('\x1f').map(str::to_string).collect()))
+        .collect::<Result<Vec<Vec<String>>, _>>()?;
+    Ok(notes)
+}
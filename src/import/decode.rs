@@ -0,0 +1,117 @@
+//! Incremental text decoding for uploaded files (UTF-8 with or without BOM, UTF-16).
+use encoding_rs::{Decoder, Encoding, UTF_8, UTF_16BE, UTF_16LE};
+
+/// Number of leading bytes used for detecting UTF-16 without a BOM
+const SNIFF_LEN: usize = 64;
+
+/// Decodes a byte stream chunk by chunk and yields complete lines
+pub(crate) struct StreamingDecoder {
+    decoder: Option<Decoder>,
+    pending: Vec<u8>,
+    buffer: String,
+}
+
+impl StreamingDecoder {
+    pub fn new() -> Self {
+        Self {
+            decoder: None,
+            pending: Vec::new(),
+            buffer: String::new(),
+        }
+    }
+
+    /// Guesses the encoding from the first bytes: a BOM (handled by the decoder itself)
+    /// or the NUL byte pattern of UTF-16 text, defaulting to UTF-8
+    fn detect(bytes: &[u8]) -> &'static Encoding {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            return encoding;
+        }
+        let sample = &bytes[..bytes.len().min(SNIFF_LEN) & !1];
+        let zeros_at = |offset: usize| {
+            sample
+                .iter()
+                .skip(offset)
+                .step_by(2)
+                .filter(|b| **b == 0)
+                .count()
+        };
+        let half = sample.len() / 2;
+        if half > 0 && zeros_at(1) * 2 > half && zeros_at(0) == 0 {
+            UTF_16LE
+        } else if half > 0 && zeros_at(0) * 2 > half && zeros_at(1) == 0 {
+            UTF_16BE
+        } else {
+            UTF_8
+        }
+    }
+
+    fn decode(&mut self, bytes: &[u8], last: bool) {
+        if self.decoder.is_none() {
+            self.pending.extend_from_slice(bytes);
+            if self.pending.len() < SNIFF_LEN && !last {
+                return;
+            }
+            self.decoder = Some(Self::detect(&self.pending).new_decoder());
+            let pending = std::mem::take(&mut self.pending);
+            return self.decode(&pending, last);
+        }
+        if let Some(decoder) = self.decoder.as_mut() {
+            let capacity = decoder
+                .max_utf8_buffer_length(bytes.len())
+                .unwrap_or(bytes.len() * 3 + 16);
+            self.buffer.reserve(capacity);
+            let _ = decoder.decode_to_string(bytes, &mut self.buffer, last);
+        }
+    }
+
+    fn take_lines(&mut self) -> Vec<String> {
+        let Some(end) = self.buffer.rfind('\n') else {
+            return Vec::new();
+        };
+        let rest = self.buffer.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.buffer, rest);
+        complete
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect()
+    }
+
+    /// Decodes the next chunk and returns the lines it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.decode(chunk, false);
+        self.take_lines()
+    }
+
+    /// Flushes the decoder and returns the remaining lines
+    pub fn finish(mut self) -> Vec<String> {
+        self.decode(&[], true);
+        let mut lines = self.take_lines();
+        if !self.buffer.is_empty() {
+            lines.push(self.buffer.trim_end_matches('\r').to_string());
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StreamingDecoder;
+
+    #[test]
+    fn test_decoder() {
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("a\tб\r\nc\td".encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect();
+        let utf8_bom = [&[0xEF, 0xBB, 0xBF][..], "a\tб\r\nc\td".as_bytes()].concat();
+        for bytes in [utf16, utf8_bom] {
+            let mut decoder = StreamingDecoder::new();
+            let mut lines = Vec::new();
+            for chunk in bytes.chunks(3) {
+                lines.extend(decoder.push(chunk));
+            }
+            lines.extend(decoder.finish());
+            assert_eq!(lines, vec!["a\tб", "c\td"]);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Background import jobs: parsed cards are stored in batches outside of the request,
+//! and the progress is tracked in the `import_job` table so that it can be polled
+//! (and the job cancelled) from the import page.
+use std::{collections::HashMap, sync::Arc};
+
+use sqlx::PgPool;
+use tracing::{error, info};
+
+use crate::{
+    import::ImportedCard,
+    models::{ImportJob, ImportJobStatus},
+};
+
+/// Number of cards inserted per statement (and transaction)
+const BATCH_SIZE: usize = 1000;
+
+/// The id of the deck the card is stored in: the deck it names or the target deck
+fn card_deck_id(card: &ImportedCard, deck_id: i32, deck_ids: &HashMap<String, i32>) -> i32 {
+    card.deck.as_ref().map_or(deck_id, |name| deck_ids[name])
+}
+
+/// Keeps the last card for each deck and front, as later cards overwrite earlier ones
+/// (and a batched upsert can't update the same row twice).
+/// The decks are compared by id, so a card naming the target deck replaces one without a deck.
+fn dedup_cards(
+    flashcards: Vec<ImportedCard>,
+    deck_id: i32,
+    deck_ids: &HashMap<String, i32>,
+) -> Vec<ImportedCard> {
+    let mut positions = HashMap::new();
+    let mut cards: Vec<Option<ImportedCard>> = Vec::with_capacity(flashcards.len());
+    for card in flashcards {
+        let key = (card_deck_id(&card, deck_id, deck_ids), card.front.clone());
+        if let Some(previous) = positions.insert(key, cards.len()) {
+            cards[previous] = None;
+        }
+        cards.push(Some(card));
+    }
+    cards.into_iter().flatten().collect()
+}
+
+/// Creates the decks named in the import and a job for the cards, and runs it in the background
+pub async fn start_import_job(
+    pool: Arc<PgPool>,
+    user_id: &str,
+    deck_id: i32,
+    flashcards: Vec<ImportedCard>,
+) -> Result<ImportJob, sqlx::Error> {
+    let deck_ids = resolve_decks(&pool, user_id, &flashcards).await?;
+    let flashcards = dedup_cards(flashcards, deck_id, &deck_ids);
+    let job = sqlx::query_as::<_, ImportJob>(
+        "INSERT INTO import_job (user_id, deck_id, total) VALUES ($1, $2, $3) RETURNING *",
+    )
+    .bind(user_id)
+    .bind(deck_id)
+    .bind(flashcards.len() as i32)
+    .fetch_one(&*pool)
+    .await?;
+
+    let job_id = job.id;
+    tokio::spawn(async move {
+        match run_import_job(&pool, job_id, deck_id, &deck_ids, flashcards).await {
+            Ok(ImportJobStatus::Cancelled) => info!("Import job {job_id} was cancelled"),
+            Ok(_) => info!("Import job {job_id} completed"),
+            Err(e) => {
+                error!("Import job {job_id} failed: {e}");
+                let result = sqlx::query(
+                    "UPDATE import_job SET status = 'failed', error = $2, updated = NOW() WHERE id = $1",
+                )
+                .bind(job_id)
+                .bind(e.to_string())
+                .execute(&*pool)
+                .await;
+                if let Err(e) = result {
+                    error!("Failed to update import job {job_id}: {e}");
+                }
+            }
+        }
+    });
+    Ok(job)
+}
+
+/// Returns the deck id of each deck named in the import, creating the missing decks
+async fn resolve_decks(
+    pool: &PgPool,
+    user_id: &str,
+    flashcards: &[ImportedCard],
+) -> Result<HashMap<String, i32>, sqlx::Error> {
+    let mut deck_ids = HashMap::new();
+    for name in flashcards.iter().filter_map(|card| card.deck.as_ref()) {
+        if deck_ids.contains_key(name) {
+            continue;
+        }
+        let id: i32 = sqlx::query_scalar(
+            r#"
+            INSERT INTO deck (name, user_id) VALUES ($1, $2)
+            ON CONFLICT (name, user_id) DO UPDATE SET name = EXCLUDED.name
+            RETURNING id
+            "#,
+        )
+        .bind(name)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+        deck_ids.insert(name.clone(), id);
+    }
+    Ok(deck_ids)
+}
+
+/// Stores the cards in the target deck (or the deck they name), one batch per transaction.
+/// Cards whose front already exists in the deck get the new back (and tags, if there are any).
+/// Stops after the current batch when the job is cancelled; the batches stored before are kept.
+async fn run_import_job(
+    pool: &PgPool,
+    job_id: i32,
+    deck_id: i32,
+    deck_ids: &HashMap<String, i32>,
+    flashcards: Vec<ImportedCard>,
+) -> Result<ImportJobStatus, sqlx::Error> {
+    for batch in flashcards.chunks(BATCH_SIZE) {
+        let mut tx = pool.begin().await?;
+        // Only running jobs are updated, so a cancelled job rolls back its current batch
+        let updated = sqlx::query(
+            r#"
+            UPDATE import_job SET processed = processed + $2, updated = NOW()
+            WHERE id = $1 AND status = 'running'
+            "#,
+        )
+        .bind(job_id)
+        .bind(batch.len() as i32)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(ImportJobStatus::Cancelled);
+        }
+
+        let deck_id_column: Vec<i32> = batch
+            .iter()
+            .map(|card| card_deck_id(card, deck_id, deck_ids))
+            .collect();
+        let fronts: Vec<&str> = batch.iter().map(|card| card.front.as_str()).collect();
+        let backs: Vec<&str> = batch.iter().map(|card| card.back.as_str()).collect();
+        // Arrays can't be nested with different lengths, so the tags are joined
+        let tags: Vec<String> = batch.iter().map(|card| card.tags.join(" ")).collect();
+        sqlx::query(
+            r#"
+            INSERT INTO flashcard (deck_id, front, back, tags)
+            SELECT deck_id, front, back, string_to_array(tags, ' ')
+            FROM UNNEST($1::INTEGER[], $2::TEXT[], $3::TEXT[], $4::TEXT[]) AS t(deck_id, front, back, tags)
+            ON CONFLICT (front, deck_id) DO UPDATE
+            SET back = EXCLUDED.back,
+                tags = CASE WHEN cardinality(EXCLUDED.tags) > 0 THEN EXCLUDED.tags ELSE flashcard.tags END
+            "#,
+        )
+        .bind(&deck_id_column)
+        .bind(&fronts)
+        .bind(&backs)
+        .bind(&tags)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+
+    let updated = sqlx::query(
+        "UPDATE import_job SET status = 'completed', updated = NOW() WHERE id = $1 AND status = 'running'",
+    )
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+    Ok(if updated.rows_affected() == 0 {
+        ImportJobStatus::Cancelled
+    } else {
+        ImportJobStatus::Completed
+    })
+}
+
+/// Cancels a running job of the user (in the deck, if given), returning the job in its new state
+pub async fn cancel_import_job(
+    pool: &PgPool,
+    user_id: &str,
+    deck_id: Option<i32>,
+    job_id: i32,
+) -> Result<ImportJob, sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE import_job SET status = 'cancelled', updated = NOW()
+        WHERE id = $1 AND ($2::INTEGER IS NULL OR deck_id = $2) AND user_id = $3
+        AND status = 'running'
+        "#,
+    )
+    .bind(job_id)
+    .bind(deck_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    sqlx::query_as::<_, ImportJob>(
+        "SELECT * FROM import_job WHERE id = $1 AND ($2::INTEGER IS NULL OR deck_id = $2) AND user_id = $3",
+    )
+    .bind(job_id)
+    .bind(deck_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Marks the jobs that were running when the server stopped as failed
+pub async fn fail_interrupted_jobs(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE import_job SET status = 'failed', error = 'Interrupted by a server restart', updated = NOW()
+        WHERE status = 'running'
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::import::ImportedCard;
+
+    #[test]
+    fn test_dedup_cards() {
+        let cards = vec![
+            ImportedCard::new("a".to_string(), "1".to_string()),
+            ImportedCard::new("b".to_string(), "2".to_string()),
+            ImportedCard::new("a".to_string(), "3".to_string()),
+            ImportedCard::new("a".to_string(), "4".to_string()).in_deck(Some("x".to_string())),
+        ];
+        let deck_ids = HashMap::from([("x".to_string(), 2)]);
+        let cards = super::dedup_cards(cards, 1, &deck_ids);
+        let backs: Vec<&str> = cards.iter().map(|card| card.back.as_str()).collect();
+        assert_eq!(backs, ["2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_dedup_cards_in_target_deck() {
+        let cards = vec![
+            ImportedCard::new("a".to_string(), "1".to_string()),
+            ImportedCard::new("a".to_string(), "2".to_string()).in_deck(Some("Target".to_string())),
+            ImportedCard::new("b".to_string(), "3".to_string()).in_deck(Some("Target".to_string())),
+            ImportedCard::new("b".to_string(), "4".to_string()),
+        ];
+        let deck_ids = HashMap::from([("Target".to_string(), 1)]);
+        let cards = super::dedup_cards(cards, 1, &deck_ids);
+        let backs: Vec<&str> = cards.iter().map(|card| card.back.as_str()).collect();
+        assert_eq!(backs, ["2", "4"]);
+    }
+}
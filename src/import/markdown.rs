@@ -0,0 +1,148 @@
+//! Importer for flashcards written in Markdown notes.
+//! Supported forms: `Question :: Answer` lines, `Q:`/`A:` blocks
+//! (answers may continue on the following lines), and headings that name the deck
+//! of the cards below them.
+use crate::import::{ImportedCard, Importer};
+
+#[derive(Default)]
+pub(crate) struct MarkdownParser {
+    deck: Option<String>,
+    question: Option<String>,
+    answer: Option<String>,
+    flashcards: Vec<ImportedCard>,
+}
+
+fn strip_list_marker(line: &str) -> &str {
+    line.strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+        .unwrap_or(line)
+}
+
+fn heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    (line.starts_with('#') && text.starts_with(' ')).then(|| text.trim())
+}
+
+fn question_prefix(line: &str) -> Option<&str> {
+    line.strip_prefix("Q:").or_else(|| line.strip_prefix("q:"))
+}
+
+fn answer_prefix(line: &str) -> Option<&str> {
+    line.strip_prefix("A:").or_else(|| line.strip_prefix("a:"))
+}
+
+impl MarkdownParser {
+    /// Ends the current `Q:`/`A:` block, adding it as a card if it's complete
+    fn flush(&mut self) {
+        if let (Some(front), Some(back)) = (self.question.take(), self.answer.take())
+            && !front.is_empty()
+            && !back.is_empty()
+        {
+            self.flashcards
+                .push(ImportedCard::new(front, back).in_deck(self.deck.clone()));
+        }
+    }
+}
+
+/// The front and back of a `Question :: Answer` line, with or without spaces around `::`
+fn split_card(line: &str) -> Option<(&str, &str)> {
+    let (front, back) = line.split_once("::")?;
+    let (front, back) = (front.trim(), back.trim());
+    (!front.is_empty() && !back.is_empty()).then_some((front, back))
+}
+
+/// Whether at least half of the lines (other than headings) are `::` cards or `Q:`/`A:` lines.
+/// Lines with a tab are columns of other exports, even with `::` in them (e.g. `std::vector`).
+pub(super) fn detect(sample: &str) -> bool {
+    let lines: Vec<&str> = sample
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && heading(line).is_none())
+        .collect();
+    let cards = lines
+        .iter()
+        .map(|line| strip_list_marker(line))
+        .filter(|line| {
+            (split_card(line).is_some() && !line.contains('\t'))
+                || question_prefix(line).is_some()
+                || answer_prefix(line).is_some()
+        })
+        .count();
+    cards > 0 && cards * 2 >= lines.len()
+}
+
+fn append_line(text: &mut String, line: &str) {
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(line);
+}
+
+impl Importer for MarkdownParser {
+    fn feed_line(&mut self, line: &str) {
+        let line = line.trim();
+        if let Some(name) = heading(line) {
+            self.flush();
+            self.deck = (!name.is_empty()).then(|| name.to_string());
+            return;
+        }
+        if line.is_empty() {
+            self.flush();
+            return;
+        }
+        let line = strip_list_marker(line);
+        if let Some(question) = question_prefix(line) {
+            self.flush();
+            self.question = Some(question.trim().to_string());
+        } else if let Some(answer) = answer_prefix(line)
+            && self.question.is_some()
+        {
+            self.answer = Some(answer.trim().to_string());
+        } else if let Some(answer) = self.answer.as_mut() {
+            append_line(answer, line);
+        } else if let Some(question) = self.question.as_mut() {
+            append_line(question, line);
+        } else if let Some((front, back)) = split_card(line) {
+            self.flashcards.push(
+                ImportedCard::new(front.to_string(), back.to_string()).in_deck(self.deck.clone()),
+            );
+        }
+    }
+
+    fn finish(mut self: Box<Self>) -> Vec<ImportedCard> {
+        self.flush();
+        self.flashcards
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::import::Importer;
+
+    #[test]
+    fn test_markdown() {
+        let sample = "# Cantonese\n- Come on! :: lei4 laa1.\n\nQ: He dances.\nA: keoi5 tiu3 mou5.\n(informal)\n## Verbs\nQ: to dance\nA: tiu3 mou5";
+        let mut parser = Box::<super::MarkdownParser>::default();
+        for line in sample.lines() {
+            parser.feed_line(line);
+        }
+        let cards = parser.finish();
+        assert_eq!(cards.len(), 3);
+        assert_eq!(cards[0].front, "Come on!");
+        assert_eq!(cards[1].back, "keoi5 tiu3 mou5.\n(informal)");
+        assert_eq!(cards[1].deck.as_deref(), Some("Cantonese"));
+        assert_eq!(cards[2].deck.as_deref(), Some("Verbs"));
+    }
+
+    #[test]
+    fn test_detect() {
+        assert!(super::detect("casa::house"));
+        assert!(super::detect("- casa :: house"));
+        assert!(super::detect("Q: casa"));
+        assert!(!super::detect("std::\ncasa\thouse"));
+        assert!(super::detect("# Deck\n\nQ: casa\nA: house\nor home\n"));
+        assert!(!super::detect("vector\tstd::vector\nmap\tstd::map"));
+        assert!(!super::detect("casa::house\nperro\tdog\ngato\tcat"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Importer for Mnemosyne XML exports, where each `<item>` has a question (`<Q>`),
+//! an answer (`<A>`) and a category (`<cat>`) that is used as the deck name.
+//! Mnemosyne's tab-separated text export is handled by the Quizlet importer.
+use crate::{
+    html::{decode_entities, sanitize},
+    import::{ImportedCard, Importer},
+};
+
+#[derive(Default)]
+pub(crate) struct MnemosyneParser {
+    xml: String,
+}
+
+pub(super) fn detect(sample: &str) -> bool {
+    sample.contains("<mnemosyne")
+}
+
+/// Returns the decoded text of the first `<tag>` element in `xml`
+fn element_text(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+    Some(decode_entities(xml[start..end].trim()))
+}
+
+impl Importer for MnemosyneParser {
+    fn feed_line(&mut self, line: &str) {
+        self.xml.push_str(line);
+        self.xml.push('\n');
+    }
+
+    fn finish(self: Box<Self>) -> Vec<ImportedCard> {
+        let mut flashcards = Vec::new();
+        let mut rest = self.xml.as_str();
+        while let Some(start) = rest.find("<item") {
+            let Some(end) = rest[start..].find("</item>") else {
+                break;
+            };
+            let item = &rest[start..start + end];
+            // The question and answer can contain (escaped) HTML
+            if let (Some(front), Some(back)) = (element_text(item, "Q"), element_text(item, "A")) {
+                let deck = element_text(item, "cat").filter(|cat| !cat.is_empty());
+                flashcards.push(ImportedCard::new(sanitize(&front), sanitize(&back)).in_deck(deck));
+            }
+            rest = &rest[start + end + "</item>".len()..];
+        }
+        flashcards
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::import::Importer;
+
+    #[test]
+    fn test_mnemosyne() {
+        let sample = r#"<?xml version="1.0" encoding="UTF-8"?>
+<mnemosyne core_version="1">
+<category active="1"><name>Verbs</name></category>
+<item id="a1" gr="2"><cat>Verbs</cat><Q>to dance</Q><A>&lt;b&gt;tiu3 mou5&lt;/b&gt;</A></item>
+</mnemosyne>"#;
+        let mut parser = Box::<super::MnemosyneParser>::default();
+        for line in sample.lines() {
+            parser.feed_line(line);
+        }
+        let cards = parser.finish();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].back, "<b>tiu3 mou5</b>");
+        assert_eq!(cards[0].deck.as_deref(), Some("Verbs"));
+    }
+}
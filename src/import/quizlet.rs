@@ -0,0 +1,106 @@
+//! Importer for Quizlet exports (and other two-column lists such as Mnemosyne's text export).
+//! Quizlet separates the term and definition with a tab by default, or a comma
+//! or a custom separator, and separates the rows with new lines. The separator of the first
+//! card is used for the whole file, and a line without it continues the previous definition.
+use crate::import::{ImportedCard, Importer};
+
+/// Separators between the term and definition, in order of preference
+const SEPARATORS: &[&str] = &["\t", " - ", ","];
+
+#[derive(Default)]
+pub(crate) struct QuizletParser {
+    /// The separator of the first card, used for the rest of the file
+    separator: Option<&'static str>,
+    flashcards: Vec<ImportedCard>,
+}
+
+/// The first separator in the line
+fn find_separator(line: &str) -> Option<&'static str> {
+    SEPARATORS
+        .iter()
+        .copied()
+        .find(|separator| line.contains(separator))
+}
+
+/// The term and definition of a card line
+fn split_card<'a>(line: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    let (term, definition) = line.split_once(separator)?;
+    let (term, definition) = (term.trim(), definition.trim());
+    (!term.is_empty() && !definition.is_empty()).then_some((term, definition))
+}
+
+/// Whether the first line is a term and a definition, and at least half of the lines are cards
+/// with its separator (the others continue a definition)
+pub(super) fn detect(sample: &str) -> bool {
+    let lines: Vec<&str> = sample
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let Some(separator) = lines.first().and_then(|line| find_separator(line)) else {
+        return false;
+    };
+    if split_card(lines[0], separator).is_none() {
+        return false;
+    }
+    let cards = lines
+        .iter()
+        .filter(|line| split_card(line, separator).is_some())
+        .count();
+    cards * 2 >= lines.len()
+}
+
+impl Importer for QuizletParser {
+    fn feed_line(&mut self, line: &str) {
+        let separator = self.separator.or_else(|| find_separator(line));
+        let split = separator.and_then(|separator| line.split_once(separator));
+        let Some((term, definition)) = split else {
+            // A line without the separator continues the previous definition
+            if let Some(card) = self.flashcards.last_mut()
+                && !line.trim().is_empty()
+            {
+                card.back.push('\n');
+                card.back.push_str(line.trim());
+            }
+            return;
+        };
+        self.separator = separator;
+        let (term, definition) = (term.trim(), definition.trim());
+        if !term.is_empty() && !definition.is_empty() {
+            self.flashcards
+                .push(ImportedCard::new(term.to_string(), definition.to_string()));
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Vec<ImportedCard> {
+        self.flashcards
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::import::Importer;
+
+    fn parse(sample: &str) -> Vec<crate::import::ImportedCard> {
+        let mut parser = Box::<super::QuizletParser>::default();
+        for line in sample.lines() {
+            parser.feed_line(line);
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn test_quizlet() {
+        let cards = parse("to dance\ttiu3 mou5, informal\nto run\tpaau2 bou6\n(jogging)\n");
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].back, "tiu3 mou5, informal");
+        assert_eq!(cards[1].front, "to run");
+        assert_eq!(cards[1].back, "paau2 bou6\n(jogging)");
+
+        let cards = parse("casa,house\nperro,dog");
+        assert_eq!(cards.len(), 2);
+        assert_eq!(
+            (cards[1].front.as_str(), cards[1].back.as_str()),
+            ("perro", "dog")
+        );
+    }
+}
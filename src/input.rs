@@ -0,0 +1,151 @@
+//! Button presses and head movements on the glasses, mapped to review actions per user so that
+//! a session can be completed without speaking (e.g. in a library or on a train).
+use std::time::Duration;
+
+use crate::models::{InputAction, InputMapping};
+
+/// Two short presses within this time are a double press
+pub const DOUBLE_PRESS_WINDOW: Duration = Duration::from_millis(400);
+
+/// An input on the glasses that can be mapped to an action
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    ShortPress,
+    LongPress,
+    DoublePress,
+    HeadUp,
+    HeadDown,
+}
+
+impl InputEvent {
+    pub const ALL: [InputEvent; 5] = [
+        InputEvent::ShortPress,
+        InputEvent::LongPress,
+        InputEvent::DoublePress,
+        InputEvent::HeadUp,
+        InputEvent::HeadDown,
+    ];
+
+    /// The key of the action in the app settings and the `user_settings` column
+    pub fn setting_key(&self) -> &'static str {
+        match self {
+            InputEvent::ShortPress => "short_press_action",
+            InputEvent::LongPress => "long_press_action",
+            InputEvent::DoublePress => "double_press_action",
+            InputEvent::HeadUp => "head_up_action",
+            InputEvent::HeadDown => "head_down_action",
+        }
+    }
+
+    pub fn from_setting_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.setting_key() == key)
+    }
+
+    /// A button press by its press type from the cloud ("short" or "long"); double presses are
+    /// told apart by their timing
+    pub fn from_press_type(press_type: Option<&str>) -> Self {
+        match press_type {
+            Some(press_type) if press_type.eq_ignore_ascii_case("long") => InputEvent::LongPress,
+            _ => InputEvent::ShortPress,
+        }
+    }
+
+    /// A head movement by its position from the cloud ("up" or "down")
+    pub fn from_head_position(position: &str) -> Option<Self> {
+        let position = position.to_lowercase();
+        if position.contains("up") {
+            Some(InputEvent::HeadUp)
+        } else if position.contains("down") {
+            Some(InputEvent::HeadDown)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the input starts the review before the session started: only a press does, as
+    /// head movements are made without meaning to, and never while a previous session can be
+    /// resumed, as starting over finishes it (the user says "start" or "resume" instead)
+    pub fn starts_session(&self, resumable: bool) -> bool {
+        let press = matches!(
+            self,
+            InputEvent::ShortPress | InputEvent::LongPress | InputEvent::DoublePress
+        );
+        press && !resumable
+    }
+}
+
+impl InputMapping {
+    pub fn action(&self, event: InputEvent) -> InputAction {
+        match event {
+            InputEvent::ShortPress => self.short_press,
+            InputEvent::LongPress => self.long_press,
+            InputEvent::DoublePress => self.double_press,
+            InputEvent::HeadUp => self.head_up,
+            InputEvent::HeadDown => self.head_down,
+        }
+    }
+
+    pub fn set_action(&mut self, event: InputEvent, action: InputAction) {
+        let slot = match event {
+            InputEvent::ShortPress => &mut self.short_press,
+            InputEvent::LongPress => &mut self.long_press,
+            InputEvent::DoublePress => &mut self.double_press,
+            InputEvent::HeadUp => &mut self.head_up,
+            InputEvent::HeadDown => &mut self.head_down,
+        };
+        *slot = action;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::InputEvent;
+    use crate::models::{InputAction, InputMapping};
+
+    #[test]
+    fn test_input_events() {
+        assert_eq!(
+            InputEvent::from_press_type(Some("long")),
+            InputEvent::LongPress
+        );
+        assert_eq!(InputEvent::from_press_type(None), InputEvent::ShortPress);
+        assert_eq!(
+            InputEvent::from_head_position("Up"),
+            Some(InputEvent::HeadUp)
+        );
+        assert_eq!(
+            InputEvent::from_head_position("down"),
+            Some(InputEvent::HeadDown)
+        );
+        assert_eq!(InputEvent::from_head_position("level"), None);
+        assert_eq!(
+            InputEvent::from_setting_key("double_press_action"),
+            Some(InputEvent::DoublePress)
+        );
+    }
+
+    #[test]
+    fn test_starts_session() {
+        assert!(InputEvent::ShortPress.starts_session(false));
+        assert!(InputEvent::DoublePress.starts_session(false));
+        assert!(!InputEvent::HeadUp.starts_session(false));
+        assert!(!InputEvent::HeadDown.starts_session(false));
+        assert!(!InputEvent::ShortPress.starts_session(true));
+        assert!(!InputEvent::HeadUp.starts_session(true));
+    }
+
+    #[test]
+    fn test_input_mapping() {
+        let mut mapping = InputMapping::default();
+        assert_eq!(mapping.action(InputEvent::HeadUp), InputAction::Reveal);
+        assert_eq!(mapping.action(InputEvent::HeadDown), InputAction::None);
+        mapping.set_action(InputEvent::HeadDown, InputAction::Again);
+        mapping.set_action(InputEvent::DoublePress, InputAction::Undo);
+        assert_eq!(mapping.action(InputEvent::HeadDown), InputAction::Again);
+        assert_eq!(mapping.action(InputEvent::DoublePress), InputAction::Undo);
+        assert_eq!(InputAction::from_value("pause"), Some(InputAction::Pause));
+        assert_eq!(InputAction::from_value("dance"), None);
+    }
+}
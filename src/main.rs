@@ -7,15 +7,28 @@ use tokio::net::TcpListener;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod answer;
+mod api_token;
+mod backup;
+mod browse;
+mod bulk;
+mod changes;
 pub(crate) mod config;
 pub(crate) mod errors;
+mod html;
 mod import;
+mod input;
 pub(crate) mod models;
+mod review_session;
 mod router;
 mod routes;
 pub(crate) mod sdk;
+mod search;
 pub(crate) mod srs;
+mod stats;
 mod templates;
+mod vocabulary;
+mod voice;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -44,8 +57,15 @@ async fn main() -> anyhow::Result<()> {
         .await
         .context("Failed to run migrations")?;
 
-    let cloud_api_url = env::var("CLOUD_API_URL")
-        .unwrap_or_else(|_| "https://prod.augmentos.cloud".to_string());
+    let interrupted = import::fail_interrupted_jobs(&pool)
+        .await
+        .context("Failed to update interrupted import jobs")?;
+    if interrupted > 0 {
+        info!("Marked {interrupted} interrupted import jobs as failed");
+    }
+
+    let cloud_api_url =
+        env::var("CLOUD_API_URL").unwrap_or_else(|_| "https://prod.augmentos.cloud".to_string());
     let cloud_domain = cloud_api_url
         .strip_prefix("https://")
         .or(cloud_api_url.strip_prefix("http://"))
@@ -54,6 +74,10 @@ async fn main() -> anyhow::Result<()> {
     let default_user_token_public_key = "-----BEGIN PUBLIC KEY-----\nMIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA0Yt2RtNOdeKQxWMY0c84\nADpY1Jy58YWZhaEgP2A5tBwFUKgy/TH9gQLWZjQ3dQ/6XXO8qq0kluoYFqM7ZDRF\nzJ0E4Yi0WQncioLRcCx4q8pDmqY9vPKgv6PruJdFWca0l0s3gZ3BqSeWum/C23xK\nFPHPwi8gvRdc6ALrkcHeciM+7NykU8c0EY8PSitNL+Tchti95kGu+j6APr5vNewi\nzRpQGOdqaLWe+ahHmtj6KtUZjm8o6lan4f/o08C6litizguZXuw2Nn/Kd9fFI1xF\nIVNJYMy9jgGaOi71+LpGw+vIpwAawp/7IvULDppvY3DdX5nt05P1+jvVJXPxMKzD\nTQIDAQAB\n-----END PUBLIC KEY-----".to_string();
     let package_name = env::var("PACKAGE_NAME").context("PACKAGE_NAME must be set")?;
     let api_key = env::var("API_KEY").context("API_KEY must be set")?.into();
+    let max_upload_mb: usize = env::var("MAX_UPLOAD_MB")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .context("MAX_UPLOAD_MB must be a number")?;
     let cookie_secret = Key::generate();
     let config = config::AppConfig {
         package_name,
@@ -63,6 +87,7 @@ async fn main() -> anyhow::Result<()> {
             .unwrap_or(default_user_token_public_key),
         cloud_api_url,
         cloud_domain,
+        max_upload_bytes: max_upload_mb * 1024 * 1024,
     };
 
     let router = router::init_router(pool, config);
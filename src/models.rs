@@ -4,20 +4,148 @@ use std::{fmt::Display, str::FromStr};
 use chrono::NaiveDateTime;
 use fsrs::MemoryState;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct Deck {
     pub id: i32,
     pub name: String,
     pub user_id: String,
+    pub answer_mode: AnswerMode,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub speech: DeckSpeech,
 }
 
-#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone)]
+/// Reading the cards of a deck aloud on the glasses
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+pub struct DeckSpeech {
+    /// Read the front when a card is shown
+    #[serde(default)]
+    pub speak_front: bool,
+    /// Read the back when a card is revealed
+    #[serde(default)]
+    pub speak_back: bool,
+    /// The text-to-speech voice id of the front, the cloud's default voice if missing
+    #[serde(default)]
+    pub front_voice: Option<String>,
+    /// The language code of the front, e.g. "en" or "yue", detected if missing
+    #[serde(default)]
+    pub front_language: Option<String>,
+    #[serde(default)]
+    pub back_voice: Option<String>,
+    #[serde(default)]
+    pub back_language: Option<String>,
+}
+
+/// How spoken answers are checked against the back of the cards of a deck
+#[derive(Clone, Copy, Debug, Default, PartialEq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "answer_mode", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AnswerMode {
+    /// The answer is revealed without checking
+    #[default]
+    Off,
+    /// The same words, ignoring case and punctuation
+    Exact,
+    /// Close answers are accepted, with a lower rating
+    Fuzzy,
+    /// The accepted answers of the card, if any, then as `Fuzzy`
+    Regex,
+}
+
+impl AnswerMode {
+    pub const ALL: [AnswerMode; 4] = [
+        AnswerMode::Off,
+        AnswerMode::Exact,
+        AnswerMode::Fuzzy,
+        AnswerMode::Regex,
+    ];
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            AnswerMode::Off => "off",
+            AnswerMode::Exact => "exact",
+            AnswerMode::Fuzzy => "fuzzy",
+            AnswerMode::Regex => "regex",
+        }
+    }
+}
+
+impl Display for AnswerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnswerMode::Off => write!(f, "Don't check answers"),
+            AnswerMode::Exact => write!(f, "Exact answers"),
+            AnswerMode::Fuzzy => write!(f, "Close answers"),
+            AnswerMode::Regex => write!(f, "Accepted answers (regex)"),
+        }
+    }
+}
+
+/// A deck with its card counts, for the deck list
+#[derive(sqlx::FromRow, Serialize, Debug, Clone, ToSchema)]
+pub struct DeckSummary {
+    pub id: i32,
+    pub name: String,
+    pub new_count: i64,
+    pub due_count: i64,
+    pub learning_count: i64,
+    pub total_count: i64,
+    /// When the earliest scheduled card is due (in the past if cards are due now)
+    pub next_due: Option<NaiveDateTime>,
+    pub last_studied: Option<NaiveDateTime>,
+}
+
+impl DeckSummary {
+    /// The summary of a deck without cards
+    pub fn empty(deck: Deck) -> Self {
+        Self {
+            id: deck.id,
+            name: deck.name,
+            new_count: 0,
+            due_count: 0,
+            learning_count: 0,
+            total_count: 0,
+            next_due: None,
+            last_studied: None,
+        }
+    }
+
+    pub fn next_due_label(&self) -> Option<String> {
+        let now = chrono::Utc::now().naive_utc();
+        self.next_due.map(|next_due| {
+            if next_due <= now {
+                "now".to_string()
+            } else if next_due.date() == now.date() {
+                next_due.format("today %H:%M").to_string()
+            } else {
+                next_due.format("%Y-%m-%d").to_string()
+            }
+        })
+    }
+
+    pub fn last_studied_label(&self) -> Option<String> {
+        let today = chrono::Utc::now().date_naive();
+        self.last_studied.map(
+            |last_studied| match (today - last_studied.date()).num_days() {
+                ..=0 => "today".to_string(),
+                1 => "yesterday".to_string(),
+                days => format!("{days} days ago"),
+            },
+        )
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct DeckNew {
     pub name: String,
+    /// Unchanged if missing
+    #[serde(default)]
+    pub answer_mode: Option<AnswerMode>,
 }
 
-#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
 pub struct Flashcard {
     pub id: i32,
     pub deck_id: i32,
@@ -28,6 +156,44 @@ pub struct Flashcard {
     pub last_scheduled: Option<NaiveDateTime>,
     pub last_stability: Option<f32>,
     pub last_difficulty: Option<f32>,
+    pub tags: Vec<String>,
+    /// Suspended cards are not studied
+    pub suspended: bool,
+    /// A regular expression for the spoken answers accepted in the `Regex` answer mode
+    pub accepted_answers: Option<String>,
+}
+
+/// Splits space-separated tags (as in Anki), dropping duplicates
+pub fn parse_tags(tags: &str) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for tag in tags.split_whitespace() {
+        if !result.iter().any(|t| t == tag) {
+            result.push(tag.to_string());
+        }
+    }
+    result
+}
+
+impl Flashcard {
+    /// The sanitized rich front text for the webview
+    pub fn front_html(&self) -> String {
+        crate::html::sanitize(&self.front)
+    }
+
+    /// The sanitized rich back text for the webview
+    pub fn back_html(&self) -> String {
+        crate::html::sanitize(&self.back)
+    }
+
+    /// The plain front text for the glasses display
+    pub fn front_text(&self) -> String {
+        crate::html::to_display_text(&self.front)
+    }
+
+    /// The plain back text for the glasses display
+    pub fn back_text(&self) -> String {
+        crate::html::to_display_text(&self.back)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -35,22 +201,47 @@ pub struct FlashcardNew {
     pub deck_id: i32,
     pub front: String,
     pub back: String,
+    /// Space-separated tags
+    #[serde(default)]
+    pub tags: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FlashcardImport {
     pub anki_text: String,
+    /// Only used for Anki exports
+    #[serde(default)]
     pub front_idx: usize,
+    #[serde(default)]
     pub back_idx: usize,
+    /// The import format name, or "auto" to detect it
+    #[serde(default)]
+    pub format: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FlashcardImportPreview {
+    pub anki_text: String,
+    #[serde(default)]
+    pub format: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct FlashcardUpdate {
     pub front: String,
     pub back: String,
+    /// Space-separated tags; unchanged if missing
+    #[serde(default)]
+    pub tags: Option<String>,
+    /// A regular expression for the accepted spoken answers, cleared if empty; unchanged if
+    /// missing
+    #[serde(default)]
+    pub accepted_answers: Option<String>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, sqlx::Type, Deserialize, Serialize)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, PartialOrd, sqlx::Type, Deserialize, Serialize, ToSchema,
+)]
 #[sqlx(type_name = "card_rating", rename_all = "lowercase")]
 pub enum CardRating {
     Easy,
@@ -59,6 +250,15 @@ pub enum CardRating {
     Again,
 }
 
+impl CardRating {
+    pub const ALL: [CardRating; 4] = [
+        CardRating::Easy,
+        CardRating::Good,
+        CardRating::Difficult,
+        CardRating::Again,
+    ];
+}
+
 impl Display for CardRating {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -73,17 +273,14 @@ impl Display for CardRating {
 impl FromStr for CardRating {
     type Err = anyhow::Error;
 
+    /// Parses the name of a rating; voice commands are parsed by `voice::parse`
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.contains("easy") {
-            Ok(CardRating::Easy)
-        } else if s.contains("good") {
-            Ok(CardRating::Good)
-        } else if s.contains("difficult") {
-            Ok(CardRating::Difficult)
-        } else if s.contains("again") {
-            Ok(CardRating::Again)
-        } else {
-            anyhow::bail!("Invalid card rating: {s}");
+        match s.trim().to_lowercase().as_str() {
+            "easy" => Ok(CardRating::Easy),
+            "good" => Ok(CardRating::Good),
+            "difficult" => Ok(CardRating::Difficult),
+            "again" => Ok(CardRating::Again),
+            _ => anyhow::bail!("Invalid card rating: {s}"),
         }
     }
 }
@@ -111,11 +308,279 @@ pub struct FlashcardReviewNew {
     pub stability: f32,
     pub difficulty: f32,
     pub flashcard_id: i32,
+    /// Time to answer, if known
+    pub duration_ms: Option<i32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FlashcardReview {
+    pub id: i32,
+    pub flashcard_id: i32,
+    pub rating: CardRating,
+    pub reviewed: NaiveDateTime,
+    pub scheduled: NaiveDateTime,
+    pub stability: f32,
+    pub difficulty: f32,
+    pub duration_ms: Option<i32>,
+}
+
+/// A review action on the glasses, for a button press or head movement
+#[derive(Clone, Copy, Debug, Default, PartialEq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "input_action", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum InputAction {
+    #[default]
+    None,
+    Reveal,
+    /// Rates the card as good, revealing it first if needed
+    Good,
+    /// Rates the card as again, revealing it first if needed
+    Again,
+    Skip,
+    /// Takes back the last rating and shows that card again
+    Undo,
+    /// Pauses the session until the same input again or "resume"
+    Pause,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 7] = [
+        InputAction::None,
+        InputAction::Reveal,
+        InputAction::Good,
+        InputAction::Again,
+        InputAction::Skip,
+        InputAction::Undo,
+        InputAction::Pause,
+    ];
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            InputAction::None => "none",
+            InputAction::Reveal => "reveal",
+            InputAction::Good => "good",
+            InputAction::Again => "again",
+            InputAction::Skip => "skip",
+            InputAction::Undo => "undo",
+            InputAction::Pause => "pause",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.value() == value)
+    }
+}
+
+/// The review actions of the button presses and head movements on the glasses
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(default)]
+pub struct InputMapping {
+    #[sqlx(rename = "short_press_action")]
+    pub short_press: InputAction,
+    #[sqlx(rename = "long_press_action")]
+    pub long_press: InputAction,
+    /// Delays the short press action a little when set, to tell the presses apart
+    #[sqlx(rename = "double_press_action")]
+    pub double_press: InputAction,
+    #[sqlx(rename = "head_up_action")]
+    pub head_up: InputAction,
+    #[sqlx(rename = "head_down_action")]
+    pub head_down: InputAction,
+}
+
+impl Default for InputMapping {
+    /// The column defaults of `user_settings`: any press or looking up reveals the card
+    fn default() -> Self {
+        Self {
+            short_press: InputAction::Reveal,
+            long_press: InputAction::Reveal,
+            double_press: InputAction::None,
+            head_up: InputAction::Reveal,
+            head_down: InputAction::None,
+        }
+    }
+}
+
+/// User settings as persisted in the database (the live values are in `srs::UserSettings`)
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct StoredUserSettings {
+    pub max_cards_per_session: i16,
+    pub desired_retention: i16,
+    /// The language of the voice commands, e.g. "es-ES"
+    #[serde(default = "default_locale")]
+    pub transcription_locale: String,
+    /// Minutes within which an interrupted session can be resumed, 0 to always start over
+    #[serde(default = "default_resume_window")]
+    pub resume_window_minutes: i16,
+    /// The review actions of the button presses and head movements
+    #[serde(default)]
+    #[sqlx(flatten)]
+    pub input_mapping: InputMapping,
+}
+
+fn default_locale() -> String {
+    crate::vocabulary::DEFAULT_LOCALE.to_string()
+}
+
+fn default_resume_window() -> i16 {
+    60
+}
+
+impl Default for StoredUserSettings {
+    /// The column defaults of `user_settings`
+    fn default() -> Self {
+        Self {
+            max_cards_per_session: 20,
+            desired_retention: 75,
+            transcription_locale: default_locale(),
+            resume_window_minutes: default_resume_window(),
+            input_mapping: InputMapping::default(),
+        }
+    }
+}
+
+/// A review session on the glasses with its progress, to resume it after an interruption
+#[derive(sqlx::FromRow, Serialize, Debug, Clone, PartialEq)]
+pub struct ReviewSession {
+    pub id: i32,
+    pub user_id: String,
+    /// The cards still to review, the current one first
+    pub card_ids: Vec<i32>,
+    pub easy: i32,
+    pub good: i32,
+    pub difficult: i32,
+    pub again: i32,
+    pub duration_ms: i64,
+    pub started: NaiveDateTime,
+    pub updated: NaiveDateTime,
+    pub finished: Option<NaiveDateTime>,
+    /// The cards rated "again"
+    pub failed_card_ids: Vec<i32>,
+    /// The first card due after the session finished, now if more cards were due
+    pub next_due: Option<NaiveDateTime>,
+}
+
+impl ReviewSession {
+    pub fn reviewed(&self) -> i32 {
+        self.easy + self.good + self.difficult + self.again
+    }
+
+    /// The time spent reviewing, e.g. "3m"
+    pub fn duration_text(&self) -> String {
+        crate::stats::format_duration(self.duration_ms)
+    }
+
+    /// Whether all the cards of the session were reviewed
+    pub fn is_complete(&self) -> bool {
+        self.finished.is_some() && self.card_ids.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "import_job_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ImportJobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ImportJob {
+    pub id: i32,
+    pub user_id: String,
+    pub deck_id: i32,
+    pub status: ImportJobStatus,
+    pub total: i32,
+    pub processed: i32,
+    pub error: Option<String>,
+    pub created: NaiveDateTime,
+    pub updated: NaiveDateTime,
+}
+
+impl ImportJob {
+    pub fn is_running(&self) -> bool {
+        self.status == ImportJobStatus::Running
+    }
+
+    /// Progress in percent, for the progress bar
+    pub fn percent(&self) -> i32 {
+        if self.total == 0 {
+            100
+        } else {
+            (i64::from(self.processed) * 100 / i64::from(self.total)) as i32
+        }
+    }
+}
+
+/// What a personal access token may do; `Write` includes `Read`
+#[derive(Clone, Copy, Debug, PartialEq, sqlx::Type, Deserialize, Serialize)]
+#[sqlx(type_name = "api_token_scope", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ApiTokenScope {
+    Read,
+    Write,
+}
+
+impl ApiTokenScope {
+    /// Read tokens may only use safe methods (GET, HEAD, OPTIONS)
+    pub fn allows(&self, method: &axum::http::Method) -> bool {
+        *self == ApiTokenScope::Write || method.is_safe()
+    }
+}
+
+impl Display for ApiTokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiTokenScope::Read => write!(f, "read"),
+            ApiTokenScope::Write => write!(f, "read & write"),
+        }
+    }
+}
+
+/// A personal access token, without its hash
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scope: ApiTokenScope,
+    pub created: NaiveDateTime,
+    pub expires: Option<NaiveDateTime>,
+    pub last_used: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires
+            .is_some_and(|expires| expires <= chrono::Utc::now().naive_utc())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiTokenNew {
+    pub name: String,
+    pub scope: ApiTokenScope,
+    /// 0 for tokens that don't expire
+    #[serde(default)]
+    pub expires_in_days: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct FlashcardStats {
     pub new_count: i64,
     pub for_review_count: i64,
     pub learning_count: i64,
 }
+
+impl FlashcardStats {
+    /// The totals over the decks
+    pub fn from_decks(decks: &[DeckSummary]) -> Self {
+        Self {
+            new_count: decks.iter().map(|deck| deck.new_count).sum(),
+            for_review_count: decks.iter().map(|deck| deck.due_count).sum(),
+            learning_count: decks.iter().map(|deck| deck.learning_count).sum(),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Review sessions on the glasses stored with their queue and progress, so that a session
+//! interrupted halfway (e.g. by the battery or a `stop_request`) can be resumed when the
+//! next one starts within the user's resume window. Finished sessions are kept as the history
+//! of the stats page.
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+use crate::{
+    models::{Flashcard, ReviewSession},
+    srs::SessionSummary,
+    stats::format_duration,
+};
+
+/// Number of sessions listed on the stats page
+const HISTORY_SESSIONS: i64 = 20;
+
+/// Number of failed cards named in the summary on the glasses
+const FAILED_CARDS_SHOWN: usize = 3;
+
+impl From<&ReviewSession> for SessionSummary {
+    fn from(session: &ReviewSession) -> Self {
+        let count = |value: i32| value.max(0) as u32;
+        SessionSummary {
+            easy: count(session.easy),
+            good: count(session.good),
+            difficult: count(session.difficult),
+            again: count(session.again),
+            duration_ms: session.duration_ms.max(0) as u64,
+        }
+    }
+}
+
+pub(crate) async fn create_review_session(
+    db: &PgPool,
+    user_id: &str,
+    card_ids: &[i32],
+) -> Result<ReviewSession, sqlx::Error> {
+    sqlx::query_as::<_, ReviewSession>(
+        "INSERT INTO review_session (user_id, card_ids) VALUES ($1, $2) RETURNING *",
+    )
+    .bind(user_id)
+    .bind(card_ids)
+    .fetch_one(db)
+    .await
+}
+
+/// Saves the cards left, the ratings and the failed cards so far, and finishes the session
+/// with the next due time once no cards are left
+pub(crate) async fn save_progress(
+    db: &PgPool,
+    id: i32,
+    card_ids: &[i32],
+    summary: &SessionSummary,
+    failed_card_ids: &[i32],
+) -> Result<ReviewSession, sqlx::Error> {
+    sqlx::query_as::<_, ReviewSession>(
+        r#"
+        UPDATE review_session
+        SET card_ids = $2, easy = $3, good = $4, difficult = $5, again = $6, duration_ms = $7,
+            failed_card_ids = $8,
+            updated = NOW(),
+            finished = CASE WHEN cardinality($2) = 0 THEN NOW() END,
+            next_due = CASE WHEN cardinality($2) = 0 THEN (
+                SELECT MIN(COALESCE(f.last_scheduled, NOW())) FROM flashcard f
+                INNER JOIN deck d ON f.deck_id = d.id
+                WHERE d.user_id = review_session.user_id AND NOT f.suspended
+            ) END
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(card_ids)
+    .bind(summary.easy as i32)
+    .bind(summary.good as i32)
+    .bind(summary.difficult as i32)
+    .bind(summary.again as i32)
+    .bind(summary.duration_ms as i64)
+    .bind(failed_card_ids)
+    .fetch_one(db)
+    .await
+}
+
+/// Finishes a session that won't be resumed, as a new one was started instead
+pub(crate) async fn finish_review_session(db: &PgPool, id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE review_session SET finished = NOW() WHERE id = $1 AND finished IS NULL")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// The latest unfinished session of the user with cards left, if updated within the window
+pub(crate) async fn find_resumable_session(
+    db: &PgPool,
+    user_id: &str,
+    window_minutes: i16,
+) -> Result<Option<ReviewSession>, sqlx::Error> {
+    if window_minutes <= 0 {
+        return Ok(None);
+    }
+    sqlx::query_as::<_, ReviewSession>(
+        r#"
+        SELECT * FROM review_session
+        WHERE user_id = $1
+        AND finished IS NULL
+        AND cardinality(card_ids) > 0
+        AND updated >= NOW() - make_interval(mins => $2)
+        ORDER BY updated DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(i32::from(window_minutes))
+    .fetch_optional(db)
+    .await
+}
+
+/// The latest sessions of the user with reviews, for the stats page
+pub(crate) async fn fetch_review_sessions(
+    db: &PgPool,
+    user_id: &str,
+) -> Result<Vec<ReviewSession>, sqlx::Error> {
+    sqlx::query_as::<_, ReviewSession>(
+        r#"
+        SELECT * FROM review_session
+        WHERE user_id = $1 AND easy + good + difficult + again > 0
+        ORDER BY started DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(HISTORY_SESSIONS)
+    .fetch_all(db)
+    .await
+}
+
+/// The fronts of the cards, as plain text, in the order of the ids
+pub(crate) async fn fetch_card_fronts(
+    db: &PgPool,
+    ids: &[i32],
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut cards = sqlx::query_as::<_, Flashcard>("SELECT * FROM flashcard WHERE id = ANY($1)")
+        .bind(ids)
+        .fetch_all(db)
+        .await?;
+    cards.sort_by_key(|card| ids.iter().position(|id| *id == card.id));
+    Ok(cards.iter().map(Flashcard::front_text).collect())
+}
+
+/// When the next review is due, e.g. "in 3h 20m"
+fn next_due_text(next_due: Option<NaiveDateTime>, now: NaiveDateTime) -> String {
+    match next_due {
+        None => "No cards scheduled".to_string(),
+        Some(next_due) if next_due <= now => "More cards are due now".to_string(),
+        Some(next_due) => format!(
+            "Next review in {}",
+            format_duration((next_due - now).num_milliseconds())
+        ),
+    }
+}
+
+/// The summary on the glasses at the end of a session: the cards reviewed with the ratings and
+/// the time spent, the failed cards and when the next review is due
+pub fn summary_text(
+    session: &ReviewSession,
+    failed_fronts: &[String],
+    now: NaiveDateTime,
+) -> String {
+    let mut lines = vec![
+        "Session complete!".to_string(),
+        SessionSummary::from(session).text(),
+    ];
+    if !session.failed_card_ids.is_empty() {
+        let mut failed = failed_fronts
+            .iter()
+            .take(FAILED_CARDS_SHOWN)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        let count = session.failed_card_ids.len();
+        if count > FAILED_CARDS_SHOWN {
+            failed.push_str(&format!(" and {} more", count - FAILED_CARDS_SHOWN));
+        }
+        lines.push(format!("Failed {count}: {failed}"));
+    }
+    lines.push(next_due_text(session.next_due, now));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, TimeDelta};
+
+    use super::summary_text;
+    use crate::models::ReviewSession;
+
+    #[test]
+    fn test_summary_text() {
+        let now = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let mut session = ReviewSession {
+            id: 1,
+            user_id: "user".to_string(),
+            card_ids: Vec::new(),
+            easy: 1,
+            good: 2,
+            difficult: 0,
+            again: 4,
+            duration_ms: 95_000,
+            started: now,
+            updated: now,
+            finished: Some(now),
+            failed_card_ids: vec![1, 2, 3, 4],
+            next_due: Some(now + TimeDelta::minutes(200)),
+        };
+        let fronts = ["casa", "你好", "perro", "gato"].map(str::to_string);
+        assert_eq!(
+            summary_text(&session, &fronts, now),
+            "Session complete!\nReviewed 7 cards in 1m: 1 easy, 2 good, 4 again\n\
+             Failed 4: casa, 你好, perro and 1 more\nNext review in 3h 20m"
+        );
+
+        session.again = 0;
+        session.failed_card_ids.clear();
+        session.next_due = Some(now);
+        assert_eq!(
+            summary_text(&session, &[], now),
+            "Session complete!\nReviewed 3 cards in 1m: 1 easy, 2 good\nMore cards are due now"
+        );
+    }
+}
@@ -2,31 +2,53 @@
 use std::sync::Arc;
 
 use axum::{
-    Extension, Router, middleware,
-    routing::{delete, get, post},
+    Extension, Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post, put},
 };
 use dashmap::DashMap;
 use sqlx::PgPool;
+use tokio::sync::broadcast;
 
 use crate::{
+    changes::{CardChange, card_changes},
     config::AppConfig,
     routes,
     sdk::{
         app_session::AppSession, auth::auth_middleware, health_handler, settings_handler,
-        tool_get_handler, tool_handler, webhook_handler,
+        spawn_session_reaper, tool_get_handler, tool_handler, webhook_handler,
     },
+    srs::SessionState,
 };
 
 pub struct AppState {
     pub db: Arc<PgPool>,
     pub active_sessions: DashMap<String, AppSession>,
+    /// The review state of the active sessions, by session id
+    pub review_sessions: DashMap<String, Arc<SessionState>>,
+    /// Changes to cards, for the active sessions
+    pub card_changes: broadcast::Sender<CardChange>,
+}
+
+impl AppState {
+    /// The review state of an active session of the user on the glasses
+    pub fn review_session(&self, user_id: &str) -> Option<Arc<SessionState>> {
+        self.review_sessions
+            .iter()
+            .find(|entry| entry.value().user_id().0 == user_id)
+            .map(|entry| entry.value().clone())
+    }
 }
 
 pub fn init_router(db: PgPool, config: AppConfig) -> Router {
     let state = Arc::new(AppState {
         db: Arc::new(db),
         active_sessions: DashMap::new(),
+        review_sessions: DashMap::new(),
+        card_changes: card_changes(),
     });
+    spawn_session_reaper(state.clone());
     // Create webhook routes that bypass authentication
     let webhook_routes = Router::new()
         .route("/webhook", post(webhook_handler))
@@ -39,15 +61,50 @@ pub fn init_router(db: PgPool, config: AppConfig) -> Router {
         .route("/health", get(health_handler))
         .route("/webview", get(routes::webview_handler))
         .route("/styles.css", get(routes::styles))
+        .route("/backup", get(routes::download_backup))
+        .route(
+            "/backup/restore",
+            post(routes::upload_backup).layer(DefaultBodyLimit::max(64 * 1024 * 1024)),
+        )
+        .route(
+            "/tokens",
+            get(routes::list_api_tokens).post(routes::create_api_token),
+        )
+        .route("/tokens/{id}", delete(routes::revoke_api_token))
+        .route("/search", get(routes::search_page))
+        .route("/search/results", get(routes::search_results))
+        .route("/stats", get(routes::stats_page))
+        .route("/review", get(routes::review_page))
+        .route("/review/rate", post(routes::rate_card))
+        .route("/session", get(routes::session_page))
+        .route("/session/events", get(routes::session_events))
+        .route("/session/command", post(routes::session_command))
+        .route("/browse", get(routes::browse_page))
+        .route("/browse/rows", get(routes::browse_rows))
+        .route("/browse/bulk", post(routes::bulk_update))
         .route("/decks", get(routes::fetch_decks).post(routes::create_deck))
         .route(
             "/decks/{id}",
             delete(routes::delete_deck).put(routes::update_deck),
         )
+        .route("/decks/{id}/speech", put(routes::update_speech))
         .route(
             "/decks/{id}/import",
             get(routes::show_import_form).post(routes::import_deck),
         )
+        .route("/decks/{id}/import/preview", post(routes::preview_import))
+        .route(
+            "/decks/{id}/import/jobs/{job_id}",
+            get(routes::get_import_job),
+        )
+        .route(
+            "/decks/{id}/import/jobs/{job_id}/cancel",
+            post(routes::cancel_import),
+        )
+        .route(
+            "/decks/{id}/import/upload",
+            post(routes::upload_import).layer(DefaultBodyLimit::max(config.max_upload_bytes)),
+        )
         .route(
             "/decks/{deck_id}/flashcards",
             get(routes::view_flashcards_page).post(routes::create_flashcard),
@@ -62,6 +119,7 @@ pub fn init_router(db: PgPool, config: AppConfig) -> Router {
                 .put(routes::update_flashcard)
                 .delete(routes::delete_flashcard),
         )
+        .nest("/api/v1", routes::api::router(config.max_upload_bytes))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -1,9 +1,24 @@
 //! All route handlers for the application.
+pub mod api;
+mod api_token;
+mod backup;
+mod browse;
 mod deck;
 mod flashcard;
+mod review;
+mod search;
+mod session;
+mod stats;
 
+pub use api_token::*;
+pub use backup::*;
+pub use browse::*;
 pub use deck::*;
 pub use flashcard::*;
+pub use review::*;
+pub use search::*;
+pub use session::*;
+pub use stats::*;
 
 use askama::Template;
 use axum::{
@@ -11,10 +26,13 @@ use axum::{
     http::StatusCode,
     response::{Html, IntoResponse, Response},
 };
+use sqlx::{PgPool, Row};
 use tracing::{error, warn};
 
 use crate::{
+    answer::compile_pattern,
     errors::ApiError,
+    models::{Deck, DeckSpeech, DeckSummary, FlashcardStats},
     sdk::{app_session::UserId, auth::AuthUser},
     templates::WebViewTemplate,
 };
@@ -29,6 +47,107 @@ fn check_user_id(user_id: Option<UserId>) -> Result<String, ApiError> {
     Ok(user_id.0)
 }
 
+/// Fetches a deck of the user; the decks of other users are not found
+async fn fetch_user_deck(db: &PgPool, user_id: &str, deck_id: i32) -> Result<Deck, ApiError> {
+    let deck = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 AND id = $2")
+        .bind(user_id)
+        .bind(deck_id)
+        .fetch_one(db)
+        .await?;
+    Ok(deck)
+}
+
+/// Sets how a deck of the user is read aloud; empty voices and languages are the defaults
+async fn update_deck_speech(
+    db: &PgPool,
+    user_id: &str,
+    deck_id: i32,
+    speech: &DeckSpeech,
+) -> Result<Deck, ApiError> {
+    let deck = sqlx::query_as::<_, Deck>(
+        r#"
+        UPDATE deck
+        SET speak_front = $3, speak_back = $4,
+            front_voice = NULLIF(TRIM($5), ''), front_language = NULLIF(TRIM($6), ''),
+            back_voice = NULLIF(TRIM($7), ''), back_language = NULLIF(TRIM($8), '')
+        WHERE id = $1 AND user_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(deck_id)
+    .bind(user_id)
+    .bind(speech.speak_front)
+    .bind(speech.speak_back)
+    .bind(&speech.front_voice)
+    .bind(&speech.front_language)
+    .bind(&speech.back_voice)
+    .bind(&speech.back_language)
+    .fetch_one(db)
+    .await?;
+    Ok(deck)
+}
+
+/// Rejects accepted answers that are not a valid regular expression
+fn check_accepted_answers(pattern: Option<&str>) -> Result<(), ApiError> {
+    match pattern.map(compile_pattern) {
+        Some(Err(e)) => Err(ApiError::BadRequest(format!(
+            "The accepted answers are not a valid regular expression: {e}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The decks of the user (or one deck) with their card counts, from one grouped query
+async fn fetch_deck_summaries(
+    db: &PgPool,
+    user_id: &str,
+    deck_id: Option<i32>,
+) -> Result<Vec<DeckSummary>, ApiError> {
+    let decks = sqlx::query_as::<_, DeckSummary>(
+        r#"
+        SELECT d.id, d.name,
+            COUNT(f.id) FILTER (WHERE f.last_rating IS NULL AND NOT f.suspended) AS new_count,
+            COUNT(f.id) FILTER (WHERE f.last_scheduled <= NOW() AND NOT f.suspended) AS due_count,
+            COUNT(f.id) FILTER (WHERE f.last_scheduled > NOW() AND NOT f.suspended)
+                AS learning_count,
+            COUNT(f.id) AS total_count,
+            MIN(f.last_scheduled) FILTER (WHERE NOT f.suspended) AS next_due,
+            MAX(f.last_reviewed) AS last_studied
+        FROM deck d
+        LEFT JOIN flashcard f ON f.deck_id = d.id
+        WHERE d.user_id = $1 AND ($2::INTEGER IS NULL OR d.id = $2)
+        GROUP BY d.id
+        ORDER BY d.id
+        "#,
+    )
+    .bind(user_id)
+    .bind(deck_id)
+    .fetch_all(db)
+    .await?;
+    Ok(decks)
+}
+
+/// Statistics for all flashcards across all decks of the user
+async fn fetch_flashcard_stats(db: &PgPool, user_id: &str) -> Result<FlashcardStats, ApiError> {
+    let stats_query = r#"
+        SELECT
+            COUNT(CASE WHEN last_rating IS NULL THEN 1 END) as new_count,
+            COUNT(CASE WHEN last_scheduled IS NOT NULL AND last_scheduled <= NOW() THEN 1 END) as for_review_count,
+            COUNT(CASE WHEN last_scheduled IS NOT NULL AND last_scheduled > NOW() THEN 1 END) as learning_count
+        FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1 AND NOT f.suspended
+    "#;
+
+    let stats_row = sqlx::query(stats_query).bind(user_id).fetch_one(db).await?;
+
+    Ok(FlashcardStats {
+        new_count: stats_row.get("new_count"),
+        for_review_count: stats_row.get("for_review_count"),
+        learning_count: stats_row.get("learning_count"),
+    })
+}
+
 fn handle_render(res: askama::Result<String>) -> Result<Html<String>, ApiError> {
     match res {
         Ok(html) => Ok(Html(html)),
@@ -0,0 +1,217 @@
+//! Versioned JSON API (`/api/v1`) for scripts and other clients.
+//! The handlers use the same ownership checks as the HTML routes, all errors are
+//! returned as `ErrorBody` JSON, and the OpenAPI document is served at `/api/v1/openapi.json`.
+mod deck;
+mod flashcard;
+mod import;
+mod settings;
+
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    body::to_bytes,
+    extract::DefaultBodyLimit,
+    http::{StatusCode, header},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+};
+use serde::Serialize;
+use utoipa::{
+    Modify, OpenApi, ToSchema,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+
+use crate::router::AppState;
+
+/// Maximum size of error bodies that are converted to JSON
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// The body of all error responses
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// The HTTP status code
+    pub status: u16,
+    pub error: String,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "MemCards API",
+        description = "JSON API for decks, flashcards, imports, stats and settings. \
+            Errors are returned as `ErrorBody`."
+    ),
+    servers((url = "/")),
+    paths(
+        deck::list_decks,
+        deck::create_deck,
+        deck::get_deck,
+        deck::update_deck,
+        deck::update_speech,
+        deck::delete_deck,
+        flashcard::list_flashcards,
+        flashcard::create_flashcard,
+        flashcard::get_flashcard,
+        flashcard::update_flashcard,
+        flashcard::delete_flashcard,
+        flashcard::list_reviews,
+        flashcard::search,
+        flashcard::bulk_update,
+        import::create_import,
+        import::get_import,
+        import::cancel_import,
+        settings::get_stats,
+        settings::get_dashboard,
+        settings::get_settings,
+        settings::update_settings,
+    ),
+    components(schemas(ErrorBody)),
+    modifiers(&BearerAuth),
+    security(("bearer" = [])),
+    tags(
+        (name = "decks"),
+        (name = "flashcards"),
+        (name = "imports", description = "Imports run as background jobs"),
+        (name = "settings"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Requests are authenticated like the webview, e.g. with a frontend token
+/// in the `Authorization: Bearer` header
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+async fn not_found() -> StatusCode {
+    StatusCode::NOT_FOUND
+}
+
+/// Converts the plain text error responses of the handlers (`ApiError`)
+/// and of the extractors into `ErrorBody` JSON
+async fn json_errors(response: Response) -> Response {
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+    let body = to_bytes(response.into_body(), MAX_ERROR_BODY_BYTES)
+        .await
+        .unwrap_or_default();
+    let mut error = String::from_utf8_lossy(&body).trim().to_string();
+    if error.is_empty() {
+        error = status.canonical_reason().unwrap_or("Error").to_string();
+    }
+    let body = ErrorBody {
+        status: status.as_u16(),
+        error,
+    };
+    (status, Json(body)).into_response()
+}
+
+pub fn router(max_upload_bytes: usize) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/decks", get(deck::list_decks).post(deck::create_deck))
+        .route(
+            "/decks/{id}",
+            get(deck::get_deck)
+                .put(deck::update_deck)
+                .delete(deck::delete_deck),
+        )
+        .route("/decks/{id}/speech", put(deck::update_speech))
+        .route(
+            "/decks/{id}/flashcards",
+            get(flashcard::list_flashcards).post(flashcard::create_flashcard),
+        )
+        .route(
+            "/decks/{id}/imports",
+            post(import::create_import).layer(DefaultBodyLimit::max(max_upload_bytes)),
+        )
+        .route(
+            "/flashcards/{id}",
+            get(flashcard::get_flashcard)
+                .put(flashcard::update_flashcard)
+                .delete(flashcard::delete_flashcard),
+        )
+        .route("/flashcards/{id}/reviews", get(flashcard::list_reviews))
+        .route("/flashcards/bulk", post(flashcard::bulk_update))
+        .route("/search", get(flashcard::search))
+        .route("/imports/{id}", get(import::get_import))
+        .route("/imports/{id}/cancel", post(import::cancel_import))
+        .route("/stats", get(settings::get_stats))
+        .route("/stats/dashboard", get(settings::get_dashboard))
+        .route(
+            "/settings",
+            get(settings::get_settings).put(settings::update_settings),
+        )
+        .fallback(not_found)
+        .layer(middleware::map_response(json_errors))
+}
+
+/// Turns unique constraint violations (e.g. duplicate deck names) into a bad request
+fn unique_violation(e: sqlx::Error, message: &str) -> crate::errors::ApiError {
+    match &e {
+        sqlx::Error::Database(db_error) if db_error.is_unique_violation() => {
+            crate::errors::ApiError::BadRequest(message.to_string())
+        }
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        http::StatusCode,
+        response::{IntoResponse, Response},
+    };
+
+    use crate::errors::ApiError;
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_errors() {
+        let response =
+            super::json_errors(ApiError::SQLError(sqlx::Error::RowNotFound).into_response()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            body_json(response).await,
+            serde_json::json!({ "status": 404, "error": "Not found" })
+        );
+
+        let response = super::json_errors(StatusCode::METHOD_NOT_ALLOWED.into_response()).await;
+        assert_eq!(body_json(response).await["error"], "Method Not Allowed");
+    }
+
+    #[test]
+    fn test_openapi() {
+        use utoipa::OpenApi;
+
+        let doc = super::ApiDoc::openapi();
+        assert!(doc.paths.paths.contains_key("/api/v1/decks/{id}/imports"));
+    }
+}
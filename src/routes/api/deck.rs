@@ -0,0 +1,173 @@
+//! Deck endpoints of the JSON API
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+use crate::{
+    errors::ApiError,
+    models::{Deck, DeckNew, DeckSpeech},
+    router::AppState,
+    routes::{api::unique_violation, check_user_id, fetch_user_deck, update_deck_speech},
+    sdk::auth::AuthUser,
+};
+
+const DUPLICATE_DECK: &str = "A deck with this name already exists";
+
+fn check_name(deck: &DeckNew) -> Result<(), ApiError> {
+    if deck.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("The deck name is empty".to_string()));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/decks",
+    tag = "decks",
+    responses((status = 200, body = [Deck]))
+)]
+pub async fn list_decks(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Deck>>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let decks = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 ORDER BY id")
+        .bind(&user_id)
+        .fetch_all(&*state.db)
+        .await?;
+
+    Ok(Json(decks))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/decks",
+    tag = "decks",
+    request_body = DeckNew,
+    responses((status = 201, body = Deck))
+)]
+pub async fn create_deck(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<DeckNew>,
+) -> Result<(StatusCode, Json<Deck>), ApiError> {
+    let user_id = check_user_id(user_id)?;
+    check_name(&body)?;
+    let deck = sqlx::query_as::<_, Deck>(
+        "INSERT INTO deck (name, user_id, answer_mode) VALUES ($1, $2, $3) RETURNING *",
+    )
+    .bind(body.name.trim())
+    .bind(user_id)
+    .bind(body.answer_mode.unwrap_or_default())
+    .fetch_one(&*state.db)
+    .await
+    .map_err(|e| unique_violation(e, DUPLICATE_DECK))?;
+
+    Ok((StatusCode::CREATED, Json(deck)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/decks/{id}",
+    tag = "decks",
+    params(("id" = i32, Path)),
+    responses((status = 200, body = Deck))
+)]
+pub async fn get_deck(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Deck>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
+
+    Ok(Json(deck))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/decks/{id}",
+    tag = "decks",
+    params(("id" = i32, Path)),
+    request_body = DeckNew,
+    responses((status = 200, body = Deck))
+)]
+pub async fn update_deck(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(body): Json<DeckNew>,
+) -> Result<Json<Deck>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    check_name(&body)?;
+    let deck = sqlx::query_as::<_, Deck>(
+        r#"
+        UPDATE deck SET name = $1, answer_mode = COALESCE($4, answer_mode)
+        WHERE id = $2 AND user_id = $3
+        RETURNING *
+        "#,
+    )
+    .bind(body.name.trim())
+    .bind(id)
+    .bind(&user_id)
+    .bind(body.answer_mode)
+    .fetch_one(&*state.db)
+    .await
+    .map_err(|e| unique_violation(e, DUPLICATE_DECK))?;
+    state.notify_deck(&user_id, deck.id);
+
+    Ok(Json(deck))
+}
+
+/// Sets how the cards of the deck are read aloud on the glasses
+#[utoipa::path(
+    put,
+    path = "/api/v1/decks/{id}/speech",
+    tag = "decks",
+    params(("id" = i32, Path)),
+    request_body = DeckSpeech,
+    responses((status = 200, body = Deck))
+)]
+pub async fn update_speech(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(body): Json<DeckSpeech>,
+) -> Result<Json<Deck>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let deck = update_deck_speech(&state.db, &user_id, id, &body).await?;
+    state.notify_deck(&user_id, deck.id);
+
+    Ok(Json(deck))
+}
+
+/// Deletes the deck with all its flashcards
+#[utoipa::path(
+    delete,
+    path = "/api/v1/decks/{id}",
+    tag = "decks",
+    params(("id" = i32, Path)),
+    responses((status = 204))
+)]
+pub async fn delete_deck(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let result = sqlx::query("DELETE FROM deck WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(&user_id)
+        .execute(&*state.db)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    state.notify_deck(&user_id, id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
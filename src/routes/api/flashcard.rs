@@ -0,0 +1,300 @@
+//! Flashcard endpoints of the JSON API
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    bulk::{BulkRequest, BulkSummary, apply_bulk},
+    errors::ApiError,
+    models::{Flashcard, FlashcardReview, FlashcardUpdate, parse_tags},
+    router::AppState,
+    routes::{api::unique_violation, check_accepted_answers, check_user_id, fetch_user_deck},
+    sdk::auth::AuthUser,
+    search::{SearchPage, SearchQuery, search_flashcards},
+};
+
+const DUPLICATE_FLASHCARD: &str = "A flashcard with this front already exists in the deck";
+
+/// Maximum number of flashcards per page
+const MAX_PAGE_SIZE: u32 = 500;
+
+#[derive(Deserialize, IntoParams)]
+pub struct FlashcardsQuery {
+    /// Page number, starting at 0
+    pub page: Option<u32>,
+    /// Flashcards per page (default 50, at most 500)
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FlashcardPage {
+    pub flashcards: Vec<Flashcard>,
+    pub page: u32,
+    pub has_more: bool,
+}
+
+fn check_card(card: &FlashcardUpdate) -> Result<(), ApiError> {
+    if card.front.trim().is_empty() || card.back.trim().is_empty() {
+        return Err(ApiError::BadRequest(
+            "The front and back must not be empty".to_string(),
+        ));
+    }
+    check_accepted_answers(card.accepted_answers.as_deref())
+}
+
+async fn fetch_user_flashcard(
+    state: &AppState,
+    user_id: &str,
+    id: i32,
+) -> Result<Flashcard, ApiError> {
+    let flashcard = sqlx::query_as::<_, Flashcard>(
+        r#"
+        SELECT * FROM flashcard
+        WHERE id = $1 AND deck_id IN (
+            SELECT id FROM deck WHERE user_id = $2
+        )
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_one(&*state.db)
+    .await?;
+    Ok(flashcard)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/decks/{id}/flashcards",
+    tag = "flashcards",
+    params(("id" = i32, Path), FlashcardsQuery),
+    responses((status = 200, body = FlashcardPage))
+)]
+pub async fn list_flashcards(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<FlashcardsQuery>,
+) -> Result<Json<FlashcardPage>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
+    let page = query.page.unwrap_or(0);
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_PAGE_SIZE);
+
+    // Get one extra to check if there are more
+    let mut flashcards = sqlx::query_as::<_, Flashcard>(
+        "SELECT * FROM flashcard WHERE deck_id = $1 ORDER BY id LIMIT $2 OFFSET $3",
+    )
+    .bind(deck.id)
+    .bind(i64::from(limit) + 1)
+    .bind(i64::from(page) * i64::from(limit))
+    .fetch_all(&*state.db)
+    .await?;
+    let has_more = flashcards.len() > limit as usize;
+    flashcards.truncate(limit as usize);
+
+    Ok(Json(FlashcardPage {
+        flashcards,
+        page,
+        has_more,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/decks/{id}/flashcards",
+    tag = "flashcards",
+    params(("id" = i32, Path)),
+    request_body = FlashcardUpdate,
+    responses((status = 201, body = Flashcard))
+)]
+pub async fn create_flashcard(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(body): Json<FlashcardUpdate>,
+) -> Result<(StatusCode, Json<Flashcard>), ApiError> {
+    let user_id = check_user_id(user_id)?;
+    check_card(&body)?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
+    let flashcard = sqlx::query_as::<_, Flashcard>(
+        r#"
+        INSERT INTO flashcard (deck_id, front, back, tags, accepted_answers)
+        VALUES ($1, $2, $3, $4, NULLIF(TRIM($5), ''))
+        RETURNING *
+        "#,
+    )
+    .bind(deck.id)
+    .bind(body.front)
+    .bind(body.back)
+    .bind(parse_tags(body.tags.as_deref().unwrap_or_default()))
+    .bind(body.accepted_answers)
+    .fetch_one(&*state.db)
+    .await
+    .map_err(|e| unique_violation(e, DUPLICATE_FLASHCARD))?;
+
+    Ok((StatusCode::CREATED, Json(flashcard)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/flashcards/{id}",
+    tag = "flashcards",
+    params(("id" = i32, Path)),
+    responses((status = 200, body = Flashcard))
+)]
+pub async fn get_flashcard(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Flashcard>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let flashcard = fetch_user_flashcard(&state, &user_id, id).await?;
+
+    Ok(Json(flashcard))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/flashcards/{id}",
+    tag = "flashcards",
+    params(("id" = i32, Path)),
+    request_body = FlashcardUpdate,
+    responses((status = 200, body = Flashcard))
+)]
+pub async fn update_flashcard(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(body): Json<FlashcardUpdate>,
+) -> Result<Json<Flashcard>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    check_card(&body)?;
+    let flashcard = sqlx::query_as::<_, Flashcard>(
+        r#"
+        UPDATE flashcard
+        SET front = $1, back = $2, tags = COALESCE($5, tags),
+            accepted_answers = CASE
+                WHEN $6::TEXT IS NULL THEN accepted_answers ELSE NULLIF(TRIM($6), '')
+            END
+        WHERE id = $3 AND deck_id IN (
+            SELECT id FROM deck WHERE user_id = $4
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(body.front)
+    .bind(body.back)
+    .bind(id)
+    .bind(&user_id)
+    .bind(body.tags.as_deref().map(parse_tags))
+    .bind(body.accepted_answers)
+    .fetch_one(&*state.db)
+    .await
+    .map_err(|e| unique_violation(e, DUPLICATE_FLASHCARD))?;
+    state.notify_cards(&user_id, vec![id]);
+
+    Ok(Json(flashcard))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/flashcards/{id}",
+    tag = "flashcards",
+    params(("id" = i32, Path)),
+    responses((status = 204))
+)]
+pub async fn delete_flashcard(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let result = sqlx::query(
+        r#"
+        DELETE FROM flashcard
+        WHERE id = $1 AND deck_id IN (
+            SELECT id FROM deck WHERE user_id = $2
+        )
+        "#,
+    )
+    .bind(id)
+    .bind(&user_id)
+    .execute(&*state.db)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    state.notify_cards(&user_id, vec![id]);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The review history of a flashcard, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/flashcards/{id}/reviews",
+    tag = "flashcards",
+    params(("id" = i32, Path)),
+    responses((status = 200, body = [FlashcardReview]))
+)]
+pub async fn list_reviews(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<FlashcardReview>>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let flashcard = fetch_user_flashcard(&state, &user_id, id).await?;
+    let reviews = sqlx::query_as::<_, FlashcardReview>(
+        "SELECT * FROM review_log WHERE flashcard_id = $1 ORDER BY reviewed DESC",
+    )
+    .bind(flashcard.id)
+    .fetch_all(&*state.db)
+    .await?;
+
+    Ok(Json(reviews))
+}
+
+/// Searches the front and back of the flashcards in all decks
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    tag = "flashcards",
+    params(SearchQuery),
+    responses((status = 200, body = SearchPage))
+)]
+pub async fn search(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchPage>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let results = search_flashcards(&state.db, &user_id, &query).await?;
+
+    Ok(Json(results))
+}
+
+/// Applies an action to several flashcards in one transaction
+#[utoipa::path(
+    post,
+    path = "/api/v1/flashcards/bulk",
+    tag = "flashcards",
+    request_body = BulkRequest,
+    responses((status = 200, body = BulkSummary))
+)]
+pub async fn bulk_update(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BulkRequest>,
+) -> Result<Json<BulkSummary>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let summary = apply_bulk(&state.db, &user_id, &request).await?;
+    state.notify_cards(&user_id, request.ids);
+
+    Ok(Json(summary))
+}
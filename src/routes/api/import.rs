@@ -0,0 +1,101 @@
+//! Import endpoints of the JSON API
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    errors::ApiError,
+    import::{ImportFormat, cancel_import_job, parse_text, start_import_job},
+    models::ImportJob,
+    router::AppState,
+    routes::{check_user_id, fetch_user_deck},
+    sdk::auth::AuthUser,
+};
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImportRequest {
+    /// The exported text
+    pub text: String,
+    /// The format of the text; detected if missing
+    pub format: Option<ImportFormat>,
+    /// Index of the front field of Anki exports (default 0)
+    pub front_idx: Option<usize>,
+    /// Index of the back field of Anki exports (default 1)
+    pub back_idx: Option<usize>,
+}
+
+/// Starts an import into the deck; poll the returned job for its progress
+#[utoipa::path(
+    post,
+    path = "/api/v1/decks/{id}/imports",
+    tag = "imports",
+    params(("id" = i32, Path)),
+    request_body = ImportRequest,
+    responses((status = 202, body = ImportJob))
+)]
+pub async fn create_import(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(body): Json<ImportRequest>,
+) -> Result<(StatusCode, Json<ImportJob>), ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
+    let flashcards = parse_text(
+        body.format,
+        body.front_idx.unwrap_or(0),
+        body.back_idx.unwrap_or(1),
+        &body.text,
+    );
+    let job = start_import_job(state.db.clone(), &user_id, deck.id, flashcards).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/imports/{id}",
+    tag = "imports",
+    params(("id" = i32, Path)),
+    responses((status = 200, body = ImportJob))
+)]
+pub async fn get_import(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<ImportJob>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let job =
+        sqlx::query_as::<_, ImportJob>("SELECT * FROM import_job WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(&user_id)
+            .fetch_one(&*state.db)
+            .await?;
+
+    Ok(Json(job))
+}
+
+/// Cancels a running import; the flashcards stored before are kept
+#[utoipa::path(
+    post,
+    path = "/api/v1/imports/{id}/cancel",
+    tag = "imports",
+    params(("id" = i32, Path)),
+    responses((status = 200, body = ImportJob))
+)]
+pub async fn cancel_import(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<ImportJob>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let job = cancel_import_job(&state.db, &user_id, None, id).await?;
+
+    Ok(Json(job))
+}
@@ -0,0 +1,144 @@
+//! Stats and settings endpoints of the JSON API
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+};
+
+use crate::{
+    errors::ApiError,
+    models::{FlashcardStats, StoredUserSettings},
+    router::AppState,
+    routes::{check_user_id, fetch_flashcard_stats, fetch_user_deck},
+    sdk::auth::AuthUser,
+    srs::{
+        MAX_RESUME_WINDOW_MINUTES, UserSettings, load_user_settings, save_user_settings,
+        switch_transcription,
+    },
+    stats::{Dashboard, StatsQuery, fetch_dashboard},
+    vocabulary::is_supported,
+};
+
+/// Card counts across all decks
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats",
+    tag = "decks",
+    responses((status = 200, body = FlashcardStats))
+)]
+pub async fn get_stats(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FlashcardStats>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let stats = fetch_flashcard_stats(&state.db, &user_id).await?;
+
+    Ok(Json(stats))
+}
+
+/// Review history, retention, answer times and the due forecast, for all decks or one deck
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/dashboard",
+    tag = "decks",
+    params(StatsQuery),
+    responses((status = 200, body = Dashboard))
+)]
+pub async fn get_dashboard(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<Dashboard>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    if let Some(deck_id) = query.deck_id {
+        fetch_user_deck(&state.db, &user_id, deck_id).await?;
+    }
+    let dashboard = fetch_dashboard(&state.db, &user_id, query.deck_id).await?;
+
+    Ok(Json(dashboard))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings",
+    tag = "settings",
+    responses((status = 200, body = StoredUserSettings))
+)]
+pub async fn get_settings(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<StoredUserSettings>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let settings = load_user_settings(&state.db, &user_id).await?;
+
+    Ok(Json(settings))
+}
+
+/// Updates the settings, including those of a running session on the glasses
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings",
+    tag = "settings",
+    request_body = StoredUserSettings,
+    responses((status = 200, body = StoredUserSettings))
+)]
+pub async fn update_settings(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<StoredUserSettings>,
+) -> Result<Json<StoredUserSettings>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let in_range = |value: i16| (1..=100).contains(&value);
+    if !in_range(body.max_cards_per_session) || !in_range(body.desired_retention) {
+        return Err(ApiError::BadRequest(
+            "Settings must be between 1 and 100".to_string(),
+        ));
+    }
+    if !(0..=MAX_RESUME_WINDOW_MINUTES).contains(&body.resume_window_minutes) {
+        return Err(ApiError::BadRequest(format!(
+            "The resume window must be between 0 and {MAX_RESUME_WINDOW_MINUTES} minutes"
+        )));
+    }
+    if !is_supported(&body.transcription_locale) {
+        return Err(ApiError::BadRequest(format!(
+            "Unsupported transcription locale: {}",
+            body.transcription_locale
+        )));
+    }
+    let max_cards_per_session = body.max_cards_per_session as u8;
+    let desired_retention = body.desired_retention as u8;
+
+    let settings = UserSettings::new(max_cards_per_session, desired_retention);
+    settings.set_transcription_locale(&body.transcription_locale);
+    settings.set_resume_window_minutes(body.resume_window_minutes);
+    settings.set_input_mapping(body.input_mapping);
+    save_user_settings(&state.db, &user_id.clone().into(), &settings)
+        .await
+        .map_err(|e| match e.downcast::<sqlx::Error>() {
+            Ok(e) => ApiError::SQLError(e),
+            Err(e) => ApiError::BadRequest(e.to_string()),
+        })?;
+    for session in state.active_sessions.iter() {
+        if session.user_id.0 == user_id {
+            session
+                .user_settings
+                .set_max_cards_per_session(max_cards_per_session);
+            session
+                .user_settings
+                .set_desired_retention(desired_retention);
+            session
+                .user_settings
+                .set_transcription_locale(&body.transcription_locale);
+            session
+                .user_settings
+                .set_resume_window_minutes(body.resume_window_minutes);
+            session.user_settings.set_input_mapping(body.input_mapping);
+        }
+    }
+    if let Some(session_state) = state.review_session(&user_id) {
+        switch_transcription(session_state);
+    }
+
+    Ok(Json(body))
+}
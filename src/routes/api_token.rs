@@ -0,0 +1,63 @@
+//! Handlers for managing personal access tokens from the webview.
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    Extension, Form,
+    extract::{Path, State},
+    response::IntoResponse,
+};
+
+use crate::{
+    api_token::{create_token, list_tokens, revoke_token},
+    errors::ApiError,
+    models::ApiTokenNew,
+    router::AppState,
+    routes::{check_user_id, handle_render},
+    sdk::auth::AuthUser,
+    templates::ApiTokensTemplate,
+};
+
+async fn render_tokens(
+    state: &AppState,
+    user_id: &str,
+    new_token: Option<String>,
+) -> Result<impl IntoResponse + use<>, ApiError> {
+    let tokens = list_tokens(&state.db, user_id).await?;
+    let template = ApiTokensTemplate { tokens, new_token };
+    handle_render(template.render())
+}
+
+pub async fn list_api_tokens(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    render_tokens(&state, &user_id, None).await
+}
+
+/// Creates a token, which is shown once in the returned list
+pub async fn create_api_token(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<ApiTokenNew>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    if form.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("The token name is empty".to_string()));
+    }
+    let token = create_token(&state.db, &user_id, &form).await?;
+    render_tokens(&state, &user_id, Some(token)).await
+}
+
+pub async fn revoke_api_token(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    if !revoke_token(&state.db, &user_id, id).await? {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    render_tokens(&state, &user_id, None).await
+}
@@ -0,0 +1,58 @@
+//! Handlers for account backup and restore routes.
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::{HeaderMap, header},
+    response::IntoResponse,
+};
+use chrono::Utc;
+
+use crate::{
+    backup::{BACKUP_VERSION, Backup, export_backup, restore_backup},
+    errors::ApiError,
+    router::AppState,
+    routes::{check_user_id, handle_render},
+    sdk::auth::AuthUser,
+    templates::RestoreSummaryTemplate,
+};
+
+pub async fn download_backup(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let backup = export_backup(&state.db, &user_id).await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        format!(
+            "attachment; filename=\"memcards-backup-{}.json\"",
+            Utc::now().format("%Y-%m-%d")
+        )
+        .parse()
+        .unwrap(),
+    );
+    Ok((headers, Json(backup)))
+}
+
+pub async fn upload_backup(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Json(backup): Json<Backup>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    if backup.version > BACKUP_VERSION {
+        return Err(ApiError::BadRequest(format!(
+            "Unsupported backup version {} (this instance supports up to {BACKUP_VERSION})",
+            backup.version
+        )));
+    }
+    let summary = restore_backup(&state.db, &user_id, backup).await?;
+
+    let template = RestoreSummaryTemplate { summary };
+    handle_render(template.render())
+}
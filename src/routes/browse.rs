@@ -0,0 +1,83 @@
+//! Handlers for the card browser.
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    Extension,
+    extract::{Query, RawQuery, State},
+    http::HeaderMap,
+    http::HeaderValue,
+    response::IntoResponse,
+};
+use axum_extra::extract::Form;
+
+use crate::{
+    browse::{BrowseQuery, browse_flashcards},
+    bulk::{BulkRequest, apply_bulk},
+    errors::ApiError,
+    models::Deck,
+    router::AppState,
+    routes::{check_user_id, handle_render},
+    sdk::auth::AuthUser,
+    templates::{BrowseRowsTemplate, BrowseTemplate, BulkSummaryTemplate},
+};
+
+pub async fn browse_page(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BrowseQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let decks = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 ORDER BY name")
+        .bind(&user_id)
+        .fetch_all(&*state.db)
+        .await?;
+
+    let template = BrowseTemplate {
+        is_authenticated: true,
+        query,
+        decks,
+    };
+    handle_render(template.render())
+}
+
+pub async fn browse_rows(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BrowseQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let results = browse_flashcards(&state.db, &user_id, &query).await?;
+
+    // Keep the filters and sorting in the page URL, so that views can be bookmarked
+    let mut headers = HeaderMap::new();
+    let url = match raw_query {
+        Some(raw_query) => format!("/browse?{raw_query}"),
+        None => "/browse".to_string(),
+    };
+    if let Ok(url) = url.parse() {
+        headers.insert("HX-Replace-Url", url);
+    }
+
+    let template = BrowseRowsTemplate { query, results };
+    Ok((headers, handle_render(template.render())?))
+}
+
+/// Applies a bulk action to the cards selected in the browser
+pub async fn bulk_update(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Form(request): Form<BulkRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let summary = apply_bulk(&state.db, &user_id, &request).await?;
+    state.notify_cards(&user_id, request.ids);
+
+    // Reload the rows, as cards were changed or removed
+    let mut headers = HeaderMap::new();
+    headers.insert("HX-Trigger", HeaderValue::from_static("browse-refresh"));
+
+    let template = BulkSummaryTemplate { summary };
+    Ok((headers, handle_render(template.render())?))
+}
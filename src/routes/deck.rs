@@ -3,19 +3,31 @@ use std::sync::Arc;
 
 use askama::Template;
 use axum::{
-    Extension, Form,
-    extract::{Path, State},
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    Extension, Form, Json,
+    extract::{
+        Multipart, Path, State,
+        multipart::{Field, MultipartError},
+    },
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
-use sqlx::Row;
+use chrono::Utc;
+use tokio::io::AsyncWriteExt;
 
 use crate::{
     errors::ApiError,
-    import::import_anki_text_to_db,
-    models::{Deck, DeckNew, FlashcardImport},
+    import::{
+        AnkiTextParser, ImportFormat, ImportPreview, Importer, PREVIEW_LINES, StreamingDecoder,
+        StreamingImport, cancel_import_job, parse_text, read_apkg_notes, start_import_job,
+    },
+    models::{
+        Deck, DeckNew, DeckSpeech, DeckSummary, FlashcardImport, FlashcardImportPreview,
+        FlashcardStats, ImportJob, ImportJobStatus,
+    },
     router::AppState,
-    routes::{check_user_id, handle_render},
+    routes::{
+        check_user_id, fetch_deck_summaries, fetch_user_deck, handle_render, update_deck_speech,
+    },
     sdk::auth::AuthUser,
     templates::{self},
 };
@@ -25,32 +37,8 @@ pub async fn fetch_decks(
     State(state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, ApiError> {
     let user_id = check_user_id(user_id)?;
-    let decks = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1")
-        .bind(&user_id)
-        .fetch_all(&*state.db)
-        .await?;
-
-    // Calculate statistics for all flashcards across all decks
-    let stats_query = r#"
-        SELECT
-            COUNT(CASE WHEN last_rating IS NULL THEN 1 END) as new_count,
-            COUNT(CASE WHEN last_scheduled IS NOT NULL AND last_scheduled <= NOW() THEN 1 END) as for_review_count,
-            COUNT(CASE WHEN last_scheduled IS NOT NULL AND last_scheduled > NOW() THEN 1 END) as learning_count
-        FROM flashcard f
-        INNER JOIN deck d ON f.deck_id = d.id
-        WHERE d.user_id = $1
-    "#;
-
-    let stats_row = sqlx::query(stats_query)
-        .bind(&user_id)
-        .fetch_one(&*state.db)
-        .await?;
-
-    let stats = crate::models::FlashcardStats {
-        new_count: stats_row.get("new_count"),
-        for_review_count: stats_row.get("for_review_count"),
-        learning_count: stats_row.get("learning_count"),
-    };
+    let decks = fetch_deck_summaries(&state.db, &user_id, None).await?;
+    let stats = FlashcardStats::from_decks(&decks);
 
     let template = templates::Decks { decks, stats };
     handle_render(template.render())
@@ -63,14 +51,17 @@ pub async fn create_deck(
 ) -> Result<impl IntoResponse, ApiError> {
     let user_id = check_user_id(user_id)?;
     let deck = sqlx::query_as::<_, Deck>(
-        "INSERT INTO deck (name, user_id) VALUES ($1, $2) RETURNING id, name, user_id",
+        "INSERT INTO deck (name, user_id, answer_mode) VALUES ($1, $2, $3) RETURNING *",
     )
     .bind(form.name)
     .bind(user_id)
+    .bind(form.answer_mode.unwrap_or_default())
     .fetch_one(&*state.db)
     .await?;
 
-    let template = templates::DeckNewTemplate { deck };
+    let template = templates::DeckNewTemplate {
+        deck: DeckSummary::empty(deck),
+    };
     handle_render(template.render())
 }
 
@@ -82,9 +73,10 @@ pub async fn delete_deck(
     let user_id = check_user_id(user_id)?;
     sqlx::query("DELETE FROM deck WHERE id = $1 AND user_id = $2")
         .bind(id)
-        .bind(user_id)
+        .bind(&user_id)
         .execute(&*state.db)
         .await?;
+    state.notify_deck(&user_id, id);
 
     Ok(StatusCode::OK)
 }
@@ -97,18 +89,42 @@ pub async fn update_deck(
 ) -> Result<impl IntoResponse, ApiError> {
     let user_id = check_user_id(user_id)?;
     let deck = sqlx::query_as::<_, Deck>(
-        "UPDATE deck SET name = $1 WHERE id = $2 AND user_id = $3 RETURNING id, name, user_id",
+        r#"
+        UPDATE deck SET name = $1, answer_mode = COALESCE($4, answer_mode)
+        WHERE id = $2 AND user_id = $3
+        RETURNING *
+        "#,
     )
     .bind(form.name)
     .bind(id)
-    .bind(user_id)
+    .bind(&user_id)
+    .bind(form.answer_mode)
     .fetch_one(&*state.db)
     .await?;
+    state.notify_deck(&user_id, deck.id);
+    let deck = fetch_deck_summaries(&state.db, &user_id, Some(deck.id))
+        .await?
+        .pop()
+        .ok_or(ApiError::UserNotFoundOrUnauthorized)?;
 
     let template = templates::DeckNewTemplate { deck };
     handle_render(template.render())
 }
 
+/// Sets how the cards of the deck are read aloud on the glasses
+pub async fn update_speech(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Form(form): Form<DeckSpeech>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let deck = update_deck_speech(&state.db, &user_id, id, &form).await?;
+    state.notify_deck(&user_id, deck.id);
+
+    Ok(StatusCode::OK)
+}
+
 pub async fn import_deck(
     Extension(AuthUser(user_id)): Extension<AuthUser>,
     State(state): State<Arc<AppState>>,
@@ -116,35 +132,227 @@ pub async fn import_deck(
     Form(form): Form<FlashcardImport>,
 ) -> Result<impl IntoResponse, ApiError> {
     let user_id = check_user_id(user_id)?;
-    let deck = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 AND id = $2")
-        .bind(&user_id)
-        .bind(id)
-        .fetch_one(&*state.db)
-        .await?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
 
-    import_anki_text_to_db(
-        &state.db,
-        deck.id,
+    let flashcards = parse_text(
+        ImportFormat::from_name(&form.format),
         form.front_idx,
         form.back_idx,
-        form.anki_text,
+        &form.anki_text,
+    );
+    let job = start_import_job(state.db.clone(), &user_id, deck.id, flashcards).await?;
+
+    import_job_response(job)
+}
+
+/// Detects the format of pasted text and returns the first cards it would produce
+pub async fn preview_import(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Form(form): Form<FlashcardImportPreview>,
+) -> Result<Json<ImportPreview>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    fetch_user_deck(&state.db, &user_id, id).await?;
+    let lines: Vec<String> = form
+        .anki_text
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(str::to_string)
+        .collect();
+    Ok(Json(ImportPreview::new(
+        ImportFormat::from_name(&form.format),
+        &lines,
+    )))
+}
+
+/// Renders the progress of an import job, which polls itself while the job is running.
+/// Redirects to the deck when the job has completed.
+fn import_job_response(job: ImportJob) -> Result<Response, ApiError> {
+    let mut headers = HeaderMap::new();
+    if job.status == ImportJobStatus::Completed {
+        // Use HTMX redirect header for cleaner redirect
+        headers.insert(
+            "HX-Redirect",
+            format!("/decks/{}/flashcards", job.deck_id)
+                .parse()
+                .unwrap(),
+        );
+    }
+    let template = templates::ImportJobTemplate { job };
+    Ok((headers, handle_render(template.render())?).into_response())
+}
+
+pub async fn get_import_job(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path((id, job_id)): Path<(i32, i32)>,
+) -> Result<Response, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let job = sqlx::query_as::<_, ImportJob>(
+        "SELECT * FROM import_job WHERE id = $1 AND deck_id = $2 AND user_id = $3",
     )
+    .bind(job_id)
+    .bind(id)
+    .bind(&user_id)
+    .fetch_one(&*state.db)
     .await?;
 
-    // Use HTMX redirect header for cleaner redirect
-    use axum::http::HeaderMap;
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "HX-Redirect",
-        format!("/decks/{}/flashcards", deck.id).parse().unwrap(),
-    );
+    import_job_response(job)
+}
+
+/// Cancels a running import; the cards stored before are kept
+pub async fn cancel_import(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path((id, job_id)): Path<(i32, i32)>,
+) -> Result<Response, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
+    let job = cancel_import_job(&state.db, &user_id, Some(deck.id), job_id).await?;
+
+    import_job_response(job)
+}
 
-    Ok((
-        headers,
-        Html(
-            "<div class='alert alert-success'>Flashcards imported successfully!</div>".to_string(),
-        ),
-    ))
+fn multipart_error(e: MultipartError) -> ApiError {
+    ApiError::BadRequest(e.body_text())
+}
+
+async fn read_apkg_upload(
+    deck_id: i32,
+    field: &mut Field<'_>,
+) -> Result<Vec<Vec<String>>, ApiError> {
+    // Zip archives need random access, so the package is spooled to a temporary file
+    let path = std::env::temp_dir().join(format!(
+        "memcards-import-{deck_id}-{}.apkg",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    let result = async {
+        let mut file = tokio::fs::File::create(&path)
+            .await
+            .map_err(|e| ApiError::BadRequest(format!("Failed to store upload: {e}")))?;
+        while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| ApiError::BadRequest(format!("Failed to store upload: {e}")))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| ApiError::BadRequest(format!("Failed to store upload: {e}")))?;
+        let apkg_path = path.clone();
+        tokio::task::spawn_blocking(move || read_apkg_notes(&apkg_path))
+            .await
+            .map_err(|e| ApiError::BadRequest(format!("Failed to read package: {e}")))?
+            .map_err(|e| ApiError::BadRequest(format!("{e:#}")))
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&path).await;
+    result
+}
+
+/// Imports an uploaded file (a text export in any supported format, or an Anki `.apkg` package).
+/// The `format`, `front_idx` and `back_idx` fields must be sent before the `file` field,
+/// so that text files can be parsed while they are streamed.
+/// With `preview=true`, only the detected format, the fields of the first row
+/// and the first cards are returned.
+pub async fn upload_import(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    mut multipart: Multipart,
+) -> Result<Response, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
+
+    let mut format: Option<ImportFormat> = None;
+    let mut front_idx: Option<usize> = None;
+    let mut back_idx: Option<usize> = None;
+    let mut preview = false;
+    let mut flashcards = None;
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        match field.name() {
+            Some("format") => {
+                format = ImportFormat::from_name(&field.text().await.map_err(multipart_error)?);
+            }
+            Some("front_idx") => {
+                front_idx = field.text().await.map_err(multipart_error)?.parse().ok();
+            }
+            Some("back_idx") => {
+                back_idx = field.text().await.map_err(multipart_error)?.parse().ok();
+            }
+            Some("preview") => {
+                preview = field.text().await.map_err(multipart_error)? == "true";
+            }
+            Some("file") => {
+                let file_name = field.file_name().unwrap_or_default().to_lowercase();
+                if file_name.ends_with(".apkg") {
+                    let notes = read_apkg_upload(deck.id, &mut field).await?;
+                    if preview {
+                        let fields = notes.into_iter().next().unwrap_or_default();
+                        return Ok(Json(ImportPreview {
+                            format: ImportFormat::Anki,
+                            fields,
+                            cards: vec![],
+                        })
+                        .into_response());
+                    }
+                    let (Some(front_idx), Some(back_idx)) = (front_idx, back_idx) else {
+                        return Err(ApiError::BadRequest(
+                            "front_idx and back_idx must be sent before the file".to_string(),
+                        ));
+                    };
+                    let mut parser = Box::new(AnkiTextParser::new(front_idx, back_idx));
+                    parser.set_html(true);
+                    for note in notes {
+                        parser.add_fields(note);
+                    }
+                    flashcards = Some(parser.finish());
+                } else if preview {
+                    let mut decoder = StreamingDecoder::new();
+                    let mut lines = Vec::new();
+                    while lines.len() < PREVIEW_LINES
+                        && let Some(chunk) = field.chunk().await.map_err(multipart_error)?
+                    {
+                        lines.extend(decoder.push(&chunk));
+                    }
+                    if lines.len() < PREVIEW_LINES {
+                        lines.extend(decoder.finish());
+                    }
+                    lines.truncate(PREVIEW_LINES);
+                    return Ok(Json(ImportPreview::new(format, &lines)).into_response());
+                } else {
+                    // Field indices are only needed for Anki exports; detected ones
+                    // default to the first two fields
+                    if format == Some(ImportFormat::Anki)
+                        && (front_idx.is_none() || back_idx.is_none())
+                    {
+                        return Err(ApiError::BadRequest(
+                            "front_idx and back_idx must be sent before the file".to_string(),
+                        ));
+                    }
+                    let mut import =
+                        StreamingImport::new(format, front_idx.unwrap_or(0), back_idx.unwrap_or(1));
+                    let mut decoder = StreamingDecoder::new();
+                    while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+                        for line in decoder.push(&chunk) {
+                            import.feed_line(line);
+                        }
+                    }
+                    for line in decoder.finish() {
+                        import.feed_line(line);
+                    }
+                    flashcards = Some(import.finish());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let flashcards =
+        flashcards.ok_or_else(|| ApiError::BadRequest("No file uploaded".to_string()))?;
+    let job = start_import_job(state.db.clone(), &user_id, deck.id, flashcards).await?;
+
+    import_job_response(job)
 }
 
 pub async fn show_import_form(
@@ -153,14 +361,20 @@ pub async fn show_import_form(
     Path(id): Path<i32>,
 ) -> Result<impl IntoResponse, ApiError> {
     let user_id = check_user_id(user_id)?;
-    let deck = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 AND id = $2")
-        .bind(&user_id)
-        .bind(id)
-        .fetch_one(&*state.db)
-        .await?;
+    let deck = fetch_user_deck(&state.db, &user_id, id).await?;
+
+    // Imports that are still running are shown again after reloading the page
+    let jobs = sqlx::query_as::<_, ImportJob>(
+        "SELECT * FROM import_job WHERE deck_id = $1 AND user_id = $2 AND status = 'running' ORDER BY id",
+    )
+    .bind(deck.id)
+    .bind(&user_id)
+    .fetch_all(&*state.db)
+    .await?;
 
     let template = templates::DeckImportTemplate {
         deck,
+        jobs,
         is_authenticated: true,
     };
     handle_render(template.render())
@@ -12,9 +12,9 @@ use serde::Deserialize;
 
 use crate::{
     errors::ApiError,
-    models::{Deck, Flashcard, FlashcardNew, FlashcardUpdate},
+    models::{Deck, Flashcard, FlashcardNew, FlashcardUpdate, parse_tags},
     router::AppState,
-    routes::{check_user_id, handle_render},
+    routes::{check_accepted_answers, check_user_id, handle_render},
     sdk::{app_session::UserId, auth::AuthUser},
     templates::{FlashcardListTemplate, FlashcardTemplate, FlashcardsTemplate},
 };
@@ -148,11 +148,12 @@ pub async fn create_flashcard(
     }
 
     let flashcard = sqlx::query_as::<_, Flashcard>(
-        "INSERT INTO flashcard (deck_id, front, back) VALUES ($1, $2, $3) RETURNING *",
+        "INSERT INTO flashcard (deck_id, front, back, tags) VALUES ($1, $2, $3, $4) RETURNING *",
     )
     .bind(form.deck_id)
     .bind(form.front)
     .bind(form.back)
+    .bind(parse_tags(&form.tags))
     .fetch_one(&*state.db)
     .await?;
 
@@ -168,12 +169,16 @@ pub async fn update_flashcard(
     Form(form): Form<FlashcardUpdate>,
 ) -> Result<impl IntoResponse, ApiError> {
     let user_id = check_user_id(user_id)?;
+    check_accepted_answers(form.accepted_answers.as_deref())?;
 
     // Verify the user owns the flashcard through the deck
     let flashcard = sqlx::query_as::<_, Flashcard>(
         r#"
         UPDATE flashcard
-        SET front = $1, back = $2
+        SET front = $1, back = $2, tags = COALESCE($5, tags),
+            accepted_answers = CASE
+                WHEN $6::TEXT IS NULL THEN accepted_answers ELSE NULLIF(TRIM($6), '')
+            END
         WHERE id = $3 AND deck_id IN (
             SELECT id FROM deck WHERE user_id = $4
         )
@@ -183,12 +188,15 @@ pub async fn update_flashcard(
     .bind(form.front)
     .bind(form.back)
     .bind(id)
-    .bind(user_id)
+    .bind(&user_id)
+    .bind(form.tags.as_deref().map(parse_tags))
+    .bind(form.accepted_answers)
     .fetch_optional(&*state.db)
     .await?;
 
     match flashcard {
         Some(flashcard) => {
+            state.notify_cards(&user_id, vec![id]);
             let template = FlashcardTemplate { flashcard };
             handle_render(template.render())
         }
@@ -213,13 +221,14 @@ pub async fn delete_flashcard(
         "#,
     )
     .bind(id)
-    .bind(user_id)
+    .bind(&user_id)
     .execute(&*state.db)
     .await?;
 
     if result.rows_affected() == 0 {
         return Err(ApiError::UserNotFoundOrUnauthorized);
     }
+    state.notify_cards(&user_id, vec![id]);
 
     Ok(StatusCode::OK)
 }
@@ -0,0 +1,210 @@
+//! Handlers for reviewing cards in the webview, with the same queue and scheduling as the
+//! sessions on the glasses.
+use std::{sync::Arc, time::Duration};
+
+use askama::Template;
+use axum::{Extension, Form, extract::State, response::IntoResponse};
+use serde::Deserialize;
+
+use crate::{
+    errors::ApiError,
+    models::{CardRating, Flashcard},
+    router::AppState,
+    routes::{check_user_id, handle_render},
+    sdk::auth::AuthUser,
+    search::empty_as_none,
+    srs::{SessionSummary, capped_duration_ms, fetch_due_cards, load_user_settings, record_review},
+    templates::{ReviewCardTemplate, ReviewTemplate},
+};
+
+/// The state of a review in the webview, sent back with every rating
+#[derive(Deserialize, Debug)]
+pub struct ReviewRating {
+    pub card_id: i32,
+    pub rating: String,
+    /// Time from showing the card to rating it
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub duration_ms: Option<u64>,
+    /// The ids of the remaining cards, comma-separated
+    #[serde(default)]
+    pub queue: String,
+    #[serde(default)]
+    pub easy: u32,
+    #[serde(default)]
+    pub good: u32,
+    #[serde(default)]
+    pub difficult: u32,
+    #[serde(default)]
+    pub again: u32,
+    #[serde(default)]
+    pub total_ms: u64,
+}
+
+/// Takes the next card of the queue that is still due, as it may have been reviewed on the
+/// glasses in the meantime
+async fn next_card(
+    state: &AppState,
+    user_id: &str,
+    queue: &mut Vec<i32>,
+) -> Result<Option<(Flashcard, String)>, ApiError> {
+    while !queue.is_empty() {
+        let id = queue.remove(0);
+        let card = sqlx::query_as::<_, Flashcard>(
+            r#"
+            SELECT f.* FROM flashcard f
+            INNER JOIN deck d ON f.deck_id = d.id
+            WHERE f.id = $1 AND d.user_id = $2 AND NOT f.suspended
+                AND (f.last_scheduled <= NOW() OR f.last_scheduled IS NULL)
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&*state.db)
+        .await?;
+        if let Some(card) = card {
+            let deck_name: String = sqlx::query_scalar("SELECT name FROM deck WHERE id = $1")
+                .bind(card.deck_id)
+                .fetch_one(&*state.db)
+                .await?;
+            return Ok(Some((card, deck_name)));
+        }
+    }
+    Ok(None)
+}
+
+fn card_template(
+    card: Option<(Flashcard, String)>,
+    queue: Vec<i32>,
+    summary: SessionSummary,
+) -> ReviewCardTemplate {
+    let (card, deck_name) = card.unzip();
+    ReviewCardTemplate {
+        card,
+        deck_name: deck_name.unwrap_or_default(),
+        remaining: queue.len(),
+        queue: queue
+            .iter()
+            .map(i32::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        summary,
+    }
+}
+
+/// Starts a review with the cards that a session on the glasses would have
+pub async fn review_page(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let settings = load_user_settings(&state.db, &user_id).await?;
+    let mut queue: Vec<i32> = fetch_due_cards(
+        &state.db,
+        &user_id,
+        settings.max_cards_per_session as usize,
+        None,
+    )
+    .await?
+    .into_iter()
+    .map(|card| card.id)
+    .collect();
+    let card = next_card(&state, &user_id, &mut queue).await?;
+
+    let template = ReviewTemplate {
+        is_authenticated: true,
+        card: card_template(card, queue, SessionSummary::default()),
+    };
+    handle_render(template.render())
+}
+
+/// Records the rating of a card and shows the next one, or the summary at the end
+pub async fn rate_card(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<ReviewRating>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let rating = form
+        .rating
+        .parse::<CardRating>()
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let card = sqlx::query_as::<_, Flashcard>(
+        r#"
+        SELECT f.* FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE f.id = $1 AND d.user_id = $2
+            AND (f.last_scheduled <= NOW() OR f.last_scheduled IS NULL)
+        "#,
+    )
+    .bind(form.card_id)
+    .bind(&user_id)
+    .fetch_optional(&*state.db)
+    .await?
+    // A card that isn't due was already rated, e.g. by a resubmitted form
+    .ok_or_else(|| ApiError::BadRequest("The card was already reviewed".to_string()))?;
+    let settings = load_user_settings(&state.db, &user_id).await?;
+    let duration = form.duration_ms.map(Duration::from_millis);
+    record_review(
+        &state.db,
+        &user_id,
+        &card,
+        rating,
+        f32::from(settings.desired_retention) / 100.0,
+        duration,
+    )
+    .await
+    .map_err(ApiError::SchedulingError)?;
+    state.notify_cards(&user_id, vec![card.id]);
+
+    let mut summary = SessionSummary {
+        easy: form.easy,
+        good: form.good,
+        difficult: form.difficult,
+        again: form.again,
+        duration_ms: form.total_ms,
+    };
+    summary.add(rating, duration.map(capped_duration_ms));
+    let mut queue: Vec<i32> = form
+        .queue
+        .split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect();
+    let card = next_card(&state, &user_id, &mut queue).await?;
+    handle_render(card_template(card, queue, summary).render())
+}
+
+#[cfg(test)]
+mod test {
+    use askama::Template;
+
+    use super::card_template;
+    use crate::{models::Flashcard, srs::SessionSummary};
+
+    #[test]
+    fn test_card_template() {
+        let card = Flashcard {
+            id: 7,
+            front: "casa".to_string(),
+            back: "house".to_string(),
+            ..Default::default()
+        };
+        let html = card_template(
+            Some((card, "Spanish".to_string())),
+            vec![8, 9],
+            SessionSummary::default(),
+        )
+        .render()
+        .unwrap();
+        // The clicked button sends the rating, as htmx only reads `hx-vals` of the form
+        for rating in ["again", "difficult", "good", "easy"] {
+            assert!(html.contains(&format!(r#"name="rating" value="{rating}""#)));
+        }
+        assert!(html.contains(r#"name="duration_ms""#));
+        assert!(html.contains(r#"name="queue" value="8,9""#));
+
+        let html = card_template(None, Vec::new(), SessionSummary::default())
+            .render()
+            .unwrap();
+        assert!(html.contains("All cards reviewed!"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Handlers for searching flashcards across all decks.
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    Extension,
+    extract::{Query, RawQuery, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+
+use crate::{
+    errors::ApiError,
+    models::Deck,
+    router::AppState,
+    routes::{check_user_id, handle_render},
+    sdk::auth::AuthUser,
+    search::{SearchQuery, fetch_tags, search_flashcards},
+    templates::{SearchResultsTemplate, SearchTemplate},
+};
+
+pub async fn search_page(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let decks = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 ORDER BY name")
+        .bind(&user_id)
+        .fetch_all(&*state.db)
+        .await?;
+    let tags = fetch_tags(&state.db, &user_id).await?;
+
+    let template = SearchTemplate {
+        is_authenticated: true,
+        query,
+        decks,
+        tags,
+    };
+    handle_render(template.render())
+}
+
+pub async fn search_results(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let results = search_flashcards(&state.db, &user_id, &query).await?;
+
+    // Keep the filters in the page URL, so that searches can be reloaded and shared
+    let mut headers = HeaderMap::new();
+    if query.page == 0 {
+        let url = match raw_query {
+            Some(raw_query) => format!("/search?{raw_query}"),
+            None => "/search".to_string(),
+        };
+        if let Ok(url) = url.parse() {
+            headers.insert("HX-Replace-Url", url);
+        }
+    }
+
+    let template = SearchResultsTemplate { results };
+    Ok((headers, handle_render(template.render())?))
+}
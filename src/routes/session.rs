@@ -0,0 +1,130 @@
+//! Handlers for following and controlling the session on the glasses from the webview
+use std::{convert::Infallible, sync::Arc};
+
+use askama::Template;
+use axum::{
+    Extension, Form,
+    extract::State,
+    http::StatusCode,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::{Stream, StreamExt, stream};
+use serde::Deserialize;
+use tokio_stream::wrappers::WatchStream;
+
+use crate::{
+    errors::ApiError,
+    models::CardRating,
+    router::AppState,
+    routes::{check_user_id, handle_render},
+    sdk::auth::AuthUser,
+    srs::{SessionCommand, SessionMirror, on_command},
+    templates::SessionTemplate,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct SessionCommandForm {
+    /// "start", "resume", "reveal", "rate", "skip" or "accept"
+    pub command: String,
+    /// The rating for "rate"
+    pub rating: Option<String>,
+}
+
+impl TryFrom<SessionCommandForm> for SessionCommand {
+    type Error = ApiError;
+
+    fn try_from(form: SessionCommandForm) -> Result<Self, Self::Error> {
+        match form.command.as_str() {
+            "start" => Ok(SessionCommand::Start),
+            "reveal" => Ok(SessionCommand::Reveal),
+            "skip" => Ok(SessionCommand::Skip),
+            "accept" => Ok(SessionCommand::Accept),
+            "resume" => Ok(SessionCommand::Resume),
+            "rate" => form
+                .rating
+                .unwrap_or_default()
+                .parse::<CardRating>()
+                .map(SessionCommand::Rate)
+                .map_err(|e| ApiError::BadRequest(e.to_string())),
+            command => Err(ApiError::BadRequest(format!("Unknown command: {command}"))),
+        }
+    }
+}
+
+/// Mirror of the display of the glasses, with buttons for when voice commands fail
+pub async fn session_page(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_user_id(user_id)?;
+    let template = SessionTemplate {
+        is_authenticated: true,
+    };
+    handle_render(template.render())
+}
+
+/// Streams the state of the display of the user's session, ending with the session.
+/// Without a session, the stream ends right away and the browser retries later.
+pub async fn session_events(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let mirrors = match state.review_session(&user_id) {
+        Some(session_state) => WatchStream::new(session_state.subscribe()).left_stream(),
+        None => stream::once(async { SessionMirror::default() }).right_stream(),
+    };
+    let events = mirrors.map(|mirror| {
+        Ok(Event::default()
+            .json_data(&mirror)
+            .unwrap_or_else(|_| Event::default().comment("invalid state")))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Runs a command in the user's session, as if it was said
+pub async fn session_command(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<SessionCommandForm>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    let command = SessionCommand::try_from(form)?;
+    let session_state = state
+        .review_session(&user_id)
+        .ok_or_else(|| ApiError::BadRequest("No session on the glasses".to_string()))?;
+    on_command(session_state, command)
+        .await
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod test {
+    use super::SessionCommandForm;
+    use crate::{models::CardRating, srs::SessionCommand};
+
+    fn command(command: &str, rating: Option<&str>) -> Option<SessionCommand> {
+        SessionCommand::try_from(SessionCommandForm {
+            command: command.to_string(),
+            rating: rating.map(str::to_string),
+        })
+        .ok()
+    }
+
+    #[test]
+    fn test_session_command() {
+        assert_eq!(command("reveal", None), Some(SessionCommand::Reveal));
+        assert_eq!(command("skip", None), Some(SessionCommand::Skip));
+        assert_eq!(command("accept", None), Some(SessionCommand::Accept));
+        assert_eq!(command("resume", None), Some(SessionCommand::Resume));
+        assert_eq!(
+            command("rate", Some("good")),
+            Some(SessionCommand::Rate(CardRating::Good))
+        );
+        assert_eq!(command("rate", None), None);
+        assert_eq!(command("dance", None), None);
+    }
+}
@@ -0,0 +1,46 @@
+//! Handler for the statistics dashboard.
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    Extension,
+    extract::{Query, State},
+    response::IntoResponse,
+};
+
+use crate::{
+    errors::ApiError,
+    models::Deck,
+    review_session::fetch_review_sessions,
+    router::AppState,
+    routes::{check_user_id, fetch_user_deck, handle_render},
+    sdk::auth::AuthUser,
+    stats::{StatsQuery, fetch_dashboard},
+    templates::StatsTemplate,
+};
+
+pub async fn stats_page(
+    Extension(AuthUser(user_id)): Extension<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StatsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = check_user_id(user_id)?;
+    if let Some(deck_id) = query.deck_id {
+        fetch_user_deck(&state.db, &user_id, deck_id).await?;
+    }
+    let decks = sqlx::query_as::<_, Deck>("SELECT * FROM deck WHERE user_id = $1 ORDER BY name")
+        .bind(&user_id)
+        .fetch_all(&*state.db)
+        .await?;
+    let dashboard = fetch_dashboard(&state.db, &user_id, query.deck_id).await?;
+    let sessions = fetch_review_sessions(&state.db, &user_id).await?;
+
+    let template = StatsTemplate {
+        is_authenticated: true,
+        query,
+        decks,
+        dashboard,
+        sessions,
+    };
+    handle_render(template.render())
+}
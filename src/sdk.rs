@@ -1,24 +1,27 @@
 //! SDK module for handling sessions, authentication, events, and layout management.
 //! This module (and its sub-modules) provides the core functionality for interacting with the AugmentOS/MentraOS platform.
 pub(crate) mod app_session;
+pub(crate) mod audio_manager;
 pub(crate) mod auth;
 mod event_manager;
 pub(crate) mod events;
 pub(crate) mod layout_manager;
+#[cfg(test)]
+mod mock_cloud;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
     config::AppConfig,
     router::AppState,
-    sdk::app_session::{AppSession, UserId},
+    sdk::app_session::{AppSession, SessionStatus, UserId, now_millis},
 };
 use anyhow::{Context, Result};
 use axum::{Extension, Json, extract::State, http::StatusCode, response::IntoResponse};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 
-use crate::srs::extract_settings;
+use crate::srs::{SettingsUpdate, save_settings_update, switch_transcription};
 use tracing::{debug, error, info, warn};
 
 #[derive(Deserialize, Debug, Clone)]
@@ -50,10 +53,46 @@ impl AppState {
             "🛑 Session {} stopped for user {}. Reason: {}",
             session_id, user_id, reason
         );
-        // Default implementation - override in your implementation
+        if let Some((_, session_state)) = self.review_sessions.remove(session_id) {
+            session_state.end();
+        }
         Ok(())
     }
 
+    /// Disconnects and removes the sessions which are closed or haven't heard from the cloud
+    /// for too long, e.g. when no `stop_request` came; returns how many were removed
+    pub(crate) async fn reap_sessions(&self) -> usize {
+        let now = now_millis();
+        let stale: Vec<String> = self
+            .active_sessions
+            .iter()
+            .filter(|session| session.connection.is_stale(now))
+            .map(|session| session.session_id.clone())
+            .collect();
+        let mut reaped = 0;
+        for session_id in stale {
+            let Some((_, mut session)) = self
+                .active_sessions
+                .remove_if(&session_id, |_, session| session.connection.is_stale(now))
+            else {
+                continue;
+            };
+            info!(
+                "🧹 Reaping session {} for user {} ({:?}, last heard from {}s ago)",
+                session_id,
+                session.user_id,
+                session.connection.status(),
+                now.saturating_sub(session.connection.last_updated()) / 1000
+            );
+            session.disconnect().await;
+            if let Some((_, session_state)) = self.review_sessions.remove(&session_id) {
+                session_state.end();
+            }
+            reaped += 1;
+        }
+        reaped
+    }
+
     /// Called when a tool call is received
     async fn on_tool_call(&self, tool_call: &ToolCall) -> Result<Option<String>> {
         debug!("🔧 Tool call received: {}", tool_call.tool_id);
@@ -170,7 +209,7 @@ pub async fn webhook_handler(
                                     "❌ Session handler failed for session {}: {}",
                                     session_id, e
                                 );
-                                session.disconnect();
+                                session.disconnect().await;
                                 (
                                     StatusCode::INTERNAL_SERVER_ERROR,
                                     Json(
@@ -214,7 +253,7 @@ pub async fn webhook_handler(
                     Ok(()) => {
                         // Properly disconnect and remove the session
                         if let Some((_, mut session)) = state.active_sessions.remove(&session_id) {
-                            session.disconnect();
+                            session.disconnect().await;
                             info!(
                                 "🛑 Stopped and disconnected session {} for user {}: {}",
                                 session_id, user_id, reason
@@ -234,7 +273,7 @@ pub async fn webhook_handler(
                         );
                         // Still try to clean up the session even if handler failed
                         if let Some((_, mut session)) = state.active_sessions.remove(&session_id) {
-                            session.disconnect();
+                            session.disconnect().await;
                         }
                         (
                             StatusCode::INTERNAL_SERVER_ERROR,
@@ -300,20 +339,12 @@ pub(crate) async fn settings_handler(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<SettingsPayload>,
 ) -> impl IntoResponse {
-    let mut new_max_cards_per_session = None;
-    let mut new_desired_retention = None;
-    for setting in &payload.settings {
-        extract_settings(
-            &mut new_max_cards_per_session,
-            &mut new_desired_retention,
-            setting,
-        );
-    }
+    let update = SettingsUpdate::from_settings(&payload.settings);
     let mut updated = 0;
-    if new_desired_retention.is_some() || new_max_cards_per_session.is_some() {
+    if !update.is_empty() {
         info!(
-            "[settings] Settings updated for user {}: max_cards_per_session={:?}, desired_retention={:?}",
-            payload.user_id_for_settings, new_max_cards_per_session, new_desired_retention
+            "[settings] Settings updated for user {}: {:?}",
+            payload.user_id_for_settings, update
         );
 
         for session in state.active_sessions.iter() {
@@ -322,17 +353,21 @@ pub(crate) async fn settings_handler(
                     "[settings] Updating session {} for user {} with new settings",
                     session.session_id, payload.user_id_for_settings
                 );
-                if let Some(max_cards) = new_max_cards_per_session {
-                    session
-                        .user_settings
-                        .set_max_cards_per_session(max_cards as u8);
-                }
-                if let Some(retention) = new_desired_retention {
-                    session.user_settings.set_desired_retention(retention as u8);
-                }
+                update.apply(&session.user_settings);
                 updated += 1;
             }
         }
+        if let Some(session_state) = state.review_session(&payload.user_id_for_settings.0) {
+            switch_transcription(session_state);
+        }
+        if let Err(e) =
+            save_settings_update(&state.db, &payload.user_id_for_settings, &update).await
+        {
+            error!(
+                "[settings] Failed to save settings for user {}: {}",
+                payload.user_id_for_settings, e
+            );
+        }
     } else {
         warn!(
             "[settings] No valid settings found in payload for user {}",
@@ -342,13 +377,93 @@ pub(crate) async fn settings_handler(
     Json(serde_json::json!({"status": "success", "sessionsUpdated": updated}))
 }
 
+/// Checks the sessions for the reaper this often
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Starts the background task which removes the dead sessions
+pub(crate) fn spawn_session_reaper(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REAP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let reaped = state.reap_sessions().await;
+            if reaped > 0 {
+                info!("🧹 Reaped {reaped} stale sessions");
+            }
+        }
+    });
+}
+
 pub(crate) async fn health_handler(
     State(state): State<Arc<AppState>>,
     Extension(config): Extension<AppConfig>,
 ) -> impl IntoResponse {
+    let mut sessions = serde_json::Map::new();
+    for status in SessionStatus::ALL {
+        let count = state
+            .active_sessions
+            .iter()
+            .filter(|session| session.connection.status() == status)
+            .count();
+        sessions.insert(status.to_string(), count.into());
+    }
     Json(serde_json::json!({
         "status": "healthy",
         "app": config.package_name,
-        "activeSessions": state.active_sessions.len()
+        "activeSessions": state.active_sessions.len(),
+        "reviewSessions": state.review_sessions.len(),
+        "sessions": sessions,
     }))
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+    use secrecy::SecretString;
+    use sqlx::postgres::PgPoolOptions;
+
+    use crate::{
+        changes::card_changes,
+        router::AppState,
+        sdk::{app_session::AppSession, mock_cloud::MockCloud},
+    };
+
+    fn session(session_id: &str, url: &str) -> AppSession {
+        AppSession::new(
+            session_id.to_string(),
+            "user".to_string().into(),
+            "app".to_string(),
+            SecretString::from("key"),
+            Some(url.to_string()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_reap_sessions() {
+        let cloud = MockCloud::start().await;
+        let state = AppState {
+            db: Arc::new(
+                PgPoolOptions::new()
+                    .connect_lazy("postgres://localhost/x")
+                    .unwrap(),
+            ),
+            active_sessions: DashMap::new(),
+            review_sessions: DashMap::new(),
+            card_changes: card_changes(),
+        };
+        let mut live = session("live", &cloud.url);
+        live.connect().await.unwrap();
+        state.active_sessions.insert("live".to_string(), live);
+        let mut closed = session("closed", &cloud.url);
+        closed.connect().await.unwrap();
+        closed.disconnect().await;
+        state.active_sessions.insert("closed".to_string(), closed);
+
+        assert_eq!(state.reap_sessions().await, 1);
+        assert!(state.active_sessions.contains_key("live"));
+        assert!(!state.active_sessions.contains_key("closed"));
+        assert_eq!(state.reap_sessions().await, 0);
+    }
+}
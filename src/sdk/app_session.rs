@@ -2,17 +2,26 @@
 //! (TPA = Third-Party App).
 use anyhow::{Context, Result, bail};
 use chrono::Utc;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{
+    SinkExt, StreamExt,
+    stream::{SplitSink, SplitStream},
+};
 use reqwest::Url;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::Display,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, AtomicU32, AtomicU64, Ordering},
+    },
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::sync::Mutex;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio::{net::TcpStream, sync::Mutex};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async,
+    tungstenite::{Message, protocol::frame::coding::CloseCode},
+};
 use tracing::{debug, error, info, warn};
 
 use crate::{
@@ -51,6 +60,312 @@ pub struct AppSubscriptionUpdate {
     pub timestamp: String,
 }
 
+impl AppSubscriptionUpdate {
+    pub fn new(package_name: String, session_id: String, subscriptions: Vec<String>) -> Self {
+        Self {
+            r#type: "subscription_update".to_string(),
+            package_name,
+            subscriptions,
+            session_id,
+            timestamp: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Send a message to the cloud over the WebSocket of a session
+pub(crate) async fn send_json(sender: &WebSocketSender, message: &impl Serialize) -> Result<()> {
+    let message_json = serde_json::to_string(message).context("Failed to serialize message")?;
+    let Some(sender) = sender else {
+        bail!("WebSocket sender not available");
+    };
+    if let Err(e) = sender
+        .lock()
+        .await
+        .send(Message::Text(message_json.into()))
+        .await
+    {
+        bail!("Failed to send message: {e}");
+    }
+    Ok(())
+}
+
+/// Reconnection attempts after the connection to the cloud is lost, before giving up
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+
+/// Delay before the first reconnection attempt, doubled for each of the next ones
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+type CloudSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type CloudStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// The delay before a reconnection attempt (from 1): exponential, up to 30 seconds
+pub(crate) fn reconnect_delay(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_RECONNECT_DELAY)
+}
+
+/// Interval of the pings to the cloud, whose pongs keep the session alive
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sessions without any message from the cloud for this long are reaped, which leaves time
+/// for all the reconnection attempts
+const STALE_SESSION_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// The lifecycle of the connection of a session, as in the health output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionStatus {
+    Connecting = 0,
+    Live = 1,
+    Reconnecting = 2,
+    /// Closed by the app or the cloud, or given up on; never reconnected
+    Closed = 3,
+}
+
+impl SessionStatus {
+    pub const ALL: [SessionStatus; 4] = [
+        SessionStatus::Connecting,
+        SessionStatus::Live,
+        SessionStatus::Reconnecting,
+        SessionStatus::Closed,
+    ];
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => SessionStatus::Connecting,
+            1 => SessionStatus::Live,
+            2 => SessionStatus::Reconnecting,
+            _ => SessionStatus::Closed,
+        }
+    }
+}
+
+impl Display for SessionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionStatus::Connecting => write!(f, "connecting"),
+            SessionStatus::Live => write!(f, "live"),
+            SessionStatus::Reconnecting => write!(f, "reconnecting"),
+            SessionStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// The state of the connection of a session, shared with its message task which reconnects
+#[derive(Debug)]
+pub struct ConnectionState {
+    status: AtomicU8,
+    reconnect_attempts: AtomicU32,
+    /// When the cloud was last heard from (any message, ping or pong), in milliseconds
+    last_updated: AtomicU64,
+    /// The streams to subscribe to again on reconnection
+    subscriptions: std::sync::Mutex<Vec<String>>,
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        Self {
+            status: AtomicU8::new(SessionStatus::Connecting as u8),
+            reconnect_attempts: AtomicU32::new(0),
+            last_updated: AtomicU64::new(now_millis()),
+            subscriptions: std::sync::Mutex::default(),
+        }
+    }
+}
+
+impl ConnectionState {
+    pub fn status(&self) -> SessionStatus {
+        SessionStatus::from_u8(self.status.load(Ordering::Relaxed))
+    }
+
+    fn set_status(&self, status: SessionStatus) {
+        self.status.store(status as u8, Ordering::Relaxed);
+    }
+
+    /// Changes the status unless it changed in the meantime, e.g. the session was closed
+    fn change_status(&self, from: SessionStatus, to: SessionStatus) -> bool {
+        self.status
+            .compare_exchange(from as u8, to as u8, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.status() == SessionStatus::Live
+    }
+
+    pub fn last_updated(&self) -> u64 {
+        self.last_updated.load(Ordering::Relaxed)
+    }
+
+    fn touch(&self) {
+        self.last_updated.store(now_millis(), Ordering::Relaxed);
+    }
+
+    /// Whether the session is closed or the cloud hasn't been heard from for too long
+    pub fn is_stale(&self, now: u64) -> bool {
+        self.status() == SessionStatus::Closed
+            || now.saturating_sub(self.last_updated()) > STALE_SESSION_TIMEOUT.as_millis() as u64
+    }
+
+    pub fn set_subscriptions(&self, streams: Vec<String>) {
+        *self.subscriptions.lock().unwrap() = streams;
+    }
+
+    fn subscriptions(&self) -> Vec<String> {
+        self.subscriptions.lock().unwrap().clone()
+    }
+}
+
+/// Connect to the cloud and send the init message
+async fn open_connection(
+    ws_url: &str,
+    init: &AppConnectionInit,
+) -> Result<(CloudSink, CloudStream)> {
+    let (ws_stream, _) = connect_async(ws_url).await?;
+    let (mut write, read) = ws_stream.split();
+    let init_json = serde_json::to_string(init).context("Failed to serialize connection init")?;
+    write
+        .send(Message::Text(init_json.into()))
+        .await
+        .context("Failed to send connection init")?;
+    Ok((write, read))
+}
+
+/// The message task of a session, which restores the connection when it is lost
+struct Reconnection {
+    ws_url: String,
+    package_name: String,
+    session_id: String,
+    init: AppConnectionInit,
+    /// Shared with the session, so its sender writes to the new connection
+    sender: Arc<Mutex<CloudSink>>,
+    connection: Arc<ConnectionState>,
+}
+
+impl Reconnection {
+    async fn run(self, mut read: CloudStream, event_manager: Arc<EventManager>) {
+        loop {
+            let reconnect = AppSession::read_messages(
+                &mut read,
+                &self.sender,
+                event_manager.clone(),
+                &self.connection,
+                &self.package_name,
+                &self.session_id,
+            )
+            .await;
+            if !reconnect {
+                self.connection.set_status(SessionStatus::Closed);
+            }
+            if !self
+                .connection
+                .change_status(SessionStatus::Live, SessionStatus::Reconnecting)
+            {
+                // Closed by the app or the cloud
+                info!(
+                    "🔌 [{}] Message handling task ended for session {}",
+                    self.package_name, self.session_id
+                );
+                return;
+            }
+            match self.reconnect().await {
+                Some(new_read) => {
+                    read = new_read;
+                    event_manager.emit_system_event(
+                        "reconnected",
+                        &SystemEvent::Reconnected {
+                            attempts: self
+                                .connection
+                                .reconnect_attempts
+                                .swap(0, Ordering::Relaxed),
+                        },
+                    );
+                }
+                None => {
+                    self.connection.set_status(SessionStatus::Closed);
+                    event_manager.emit_system_event(
+                        "disconnected",
+                        &SystemEvent::Disconnected("Connection lost".to_string()),
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Connect again with exponential backoff, then restore the subscriptions
+    async fn reconnect(&self) -> Option<CloudStream> {
+        loop {
+            let attempt = self
+                .connection
+                .reconnect_attempts
+                .fetch_add(1, Ordering::Relaxed)
+                + 1;
+            if attempt > MAX_RECONNECT_ATTEMPTS {
+                error!(
+                    "❌ [{}] Gave up reconnecting session {} after {} attempts",
+                    self.package_name, self.session_id, MAX_RECONNECT_ATTEMPTS
+                );
+                return None;
+            }
+            let delay = reconnect_delay(attempt);
+            info!(
+                "⏳ [{}] Reconnecting session {} in {:?} (attempt {})",
+                self.package_name, self.session_id, delay, attempt
+            );
+            tokio::time::sleep(delay).await;
+            if self.connection.status() == SessionStatus::Closed {
+                return None;
+            }
+            let init = AppConnectionInit {
+                timestamp: Utc::now().to_rfc3339(),
+                ..self.init.clone()
+            };
+            let (write, read) = match open_connection(&self.ws_url, &init).await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!(
+                        "⚠️ [{}] Reconnection attempt {} failed: {}",
+                        self.package_name, attempt, e
+                    );
+                    continue;
+                }
+            };
+            *self.sender.lock().await = write;
+            if !self
+                .connection
+                .change_status(SessionStatus::Reconnecting, SessionStatus::Live)
+            {
+                // Closed while reconnecting
+                let _ = self.sender.lock().await.close().await;
+                return None;
+            }
+            self.connection.touch();
+            let subscriptions = self.connection.subscriptions();
+            if !subscriptions.is_empty() {
+                let update = AppSubscriptionUpdate::new(
+                    self.package_name.clone(),
+                    self.session_id.clone(),
+                    subscriptions,
+                );
+                if let Err(e) = send_json(&Some(self.sender.clone()), &update).await {
+                    warn!(
+                        "⚠️ [{}] Failed to restore the subscriptions: {}",
+                        self.package_name, e
+                    );
+                }
+            }
+            info!(
+                "✅ [{}] Reconnected session {} (attempt {})",
+                self.package_name, self.session_id, attempt
+            );
+            return Some(read);
+        }
+    }
+}
+
 pub(super) fn now_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -92,10 +407,8 @@ pub struct AppSession {
     pub package_name: String,
     pub api_key: SecretString,
     pub augmentos_websocket_url: Option<String>,
-    pub last_updated: u64, // timestamp
     pub user_settings: Arc<UserSettings>,
-    pub connected: bool,
-    pub reconnect_attempts: u32,
+    pub connection: Arc<ConnectionState>,
     pub event_manager: EventManager,
     pub layout_manager: LayoutManager,
     pub websocket_sender: WebSocketSender,
@@ -118,21 +431,30 @@ impl AppSession {
             package_name,
             api_key,
             augmentos_websocket_url,
-            last_updated: now_millis(),
             user_settings: Arc::new(UserSettings::new(20, 75)),
-            connected: false,
-            reconnect_attempts: 0,
+            connection: Arc::default(),
             event_manager,
             layout_manager,
             websocket_sender: None,
         }
     }
 
+    /// The connection init message, which is sent again on reconnection
+    fn init_message(&self) -> AppConnectionInit {
+        AppConnectionInit {
+            r#type: "tpa_connection_init".to_string(), // Correct message type from TS enum
+            session_id: self.session_id.clone(),
+            package_name: self.package_name.clone(),
+            api_key: self.api_key.expose_secret().to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+        }
+    }
+
     /// Connect to AugmentOS Cloud WebSocket
     pub async fn connect(&mut self) -> Result<()> {
         let ws_url = self
             .augmentos_websocket_url
-            .as_ref()
+            .clone()
             .context("WebSocket URL not provided")?;
 
         info!(
@@ -141,162 +463,43 @@ impl AppSession {
         );
 
         // Validate URL format before connecting
-        let _parsed_url = Url::parse(ws_url).context("Invalid WebSocket URL")?;
+        let _parsed_url = Url::parse(&ws_url).context("Invalid WebSocket URL")?;
 
         // Add retry logic for connection
         let mut last_error = String::new();
         for attempt in 1..=3 {
-            match connect_async(ws_url).await {
-                Ok((ws_stream, response)) => {
+            match open_connection(&ws_url, &self.init_message()).await {
+                Ok((write, read)) => {
                     info!(
                         "✅ [{}] Connected to WebSocket for session {} (attempt {})",
                         self.package_name, self.session_id, attempt
                     );
-                    debug!(
-                        "🔗 [{}] WebSocket response status: {:?}",
-                        self.package_name,
-                        response.status()
-                    );
-
-                    let (write, mut read) = ws_stream.split();
                     let write = Arc::new(Mutex::new(write));
 
                     // Store the WebSocket sender for later use (e.g., sending display requests)
                     self.websocket_sender = Some(write.clone());
+                    self.connection.set_status(SessionStatus::Live);
+                    self.connection
+                        .reconnect_attempts
+                        .store(0, Ordering::Relaxed);
+                    self.connection.touch();
 
-                    // Send connection initialization - use the correct TPA prefix
-                    let init_msg = AppConnectionInit {
-                        r#type: "tpa_connection_init".to_string(), // Correct message type from TS enum
-                        session_id: self.session_id.clone(),
+                    // Create shared references to the event manager's internal state
+                    let event_manager = Arc::new(EventManager {
+                        stream_handlers: self.event_manager.stream_handlers.clone(),
+                        system_handlers: self.event_manager.system_handlers.clone(),
+                        active_subscriptions: self.event_manager.active_subscriptions.clone(),
+                    });
+                    let reconnection = Reconnection {
+                        ws_url,
                         package_name: self.package_name.clone(),
-                        api_key: self.api_key.expose_secret().to_string(),
-                        timestamp: Utc::now().to_rfc3339(),
-                    };
-
-                    let init_json = serde_json::to_string(&init_msg)
-                        .context("Failed to serialize init message")?;
-
-                    debug!(
-                        "🔍 [{}] Sending connection init message: {}",
-                        self.package_name, init_json
-                    );
-
-                    // Send the message without holding the lock across await
-                    let send_result = {
-                        let mut sender = write.lock().await;
-                        sender.send(Message::Text(init_json.into())).await
+                        session_id: self.session_id.clone(),
+                        init: self.init_message(),
+                        sender: write,
+                        connection: self.connection.clone(),
                     };
-
-                    if let Err(e) = send_result {
-                        bail!("Failed to send init message: {e}");
-                    }
-
-                    debug!(
-                        "📤 [{}] Connection init message sent successfully",
-                        self.package_name
-                    );
-
-                    self.connected = true;
-                    self.reconnect_attempts = 0;
-                    self.last_updated = now_millis();
-
                     // Spawn background task to handle messages
-                    let session_id = self.session_id.clone();
-                    let package_name = self.package_name.clone();
-                    // Create shared references to the event manager's internal state
-                    let stream_handlers = self.event_manager.stream_handlers.clone();
-                    let system_handlers = self.event_manager.system_handlers.clone();
-                    let active_subscriptions = self.event_manager.active_subscriptions.clone();
-
-                    tokio::spawn(async move {
-                        info!(
-                            "🎧 [{}] Starting message handler for session {}",
-                            package_name, session_id
-                        );
-
-                        // Create EventManager instance with shared state
-                        let shared_event_manager = EventManager {
-                            stream_handlers,
-                            system_handlers,
-                            active_subscriptions,
-                        };
-                        let event_manager_arc = Arc::new(shared_event_manager);
-
-                        while let Some(msg) = read.next().await {
-                            match msg {
-                                Ok(Message::Text(text)) => {
-                                    let text_str = text.to_string();
-                                    debug!("📨 [{}] Received message: {}", package_name, text_str);
-                                    // Handle incoming messages (connection ack, dataSent display request streams, etc.)
-                                    if let Err(e) = Self::handle_websocket_message(
-                                        &text_str,
-                                        event_manager_arc.clone(),
-                                    )
-                                    .await
-                                    {
-                                        warn!(
-                                            "⚠️ [{}] Error handling message: {}",
-                                            package_name, e
-                                        );
-                                    }
-                                }
-                                Ok(Message::Binary(data)) => {
-                                    debug!(
-                                        "📨 [{}] Received binary data: {} bytes",
-                                        package_name,
-                                        data.len()
-                                    );
-                                    // Handle binary data (audio, etc.)
-                                }
-                                Ok(Message::Close(close_frame)) => {
-                                    if let Some(cf) = close_frame {
-                                        info!(
-                                            "👋 [{}] WebSocket connection closed for session {} - Code: {}, Reason: {}",
-                                            package_name, session_id, cf.code, cf.reason
-                                        );
-                                    } else {
-                                        info!(
-                                            "👋 [{}] WebSocket connection closed for session {}",
-                                            package_name, session_id
-                                        );
-                                    }
-                                    break;
-                                }
-                                Ok(Message::Ping(payload)) => {
-                                    debug!(
-                                        "🏓 [{}] Received ping: {} bytes",
-                                        package_name,
-                                        payload.len()
-                                    );
-                                    let pong_msg = Message::Pong(payload);
-                                    if let Err(e) = write.lock().await.send(pong_msg).await {
-                                        error!(
-                                            "❌ [{}] Failed to send pong response: {}",
-                                            package_name, e
-                                        );
-                                    }
-                                }
-                                Ok(Message::Pong(payload)) => {
-                                    debug!(
-                                        "🏓 [{}] Received pong: {} bytes",
-                                        package_name,
-                                        payload.len()
-                                    );
-                                }
-                                Ok(Message::Frame(_)) => {
-                                    debug!("🔧 [{}] Received frame", package_name);
-                                }
-                                Err(e) => {
-                                    error!("❌ [{}] WebSocket error: {}", package_name, e);
-                                    break;
-                                }
-                            }
-                        }
-                        info!(
-                            "🔌 [{}] WebSocket handler task ended for session {}",
-                            package_name, session_id
-                        );
-                    });
+                    tokio::spawn(reconnection.run(read, event_manager));
 
                     return Ok(());
                 }
@@ -317,10 +520,100 @@ impl AppSession {
         }
 
         error!("❌ [{}] All connection attempts failed", self.package_name);
-        self.connected = false;
+        self.connection.set_status(SessionStatus::Closed);
         bail!(last_error)
     }
 
+    /// Read the messages of a connection until it is closed, pinging the cloud in between;
+    /// false if it shouldn't be restored
+    async fn read_messages(
+        read: &mut CloudStream,
+        sender: &Mutex<CloudSink>,
+        event_manager: Arc<EventManager>,
+        connection: &ConnectionState,
+        package_name: &str,
+        session_id: &str,
+    ) -> bool {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.tick().await;
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => msg,
+                _ = heartbeat.tick() => {
+                    if let Err(e) = sender.lock().await.send(Message::Ping(Default::default())).await {
+                        warn!("⚠️ [{}] Failed to send ping: {}", package_name, e);
+                    }
+                    continue;
+                }
+            };
+            let Some(msg) = msg else {
+                break;
+            };
+            connection.touch();
+            match msg {
+                Ok(Message::Text(text)) => {
+                    let text_str = text.to_string();
+                    debug!("📨 [{}] Received message: {}", package_name, text_str);
+                    // Handle incoming messages (connection ack, dataSent display request streams, etc.)
+                    if let Err(e) =
+                        Self::handle_websocket_message(&text_str, event_manager.clone()).await
+                    {
+                        warn!("⚠️ [{}] Error handling message: {}", package_name, e);
+                    }
+                }
+                Ok(Message::Binary(data)) => {
+                    debug!(
+                        "📨 [{}] Received binary data: {} bytes",
+                        package_name,
+                        data.len()
+                    );
+                    // Handle binary data (audio, etc.)
+                }
+                Ok(Message::Close(close_frame)) => {
+                    if let Some(cf) = close_frame {
+                        info!(
+                            "👋 [{}] WebSocket connection closed for session {} - Code: {}, Reason: {}",
+                            package_name, session_id, cf.code, cf.reason
+                        );
+                        // The cloud ended the session
+                        return cf.code != CloseCode::Normal;
+                    }
+                    info!(
+                        "👋 [{}] WebSocket connection closed for session {}",
+                        package_name, session_id
+                    );
+                    return true;
+                }
+                Ok(Message::Ping(payload)) => {
+                    debug!(
+                        "🏓 [{}] Received ping: {} bytes",
+                        package_name,
+                        payload.len()
+                    );
+                    let pong_msg = Message::Pong(payload);
+                    if let Err(e) = sender.lock().await.send(pong_msg).await {
+                        error!("❌ [{}] Failed to send pong response: {}", package_name, e);
+                    }
+                }
+                Ok(Message::Pong(payload)) => {
+                    debug!(
+                        "🏓 [{}] Received pong: {} bytes",
+                        package_name,
+                        payload.len()
+                    );
+                }
+                Ok(Message::Frame(_)) => {
+                    debug!("🔧 [{}] Received frame", package_name);
+                }
+                Err(e) => {
+                    error!("❌ [{}] WebSocket error: {}", package_name, e);
+                    return true;
+                }
+            }
+        }
+        true
+    }
+
     /// Handle incoming WebSocket messages and emit events
     async fn handle_websocket_message(
         message: &str,
@@ -633,6 +926,24 @@ impl AppSession {
                     },
                 );
             }
+            "audio_play_response" => {
+                let success = json_value
+                    .get("success")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                if success {
+                    debug!("🔊 Audio played: {}", json_value);
+                } else {
+                    warn!("🔇 Audio playback failed: {}", json_value);
+                }
+                event_manager.emit_system_event(
+                    "audio_play_response",
+                    &SystemEvent::CustomMessage {
+                        action: "audio_play_response".to_string(),
+                        payload: json_value.clone(),
+                    },
+                );
+            }
             "subscription_ack" | "subscription_update_ack" => {
                 info!("✅ Subscription acknowledgment received");
                 if let Some(subscriptions) =
@@ -665,30 +976,32 @@ impl AppSession {
     }
 
     /// Disconnect from WebSocket
-    pub fn disconnect(&mut self) {
-        if self.connected {
+    pub async fn disconnect(&mut self) {
+        let status = self.connection.status();
+        self.connection.set_status(SessionStatus::Closed);
+        if status != SessionStatus::Closed {
             info!(
-                "👋 [{}] Disconnecting session {}",
-                self.package_name, self.session_id
+                "👋 [{}] Disconnecting session {} ({:?})",
+                self.package_name, self.session_id, status
             );
-            self.connected = false;
-            self.last_updated = now_millis();
+            if let Some(sender) = &self.websocket_sender {
+                let _ = sender.lock().await.close().await;
+            }
         }
     }
 
     /// Subscribe to event streams
     pub async fn subscribe_to_streams(&self, streams: Vec<String>) -> Result<()> {
-        if !self.connected {
+        self.connection.set_subscriptions(streams.clone());
+        if !self.connection.is_connected() {
             bail!("Session not connected");
         }
 
-        let subscription_msg = AppSubscriptionUpdate {
-            r#type: "subscription_update".to_string(),
-            package_name: self.package_name.clone(),
-            subscriptions: streams.clone(),
-            session_id: self.session_id.clone(),
-            timestamp: Utc::now().to_rfc3339(),
-        };
+        let subscription_msg = AppSubscriptionUpdate::new(
+            self.package_name.clone(),
+            self.session_id.clone(),
+            streams.clone(),
+        );
 
         // Send the subscription update via WebSocket
         let subscription_json = serde_json::to_string(&subscription_msg)
@@ -714,7 +1027,7 @@ impl AppSession {
 
     /// Send a display request to AugmentOS Cloud
     pub async fn send_display_request(&self, display_request: &DisplayRequest) -> Result<()> {
-        if !self.connected {
+        if !self.connection.is_connected() {
             bail!("Session not connected");
         }
 
@@ -747,3 +1060,143 @@ impl AppSession {
         self.send_display_request(&display_request).await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use secrecy::SecretString;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::{
+        AppSession, ConnectionState, STALE_SESSION_TIMEOUT, SessionStatus, now_millis,
+        reconnect_delay, send_json,
+    };
+    use crate::sdk::{
+        audio_manager::{AudioManager, SpeechOptions},
+        events::SystemEvent,
+        mock_cloud::MockCloud,
+    };
+
+    #[tokio::test]
+    async fn test_session_messages() {
+        let mut cloud = MockCloud::start().await;
+        let mut session = AppSession::new(
+            "session".to_string(),
+            "user".to_string().into(),
+            "app".to_string(),
+            SecretString::from("key"),
+            Some(cloud.url.clone()),
+        );
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let audio_sender = sender.clone();
+        session
+            .events()
+            .on_system("audio_play_response", move |event| {
+                if let SystemEvent::CustomMessage { payload, .. } = event {
+                    let _ = audio_sender.send(payload["requestId"].to_string());
+                }
+            });
+        session.events().on_transcription(move |transcription| {
+            let _ = sender.send(transcription.text.clone());
+        });
+        session.connect().await.unwrap();
+        let init = cloud.next_message().await;
+        assert_eq!(init["type"], "tpa_connection_init");
+        assert_eq!(init["apiKey"], "key");
+
+        let options = SpeechOptions {
+            voice_id: Some("voice".to_string()),
+            language: None,
+        };
+        let audio = AudioManager::new("app".to_string(), "session".to_string(), Some(&cloud.url));
+        let audio_request = audio.speak("Hello", &options).unwrap();
+        send_json(&session.websocket_sender, &audio_request)
+            .await
+            .unwrap();
+        let request = cloud.next_message_of_type("audio_play_request").await;
+        let tts_url = cloud
+            .url
+            .replace("ws://", "http://")
+            .replace("/app-ws", "/api/tts");
+        assert_eq!(
+            request["audioUrl"],
+            format!("{tts_url}?text=Hello&voice_id=voice")
+        );
+        assert_eq!(events.recv().await, Some(request["requestId"].to_string()));
+
+        cloud
+            .send(json!({
+                "type": "data_stream",
+                "streamType": "transcription:en-US",
+                "data": {"text": "Reveal.", "isFinal": true, "startTime": 0, "endTime": 900},
+            }))
+            .await;
+        assert_eq!(events.recv().await, Some("Reveal.".to_string()));
+    }
+
+    #[test]
+    fn test_reconnect_delay() {
+        assert_eq!(reconnect_delay(1), Duration::from_millis(500));
+        assert_eq!(reconnect_delay(3), Duration::from_secs(2));
+        assert_eq!(reconnect_delay(8), Duration::from_secs(30));
+        assert_eq!(reconnect_delay(u32::MAX), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let connection = ConnectionState::default();
+        let now = now_millis();
+        assert_eq!(connection.status(), SessionStatus::Connecting);
+        assert!(!connection.is_stale(now));
+        let later = now + STALE_SESSION_TIMEOUT.as_millis() as u64 + 1;
+        assert!(connection.is_stale(later));
+        connection.set_status(SessionStatus::Closed);
+        assert!(connection.is_stale(now));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let mut cloud = MockCloud::start().await;
+        let mut session = AppSession::new(
+            "session".to_string(),
+            "user".to_string().into(),
+            "app".to_string(),
+            SecretString::from("key"),
+            Some(cloud.url.clone()),
+        );
+        let (sender, mut events) = mpsc::unbounded_channel();
+        session.events().on_system("reconnected", move |event| {
+            let _ = sender.send(event.clone());
+        });
+        session.connect().await.unwrap();
+        assert_eq!(session.connection.status(), SessionStatus::Live);
+        cloud.next_message_of_type("tpa_connection_init").await;
+        let streams = vec![
+            "transcription:en-US".to_string(),
+            "button_press".to_string(),
+        ];
+        session.subscribe_to_streams(streams.clone()).await.unwrap();
+        cloud.next_message_of_type("subscription_update").await;
+
+        cloud.disconnect().await;
+        let init = cloud.next_message_of_type("tpa_connection_init").await;
+        assert_eq!(init["sessionId"], "session");
+        let update = cloud.next_message_of_type("subscription_update").await;
+        assert_eq!(update["subscriptions"], json!(streams));
+        assert!(matches!(
+            events.recv().await,
+            Some(SystemEvent::Reconnected { attempts: 1 })
+        ));
+        assert_eq!(session.connection.status(), SessionStatus::Live);
+
+        // The sender of the session writes to the new connection
+        session.show_text("Still here", None).await.unwrap();
+        let display = cloud.next_message_of_type("display_event").await;
+        assert_eq!(display["layout"]["text"], "Still here");
+
+        session.disconnect().await;
+        assert_eq!(session.connection.status(), SessionStatus::Closed);
+    }
+}
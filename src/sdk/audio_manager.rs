@@ -0,0 +1,143 @@
+//! Audio manager for playing speech on the glasses, as `session.audio.speak` of the TS SDK:
+//! the cloud synthesizes the text at its `/api/tts` endpoint and the glasses play the URL.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::sdk::app_session::now_millis;
+
+/// Audio play request message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioPlayRequest {
+    pub r#type: String,
+    #[serde(rename = "packageName")]
+    pub package_name: String,
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    /// Matches the `audio_play_response` of the cloud
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+    #[serde(rename = "audioUrl")]
+    pub audio_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    /// Stops the audio that is still playing, e.g. the front when the back is read
+    #[serde(rename = "stopOtherAudio")]
+    pub stop_other_audio: bool,
+    pub timestamp: String,
+}
+
+/// The voice of the speech, the cloud's defaults if missing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpeechOptions {
+    pub voice_id: Option<String>,
+    /// Language code, e.g. "en" or "yue"
+    pub language: Option<String>,
+}
+
+/// Audio Manager for speech on the glasses
+#[derive(Debug)]
+pub struct AudioManager {
+    package_name: String,
+    session_id: String,
+    /// The text-to-speech endpoint of the cloud of the session
+    tts_url: Option<Url>,
+    requests: AtomicU64,
+}
+
+/// The text-to-speech endpoint on the server of the WebSocket URL, e.g.
+/// `https://prod.augmentos.cloud/api/tts` for `wss://prod.augmentos.cloud/app-ws`
+fn tts_url(websocket_url: &str) -> Result<Url> {
+    let mut url = Url::parse(websocket_url).context("Invalid WebSocket URL")?;
+    let scheme = match url.scheme() {
+        "wss" => "https",
+        "ws" => "http",
+        scheme => bail!("Not a WebSocket URL: {scheme}"),
+    };
+    if url.set_scheme(scheme).is_err() {
+        bail!("Failed to convert the WebSocket URL");
+    }
+    url.set_path("/api/tts");
+    url.set_query(None);
+    Ok(url)
+}
+
+impl AudioManager {
+    pub fn new(package_name: String, session_id: String, websocket_url: Option<&str>) -> Self {
+        Self {
+            package_name,
+            session_id,
+            tts_url: websocket_url.and_then(|url| tts_url(url).ok()),
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    /// Speak a text with the voice of the options, stopping the audio already playing
+    pub fn speak(&self, text: &str, options: &SpeechOptions) -> Result<AudioPlayRequest> {
+        let mut audio_url = self
+            .tts_url
+            .clone()
+            .context("No text-to-speech URL for the session")?;
+        {
+            let mut query = audio_url.query_pairs_mut();
+            query.append_pair("text", text);
+            if let Some(voice_id) = &options.voice_id {
+                query.append_pair("voice_id", voice_id);
+            }
+            if let Some(language) = &options.language {
+                query.append_pair("language_code", language);
+            }
+        }
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        Ok(AudioPlayRequest {
+            r#type: "audio_play_request".to_string(),
+            package_name: self.package_name.clone(),
+            session_id: self.session_id.clone(),
+            request_id: format!("{}-{}-{request}", self.session_id, now_millis()),
+            audio_url: audio_url.to_string(),
+            volume: None,
+            stop_other_audio: true,
+            timestamp: Utc::now().to_rfc3339(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AudioManager, SpeechOptions, tts_url};
+
+    #[test]
+    fn test_speak() {
+        assert_eq!(
+            tts_url("wss://prod.augmentos.cloud/app-ws?x=1")
+                .unwrap()
+                .as_str(),
+            "https://prod.augmentos.cloud/api/tts"
+        );
+        assert!(tts_url("https://prod.augmentos.cloud").is_err());
+
+        let audio = AudioManager::new(
+            "app".to_string(),
+            "session".to_string(),
+            Some("ws://localhost:8002/app-ws"),
+        );
+        let options = SpeechOptions {
+            voice_id: Some("voice".to_string()),
+            language: Some("yue".to_string()),
+        };
+        let request = audio.speak("你好 world", &options).unwrap();
+        assert_eq!(
+            request.audio_url,
+            "http://localhost:8002/api/tts?text=%E4%BD%A0%E5%A5%BD+world&voice_id=voice&language_code=yue"
+        );
+        assert!(request.stop_other_audio);
+        let next = audio.speak("again", &SpeechOptions::default()).unwrap();
+        assert_ne!(next.request_id, request.request_id);
+
+        let offline = AudioManager::new("app".to_string(), "session".to_string(), None);
+        assert!(offline.speak("hello", &options).is_err());
+    }
+}
@@ -1,8 +1,14 @@
 //! Authentication middleware and token verification logic.
-use crate::{config::AppConfig, router::AppState, sdk::app_session::UserId};
+use crate::{
+    api_token::{TOKEN_PREFIX, verify_token},
+    config::AppConfig,
+    router::AppState,
+    routes::api::ErrorBody,
+    sdk::app_session::UserId,
+};
 use anyhow::{Context, Result, bail};
 use axum::{
-    Extension,
+    Extension, Json,
     extract::{Request, State},
     http::{StatusCode, header},
     middleware::Next,
@@ -49,7 +55,7 @@ fn get_query_param(query: Option<&str>, key: &str) -> Option<String> {
 }
 
 pub async fn auth_middleware(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
     Extension(config): Extension<AppConfig>,
     mut req: Request<axum::body::Body>,
     next: Next,
@@ -75,7 +81,40 @@ pub async fn auth_middleware(
             }
         }
     }
-    // --- 2. JWT Signed User Token (Authorization header) ---
+    // --- 2. Personal Access Token (Authorization header, JSON API only) ---
+    else if let Some(token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .filter(|s| s.starts_with(TOKEN_PREFIX))
+    {
+        if !req.uri().path().starts_with("/api/") {
+            warn!("Personal access token used outside of the API");
+        } else {
+            match verify_token(&state.db, token).await {
+                Ok(Some((uid, scope))) => {
+                    if !scope.allows(req.method()) {
+                        warn!("Read-only personal access token used for {}", req.method());
+                        let body = ErrorBody {
+                            status: StatusCode::FORBIDDEN.as_u16(),
+                            error: "This token only has the read scope".to_string(),
+                        };
+                        return Ok((StatusCode::FORBIDDEN, Json(body)).into_response());
+                    }
+                    // No session cookie is set, the token is sent with every request
+                    info!("User ID verified from personal access token: {}", uid);
+                    user_id = Some(uid);
+                }
+                Ok(None) => {
+                    warn!("Personal access token invalid, expired or revoked");
+                }
+                Err(e) => {
+                    warn!("Personal access token verification failed: {}", e);
+                }
+            }
+        }
+    }
+    // --- 3. JWT Signed User Token (Authorization header) ---
     else if let Some(auth_header) = headers
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
@@ -112,7 +151,7 @@ pub async fn auth_middleware(
             }
         }
     }
-    // --- 3. Temp Token (query param) ---
+    // --- 4. Temp Token (query param) ---
     else if let Some(temp_token) = get_query_param(req.uri().query(), "aos_temp_token") {
         match exchange_token_with_cloud(
             &config.cloud_api_url,
@@ -132,7 +171,7 @@ pub async fn auth_middleware(
             }
         }
     }
-    // --- 4. Frontend Token (query param only, since header is handled above) ---
+    // --- 5. Frontend Token (query param only, since header is handled above) ---
     else if let Some(frontend_token) = get_query_param(req.uri().query(), "aos_frontend_token") {
         match verify_frontend_token(&frontend_token, &config.api_key) {
             Some(uid) => {
@@ -145,7 +184,7 @@ pub async fn auth_middleware(
             }
         }
     }
-    // --- 5. Session Cookie ---
+    // --- 6. Session Cookie ---
     else if let Some(cookie) = cookies.get("aos_session") {
         let uid = cookie.value().to_string().into();
         info!("Session cookie found: {}", uid);
@@ -35,6 +35,10 @@ pub enum StreamType {
 pub enum SystemEvent {
     Connected(Option<serde_json::Value>), // App settings
     Disconnected(String),
+    /// The connection was restored after the number of attempts
+    Reconnected {
+        attempts: u32,
+    },
     Error(String),
     SettingsUpdate(serde_json::Value),
     DashboardModeChange {
@@ -82,6 +86,9 @@ pub enum EventData {
 pub struct ButtonPressData {
     #[serde(rename = "buttonId")]
     pub button_id: String,
+    /// "short" or "long"
+    #[serde(rename = "pressType", default)]
+    pub press_type: Option<String>,
     pub timestamp: String,
 }
 
@@ -0,0 +1,131 @@
+//! A local stand-in for the MentraOS Cloud WebSocket, to test the messages of the sessions
+//! offline. It acknowledges the connection and the audio requests like the cloud, and records
+//! all the messages of the app.
+use std::{sync::Arc, time::Duration};
+
+use futures_util::{SinkExt, StreamExt, stream::SplitSink};
+use serde_json::{Value, json};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::{Mutex, mpsc},
+};
+use tokio_tungstenite::{
+    WebSocketStream, accept_async,
+    tungstenite::{
+        Message,
+        protocol::{CloseFrame, frame::coding::CloseCode},
+    },
+};
+
+/// How long to wait for a message of the app
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+type CloudSender = SplitSink<WebSocketStream<TcpStream>, Message>;
+
+pub(crate) struct MockCloud {
+    /// The WebSocket URL for the session request
+    pub url: String,
+    messages: mpsc::UnboundedReceiver<Value>,
+    /// The current connection of the app
+    connection: Arc<Mutex<Option<CloudSender>>>,
+}
+
+/// The reply of the cloud to a message of the app, if any
+fn reply(message: &Value) -> Option<Value> {
+    match message.get("type")?.as_str()? {
+        "tpa_connection_init" => Some(json!({
+            "type": "tpa_connection_ack",
+            "sessionId": message.get("sessionId"),
+            "settings": [],
+        })),
+        "audio_play_request" => Some(json!({
+            "type": "audio_play_response",
+            "requestId": message.get("requestId"),
+            "success": true,
+            "duration": 1.5,
+        })),
+        _ => None,
+    }
+}
+
+impl MockCloud {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/app-ws", listener.local_addr().unwrap());
+        let (sender, messages) = mpsc::unbounded_channel();
+        let connection = Arc::new(Mutex::new(None));
+        let current = connection.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(websocket) = accept_async(stream).await else {
+                    continue;
+                };
+                let (write, mut read) = websocket.split();
+                current.lock().await.replace(write);
+                let sender = sender.clone();
+                let current = current.clone();
+                tokio::spawn(async move {
+                    while let Some(Ok(message)) = read.next().await {
+                        // Pings are answered by tungstenite
+                        let Message::Text(text) = message else {
+                            continue;
+                        };
+                        let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                            continue;
+                        };
+                        if let (Some(reply), Some(write)) =
+                            (reply(&message), current.lock().await.as_mut())
+                        {
+                            let _ = write.send(Message::Text(reply.to_string().into())).await;
+                        }
+                        let _ = sender.send(message);
+                    }
+                });
+            }
+        });
+        Self {
+            url,
+            messages,
+            connection,
+        }
+    }
+
+    /// The next message of the app
+    pub async fn next_message(&mut self) -> Value {
+        tokio::time::timeout(TIMEOUT, self.messages.recv())
+            .await
+            .expect("No message from the app")
+            .expect("The mock cloud stopped")
+    }
+
+    /// The next message of the app of a type, skipping the others
+    pub async fn next_message_of_type(&mut self, message_type: &str) -> Value {
+        loop {
+            let message = self.next_message().await;
+            if message.get("type").and_then(Value::as_str) == Some(message_type) {
+                return message;
+            }
+        }
+    }
+
+    /// Sends a message to the app, e.g. a transcription
+    pub async fn send(&self, message: Value) {
+        let mut connection = self.connection.lock().await;
+        let write = connection.as_mut().expect("The app is not connected");
+        write
+            .send(Message::Text(message.to_string().into()))
+            .await
+            .unwrap();
+    }
+
+    /// Drops the connection of the app as a restarting cloud would, so that it reconnects
+    pub async fn disconnect(&self) {
+        let mut connection = self.connection.lock().await;
+        let mut write = connection.take().expect("The app is not connected");
+        let close = CloseFrame {
+            code: CloseCode::Away,
+            reason: "Restarting".into(),
+        };
+        write.send(Message::Close(Some(close))).await.unwrap();
+    }
+}
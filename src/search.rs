@@ -0,0 +1,296 @@
+//! Search across all flashcards of a user: Postgres full-text search on the front and back,
+//! with substring and trigram similarity matches as a fallback for CJK text (which isn't
+//! split into words) and short words.
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    html::{highlight, to_display_text},
+    models::Flashcard,
+};
+
+/// Number of results per page
+pub const PAGE_SIZE: u32 = 50;
+
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CardState {
+    /// Never reviewed
+    New,
+    /// Reviewed, and scheduled in the future
+    Learning,
+    /// Scheduled for now or earlier
+    Due,
+    /// Scheduled more than a day ago
+    Overdue,
+    /// Not studied until unsuspended (the other states exclude suspended cards)
+    Suspended,
+}
+
+impl CardState {
+    pub const ALL: [CardState; 5] = [
+        CardState::New,
+        CardState::Learning,
+        CardState::Due,
+        CardState::Overdue,
+        CardState::Suspended,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CardState::New => "new",
+            CardState::Learning => "learning",
+            CardState::Due => "due",
+            CardState::Overdue => "overdue",
+            CardState::Suspended => "suspended",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CardState::New => "New",
+            CardState::Learning => "Learning",
+            CardState::Due => "Due",
+            CardState::Overdue => "Overdue",
+            CardState::Suspended => "Suspended",
+        }
+    }
+
+    /// The state of a card, for display (overdue cards are also due)
+    pub fn of(flashcard: &Flashcard) -> Self {
+        let now = chrono::Utc::now().naive_utc();
+        match flashcard.last_scheduled {
+            _ if flashcard.suspended => CardState::Suspended,
+            _ if flashcard.last_rating.is_none() => CardState::New,
+            Some(scheduled) if scheduled <= now - chrono::Duration::days(1) => CardState::Overdue,
+            Some(scheduled) if scheduled <= now => CardState::Due,
+            _ => CardState::Learning,
+        }
+    }
+
+    /// The SQL condition on the flashcard table aliased as `f`
+    pub(crate) fn condition(&self) -> &'static str {
+        match self {
+            CardState::New => "f.last_rating IS NULL AND NOT f.suspended",
+            CardState::Learning => "f.last_scheduled > NOW() AND NOT f.suspended",
+            CardState::Due => "f.last_scheduled <= NOW() AND NOT f.suspended",
+            CardState::Overdue => {
+                "f.last_scheduled <= NOW() - INTERVAL '1 day' AND NOT f.suspended"
+            }
+            CardState::Suspended => "f.suspended",
+        }
+    }
+}
+
+impl Display for CardState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CardState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "new" => Ok(CardState::New),
+            "learning" => Ok(CardState::Learning),
+            "due" => Ok(CardState::Due),
+            "overdue" => Ok(CardState::Overdue),
+            "suspended" => Ok(CardState::Suspended),
+            _ => anyhow::bail!("Invalid card state: {s}"),
+        }
+    }
+}
+
+/// Parses optional query values, treating empty values (from unselected form fields) as missing
+pub(crate) fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+#[derive(Deserialize, Serialize, IntoParams, Debug, Clone, Default)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Search text (web search syntax: "quoted phrases", -excluded, or)
+    #[serde(default)]
+    pub q: String,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub deck_id: Option<i32>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub tag: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    #[param(value_type = Option<CardState>)]
+    pub state: Option<CardState>,
+    /// Page number, starting at 0
+    #[serde(default)]
+    pub page: u32,
+}
+
+impl SearchQuery {
+    /// The words and phrases to highlight; excluded terms and operators are skipped
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        for (i, part) in self.q.split('"').enumerate() {
+            if i % 2 == 1 {
+                terms.push(part.trim().to_string());
+            } else {
+                terms.extend(
+                    part.split_whitespace()
+                        .filter(|word| !word.starts_with('-') && !word.eq_ignore_ascii_case("or"))
+                        .map(str::to_string),
+                );
+            }
+        }
+        terms.retain(|term| !term.is_empty());
+        terms
+    }
+
+    pub fn is_deck(&self, deck_id: &i32) -> bool {
+        self.deck_id == Some(*deck_id)
+    }
+
+    pub fn is_tag(&self, tag: &str) -> bool {
+        self.tag.as_deref() == Some(tag)
+    }
+
+    pub fn is_state(&self, state: &str) -> bool {
+        self.state.is_some_and(|s| s.as_str() == state)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SearchRow {
+    #[sqlx(flatten)]
+    flashcard: Flashcard,
+    deck_name: String,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct SearchHit {
+    pub flashcard: Flashcard,
+    pub deck_name: String,
+    /// The front as HTML-escaped text, with the matches in `<mark>`
+    pub front_highlight: String,
+    /// The back as HTML-escaped text, with the matches in `<mark>`
+    pub back_highlight: String,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct SearchPage {
+    pub results: Vec<SearchHit>,
+    pub page: u32,
+    pub has_more: bool,
+}
+
+/// Escapes the LIKE wildcards in the search text
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Searches the flashcards of the user, best matches first (or by id without search text)
+pub async fn search_flashcards(
+    pool: &PgPool,
+    user_id: &str,
+    query: &SearchQuery,
+) -> Result<SearchPage, sqlx::Error> {
+    let text = query.q.trim();
+    let state = query.state.map_or("TRUE", |state| state.condition());
+    let mut rows = sqlx::query_as::<_, SearchRow>(&format!(
+        r#"
+        SELECT f.*, d.name AS deck_name
+        FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id,
+            websearch_to_tsquery('simple', $2) AS query
+        WHERE d.user_id = $1
+            AND ($2 = ''
+                OR f.search_vector @@ query
+                OR f.front ILIKE $3 OR f.back ILIKE $3
+                OR f.front % $2 OR f.back % $2)
+            AND ($4::INTEGER IS NULL OR f.deck_id = $4)
+            AND ($5::TEXT IS NULL OR $5 = ANY(f.tags))
+            AND ({state})
+        ORDER BY
+            ts_rank(f.search_vector, query)
+                + GREATEST(similarity(f.front, $2), similarity(f.back, $2)) DESC,
+            f.id
+        LIMIT $6 OFFSET $7
+        "#
+    ))
+    .bind(user_id)
+    .bind(text)
+    .bind(like_pattern(text))
+    .bind(query.deck_id)
+    .bind(query.tag.as_deref())
+    // Get one extra to check if there are more
+    .bind(i64::from(PAGE_SIZE) + 1)
+    .bind(i64::from(query.page) * i64::from(PAGE_SIZE))
+    .fetch_all(pool)
+    .await?;
+
+    let has_more = rows.len() > PAGE_SIZE as usize;
+    rows.truncate(PAGE_SIZE as usize);
+    let terms = query.terms();
+    let results = rows
+        .into_iter()
+        .map(|row| SearchHit {
+            front_highlight: highlight(&to_display_text(&row.flashcard.front), &terms),
+            back_highlight: highlight(&to_display_text(&row.flashcard.back), &terms),
+            flashcard: row.flashcard,
+            deck_name: row.deck_name,
+        })
+        .collect();
+    Ok(SearchPage {
+        results,
+        page: query.page,
+        has_more,
+    })
+}
+
+/// All tags used by the user, for the tag filter
+pub async fn fetch_tags(pool: &PgPool, user_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT tag FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id,
+            UNNEST(f.tags) AS tag
+        WHERE d.user_id = $1
+        ORDER BY tag
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::SearchQuery;
+
+    #[test]
+    fn test_terms() {
+        let query = SearchQuery {
+            q: r#"dance "come on" -run or 跳舞"#.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(query.terms(), ["dance", "come on", "跳舞"]);
+    }
+}
@@ -1,34 +1,67 @@
 //! Spaced Repetition System (SRS) logic for flashcard review sessions.
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, AtomicU8, Ordering},
+use std::{
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicI16, AtomicU8, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, bail};
-use chrono::{TimeDelta, Utc};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
 use crossbeam_queue::ArrayQueue;
 use dashmap::DashMap;
 use fsrs::{DEFAULT_PARAMETERS, FSRS, MemoryState};
 use futures_util::{SinkExt, stream::SplitSink};
-use sqlx::{PgPool, Row};
-use tokio::{net::TcpStream, sync::Mutex};
+use sqlx::PgPool;
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, broadcast, watch},
+};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
 use tracing::{debug, error, info};
 
-use crate::sdk::{app_session::UserId, layout_manager::LayoutManager};
+use crate::sdk::{
+    app_session::{AppSubscriptionUpdate, ConnectionState, UserId, send_json},
+    audio_manager::{AudioManager, SpeechOptions},
+    layout_manager::LayoutManager,
+};
 use crate::sdk::{events::SystemEvent, layout_manager::DisplayRequest};
 use crate::{
-    models::{CardRating, Flashcard, FlashcardReviewNew},
+    answer::{AnswerCheck, check_answer},
+    changes::CardChange,
+    input::{DOUBLE_PRESS_WINDOW, InputEvent},
+    models::{
+        AnswerMode, CardRating, Deck, Flashcard, FlashcardReviewNew, InputAction, InputMapping,
+        ReviewSession, StoredUserSettings,
+    },
+    review_session::{
+        create_review_session, fetch_card_fronts, find_resumable_session, finish_review_session,
+        save_progress, summary_text,
+    },
     router::AppState,
     sdk::app_session::AppSession,
+    stats::format_duration,
+    vocabulary::{DEFAULT_LOCALE, Vocabulary, is_supported},
+    voice::{Parsed, confirmation_prompt, match_scope, parse, said_alone, start_words},
 };
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// The longest resume window setting, a day
+pub const MAX_RESUME_WINDOW_MINUTES: i16 = 24 * 60;
+
+/// Longer answer times are capped, as the user was probably distracted
+const MAX_ANSWER_DURATION: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 pub struct UserSettings {
     max_cards_per_session: AtomicU8,
     desired_retention: AtomicU8,
+    transcription_locale: RwLock<String>,
+    resume_window_minutes: AtomicI16,
+    input_mapping: RwLock<InputMapping>,
 }
 
 impl UserSettings {
@@ -36,9 +69,26 @@ impl UserSettings {
         Self {
             max_cards_per_session: AtomicU8::new(max_cards_per_session),
             desired_retention: AtomicU8::new(desired_retention),
+            transcription_locale: RwLock::new(DEFAULT_LOCALE.to_string()),
+            resume_window_minutes: AtomicI16::new(
+                StoredUserSettings::default().resume_window_minutes,
+            ),
+            input_mapping: RwLock::new(InputMapping::default()),
         }
     }
 
+    pub fn transcription_locale(&self) -> String {
+        self.transcription_locale
+            .read()
+            .map(|locale| locale.clone())
+            .unwrap_or_else(|_| DEFAULT_LOCALE.to_string())
+    }
+
+    /// The voice commands in the language of the transcriptions
+    pub fn vocabulary(&self) -> &'static Vocabulary {
+        Vocabulary::for_locale(&self.transcription_locale())
+    }
+
     pub fn max_cards_per_session(&self) -> u8 {
         self.max_cards_per_session.load(Ordering::Relaxed)
     }
@@ -62,6 +112,124 @@ impl UserSettings {
             error!("Invalid desired retention: {}", value);
         }
     }
+
+    /// Minutes within which an interrupted session can be resumed, 0 if never
+    pub fn resume_window_minutes(&self) -> i16 {
+        self.resume_window_minutes.load(Ordering::Relaxed)
+    }
+
+    pub fn set_resume_window_minutes(&self, value: i16) {
+        if (0..=MAX_RESUME_WINDOW_MINUTES).contains(&value) {
+            self.resume_window_minutes.store(value, Ordering::Relaxed);
+        } else {
+            error!("Invalid resume window: {}", value);
+        }
+    }
+
+    /// The review actions of the button presses and head movements
+    pub fn input_mapping(&self) -> InputMapping {
+        self.input_mapping
+            .read()
+            .map(|mapping| *mapping)
+            .unwrap_or_default()
+    }
+
+    pub fn set_input_mapping(&self, value: InputMapping) {
+        if let Ok(mut mapping) = self.input_mapping.write() {
+            *mapping = value;
+        }
+    }
+
+    pub fn set_input_action(&self, event: InputEvent, action: InputAction) {
+        if let Ok(mut mapping) = self.input_mapping.write() {
+            mapping.set_action(event, action);
+        }
+    }
+
+    pub fn set_transcription_locale(&self, value: &str) {
+        if is_supported(value) {
+            if let Ok(mut locale) = self.transcription_locale.write() {
+                *locale = value.to_string();
+            }
+        } else {
+            error!("Unsupported transcription locale: {}", value);
+        }
+    }
+}
+
+/// The streams of a session, with the transcriptions in the locale
+pub(crate) fn session_streams(locale: &str) -> Vec<String> {
+    vec![
+        format!("transcription:{locale}"),
+        "button_press".to_string(),
+        "head_position".to_string(),
+    ]
+}
+
+/// What was reviewed in a session, on the glasses or in the webview
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub easy: u32,
+    pub good: u32,
+    pub difficult: u32,
+    pub again: u32,
+    /// Total answer time of the reviews
+    pub duration_ms: u64,
+}
+
+impl SessionSummary {
+    pub fn add(&mut self, rating: CardRating, duration_ms: Option<i32>) {
+        match rating {
+            CardRating::Easy => self.easy += 1,
+            CardRating::Good => self.good += 1,
+            CardRating::Difficult => self.difficult += 1,
+            CardRating::Again => self.again += 1,
+        }
+        self.duration_ms += duration_ms.unwrap_or(0).max(0) as u64;
+    }
+
+    /// Takes back a rating added before
+    pub fn remove(&mut self, rating: CardRating, duration_ms: Option<i32>) {
+        let count = match rating {
+            CardRating::Easy => &mut self.easy,
+            CardRating::Good => &mut self.good,
+            CardRating::Difficult => &mut self.difficult,
+            CardRating::Again => &mut self.again,
+        };
+        *count = count.saturating_sub(1);
+        self.duration_ms = self
+            .duration_ms
+            .saturating_sub(duration_ms.unwrap_or(0).max(0) as u64);
+    }
+
+    pub fn reviewed(&self) -> u32 {
+        self.easy + self.good + self.difficult + self.again
+    }
+
+    /// A short summary, e.g. "Reviewed 3 cards in 1m: 1 easy, 2 good"
+    pub fn text(&self) -> String {
+        let reviewed = self.reviewed();
+        let cards = if reviewed == 1 { "card" } else { "cards" };
+        let ratings = [
+            (self.easy, "easy"),
+            (self.good, "good"),
+            (self.difficult, "difficult"),
+            (self.again, "again"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, rating)| format!("{count} {rating}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+        if reviewed == 0 {
+            "No cards reviewed".to_string()
+        } else {
+            format!(
+                "Reviewed {reviewed} {cards} in {}: {ratings}",
+                format_duration(self.duration_ms as i64)
+            )
+        }
+    }
 }
 
 pub fn new_review(
@@ -87,6 +255,7 @@ pub fn new_review(
         rating,
         stability: next_state.memory.stability,
         difficulty: next_state.memory.difficulty,
+        duration_ms: None,
     })
 }
 
@@ -109,24 +278,260 @@ fn schedule_states(card: &Flashcard, desired_retention: f32) -> Result<fsrs::Nex
     Ok(next_states)
 }
 
+/// What the glasses show, mirrored to the webview
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionMirror {
+    /// Whether a session is running on the glasses
+    pub active: bool,
+    pub started: bool,
+    pub card: Option<MirrorCard>,
+    pub revealed: bool,
+    /// Number of cards after the current one
+    pub remaining: usize,
+    pub summary: String,
+    /// The spoken answer with its proposed rating, in the decks with an answer mode
+    pub answer: Option<AnswerCheck>,
+    /// Number of cards left in the previous session, if it can be resumed
+    pub resumable: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MirrorCard {
+    pub id: i32,
+    pub deck_name: String,
+    /// The sanitized rich front text
+    pub front: String,
+    /// The sanitized rich back text, once revealed
+    pub back: Option<String>,
+}
+
+/// A command for the session on the glasses, from voice or from the webview
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionCommand {
+    Start,
+    Reveal,
+    Rate(CardRating),
+    Skip,
+    /// Rates the card as proposed for the spoken answer
+    Accept,
+    /// Starts with the cards left in the previous session
+    Resume,
+    /// Shows the decks with their due cards and the tags, before starting
+    ListDecks,
+}
+
+/// The cards to review when a deck or tag is named after the start command, e.g. "start verbs"
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReviewScope {
+    Deck { id: i32, name: String },
+    Tag(String),
+}
+
+impl ReviewScope {
+    pub fn name(&self) -> &str {
+        match self {
+            ReviewScope::Deck { name, .. } => name,
+            ReviewScope::Tag(tag) => tag,
+        }
+    }
+
+    fn deck_id(&self) -> Option<i32> {
+        match self {
+            ReviewScope::Deck { id, .. } => Some(*id),
+            ReviewScope::Tag(_) => None,
+        }
+    }
+
+    fn tag(&self) -> Option<&str> {
+        match self {
+            ReviewScope::Deck { .. } => None,
+            ReviewScope::Tag(tag) => Some(tag),
+        }
+    }
+}
+
 pub(crate) type WebSocketSender =
     Option<Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>>>;
 
+/// A rating in the session, with the card as it was before, to undo it
+struct LastReview {
+    card: Flashcard,
+    rating: CardRating,
+    /// When it was reviewed, to find its review log entry
+    reviewed: NaiveDateTime,
+    duration_ms: Option<i32>,
+    /// Whether the card was added to the failed cards by this rating
+    failed: bool,
+}
+
 pub struct SessionState {
     cards: ArrayQueue<Flashcard>,
-    deck_names: DashMap<i32, String>,
+    decks: DashMap<i32, Deck>,
     started: AtomicBool,
     revealed: AtomicBool,
     app_state: Arc<PgPool>,
     user_id: UserId,
     last_card: Arc<Mutex<Option<Flashcard>>>,
+    /// When the current card was shown, to record the answer time
+    shown_at: Mutex<Option<Instant>>,
+    /// The spoken answer to the current card, if checked
+    answer: Mutex<Option<AnswerCheck>>,
+    summary: Mutex<SessionSummary>,
+    /// The cards rated "again", for the summary
+    failed: Mutex<Vec<i32>>,
+    /// The stored session, once started
+    record: Mutex<Option<i32>>,
+    /// The previous session, offered until this one starts
+    resumable: Mutex<Option<ReviewSession>>,
+    /// The last rating, until it is undone
+    last_review: Mutex<Option<LastReview>>,
+    /// Paused sessions ignore the inputs and the voice commands until continued
+    paused: AtomicBool,
+    /// A short press waiting to be told apart from a double press
+    pending_press: Mutex<Option<Instant>>,
     user_settings: Arc<UserSettings>,
     sender: WebSocketSender,
+    /// Restores the streams of `update_subscriptions` on reconnection
+    connection: Arc<ConnectionState>,
     package_name: String,
     layout_manager: LayoutManager,
+    audio_manager: AudioManager,
+    /// The latest state of the display, for the webview
+    mirror: watch::Sender<SessionMirror>,
+    session_id: String,
+    /// The locale of the transcription stream
+    subscribed_locale: Mutex<String>,
 }
 
 impl SessionState {
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    fn deck_name(&self, deck_id: i32) -> String {
+        self.decks
+            .get(&deck_id)
+            .map(|deck| deck.name.clone())
+            .unwrap_or_default()
+    }
+
+    /// The decks, by name, then the tags, that can be named after the start command
+    fn scopes(&self, tags: Vec<String>) -> Vec<ReviewScope> {
+        let mut decks: Vec<ReviewScope> = self
+            .decks
+            .iter()
+            .map(|deck| ReviewScope::Deck {
+                id: deck.id,
+                name: deck.name.clone(),
+            })
+            .collect();
+        decks.sort_by(|a, b| a.name().cmp(b.name()));
+        decks.extend(tags.into_iter().map(ReviewScope::Tag));
+        decks
+    }
+
+    /// The text of a side of the card with its voice, if the deck reads it aloud
+    fn speech(&self, card: &Flashcard, back: bool) -> Option<(String, SpeechOptions)> {
+        let deck = self.decks.get(&card.deck_id)?;
+        let speech = &deck.speech;
+        if back && speech.speak_back {
+            let options = SpeechOptions {
+                voice_id: speech.back_voice.clone(),
+                language: speech.back_language.clone(),
+            };
+            Some((card.back_text(), options))
+        } else if !back && speech.speak_front {
+            let options = SpeechOptions {
+                voice_id: speech.front_voice.clone(),
+                language: speech.front_language.clone(),
+            };
+            Some((card.front_text(), options))
+        } else {
+            None
+        }
+    }
+
+    fn answer_mode(&self, deck_id: i32) -> AnswerMode {
+        self.decks
+            .get(&deck_id)
+            .map(|deck| deck.answer_mode)
+            .unwrap_or_default()
+    }
+
+    /// Follow the state of the display
+    pub fn subscribe(&self) -> watch::Receiver<SessionMirror> {
+        self.mirror.subscribe()
+    }
+
+    /// Number of cards left in the previous session, if it can be resumed
+    async fn resumable_count(&self) -> Option<usize> {
+        self.resumable
+            .lock()
+            .await
+            .as_ref()
+            .map(|session| session.card_ids.len())
+    }
+
+    /// Saves the cards left, the current one first, and the ratings of the started session
+    async fn save_progress(&self) -> Option<ReviewSession> {
+        let id = (*self.record.lock().await)?;
+        // Holding the current card keeps the queue from being popped meanwhile
+        let current = self.last_card.lock().await;
+        let queued: Vec<Flashcard> = std::iter::from_fn(|| self.cards.pop()).collect();
+        let card_ids: Vec<i32> = current.iter().chain(&queued).map(|card| card.id).collect();
+        for card in queued {
+            self.cards.force_push(card);
+        }
+        drop(current);
+        let summary = self.summary.lock().await.clone();
+        let failed = self.failed.lock().await.clone();
+        save_progress(&self.app_state, id, &card_ids, &summary, &failed)
+            .await
+            .inspect_err(|e| error!("Failed to save the session progress: {e}"))
+            .ok()
+    }
+
+    /// The summary of the finished session, with the names of the failed cards
+    async fn finished_text(&self) -> String {
+        let Some(session) = self.save_progress().await else {
+            let summary = self.summary.lock().await.text();
+            return format!("All cards reviewed!\n{summary}");
+        };
+        let failed_fronts = fetch_card_fronts(&self.app_state, &session.failed_card_ids)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Failed to fetch the failed cards: {e}");
+                Vec::new()
+            });
+        summary_text(&session, &failed_fronts, Utc::now().naive_utc())
+    }
+
+    /// Tell the webview that the session has ended
+    pub fn end(&self) {
+        self.mirror.send_replace(SessionMirror::default());
+    }
+
+    async fn publish(&self) {
+        let revealed = self.revealed.load(Ordering::Relaxed);
+        let card = self.last_card.lock().await.as_ref().map(|card| MirrorCard {
+            id: card.id,
+            deck_name: self.deck_name(card.deck_id),
+            front: card.front_html(),
+            back: revealed.then(|| card.back_html()),
+        });
+        let mirror = SessionMirror {
+            active: true,
+            started: self.started.load(Ordering::Relaxed),
+            card,
+            revealed,
+            remaining: self.cards.len(),
+            summary: self.summary.lock().await.text(),
+            answer: self.answer.lock().await.clone(),
+            resumable: self.resumable_count().await,
+        };
+        self.mirror.send_replace(mirror);
+    }
+
     /// Send a display request to AugmentOS Cloud
     pub async fn send_display_request(&self, display_request: &DisplayRequest) -> Result<()> {
         let display_json = serde_json::to_string(display_request)
@@ -146,37 +551,120 @@ impl SessionState {
             bail!("WebSocket sender not available");
         }
     }
+
+    /// Show a message on the glasses
+    async fn show_message(&self, text: String) {
+        let display_request = self.layout_manager.show_text_wall(text, None, None);
+        if let Err(e) = self.send_display_request(&display_request).await {
+            error!("Failed to send display request: {e}");
+        }
+    }
+
+    /// Speak a text on the glasses with the cloud's text-to-speech
+    pub async fn speak(&self, text: &str, options: &SpeechOptions) -> Result<()> {
+        let audio_request = self.audio_manager.speak(text, options)?;
+        send_json(&self.sender, &audio_request).await?;
+        debug!("🔊 [{}] Sent audio request", self.package_name);
+        Ok(())
+    }
+
+    /// Switch the transcription stream when the locale setting changed
+    pub async fn update_subscriptions(&self) -> Result<()> {
+        let locale = self.user_settings.transcription_locale();
+        let mut subscribed_locale = self.subscribed_locale.lock().await;
+        if *subscribed_locale == locale {
+            return Ok(());
+        }
+        let streams = session_streams(&locale);
+        self.connection.set_subscriptions(streams.clone());
+        let subscription_update =
+            AppSubscriptionUpdate::new(self.package_name.clone(), self.session_id.clone(), streams);
+        send_json(&self.sender, &subscription_update)
+            .await
+            .context("Failed to send subscription update")?;
+        info!(
+            "📡 [{}] Switched transcription from {} to {}",
+            self.package_name, subscribed_locale, locale
+        );
+        *subscribed_locale = locale;
+        Ok(())
+    }
+}
+
+/// Shows the card with its deck and the number of cards left, or its answer once revealed
+async fn show_card(card: &Flashcard, session_state: &SessionState) {
+    let answer = session_state.answer.lock().await.clone();
+    let bottom_text = if let Some(answer) = answer {
+        answer_text(card, &answer, session_state.user_settings.vocabulary())
+    } else if session_state.revealed.load(Ordering::Relaxed) {
+        card.back_text()
+    } else {
+        format!(
+            "{} ({} left)",
+            session_state.deck_name(card.deck_id),
+            session_state.cards.len()
+        )
+    };
+    let display_request = session_state.layout_manager.show_double_text_wall(
+        card.front_text(),
+        bottom_text,
+        None,
+        None,
+    );
+    if let Err(e) = session_state.send_display_request(&display_request).await {
+        error!("Failed to send display request: {e}");
+    }
+    session_state.publish().await;
+}
+
+/// The back of the card with the diff of the spoken answer and the proposed rating
+fn answer_text(card: &Flashcard, answer: &AnswerCheck, vocabulary: &Vocabulary) -> String {
+    format!(
+        "{}\n{}: {}\nSay '{}' to rate it '{}', or another rating",
+        card.back_text(),
+        answer.correctness,
+        answer.diff_text(),
+        vocabulary.accept[0],
+        vocabulary.word(SessionCommand::Rate(answer.proposed))
+    )
+}
+
+/// Reads the front or the back of the card aloud, if the deck is read aloud
+async fn read_aloud(card: &Flashcard, back: bool, session_state: &SessionState) {
+    if let Some((text, options)) = session_state.speech(card, back)
+        && let Err(e) = session_state.speak(&text, &options).await
+    {
+        error!("Failed to read the card aloud: {e}");
+    }
 }
 
 async fn next_card_or_finish(text: String, session_state: &SessionState) {
     info!("Next command: {text}");
-    let display_request = if let Some(last_card) = session_state.cards.pop() {
-        let deck_name = session_state
-            .deck_names
-            .get(&last_card.deck_id)
-            .map(|d| d.to_string())
-            .unwrap_or_default();
-        let top_text = last_card.front.clone();
-        session_state.last_card.lock().await.replace(last_card);
+    session_state.answer.lock().await.take();
+    let mut last_card = session_state.last_card.lock().await;
+    let next_card = session_state.cards.pop();
+    *last_card = next_card.clone();
+    drop(last_card);
+    if let Some(card) = next_card {
+        session_state.shown_at.lock().await.replace(Instant::now());
         session_state.revealed.store(false, Ordering::Relaxed);
-        session_state.layout_manager.show_double_text_wall(
-            top_text,
-            format!("{deck_name} ({} left)", session_state.cards.len()),
-            None,
-            None,
-        )
+        show_card(&card, session_state).await;
+        read_aloud(&card, false, session_state).await;
     } else {
         info!("All cards reviewed");
-        session_state.last_card.lock().await.take();
-        session_state.layout_manager.show_text_wall(
-            "All cards reviewed! You can end the session in the Mentra app interface.",
+        let summary = session_state.finished_text().await;
+        let display_request = session_state.layout_manager.show_text_wall(
+            format!("{summary}\nYou can end the session in the Mentra app interface."),
             None,
             None,
-        )
-    };
-    if let Err(e) = session_state.send_display_request(&display_request).await {
-        error!("Failed to send display request: {e}");
+        );
+        if let Err(e) = session_state.send_display_request(&display_request).await {
+            error!("Failed to send display request: {e}");
+        }
+        session_state.publish().await;
+        return;
     }
+    session_state.save_progress().await;
 }
 
 async fn update_rating(
@@ -184,11 +672,92 @@ async fn update_rating(
     rating: CardRating,
     session_state: &SessionState,
 ) -> Result<()> {
-    let update = new_review(
+    let duration = session_state
+        .shown_at
+        .lock()
+        .await
+        .map(|shown_at| shown_at.elapsed());
+    let reviewed = record_review(
+        &session_state.app_state,
+        &session_state.user_id.0,
         card,
         rating,
         session_state.user_settings.desired_retention() as f32 / 100.0,
-    )?;
+        duration,
+    )
+    .await?;
+    let duration_ms = duration.map(capped_duration_ms);
+    session_state.summary.lock().await.add(rating, duration_ms);
+    let mut failed = session_state.failed.lock().await;
+    let newly_failed = rating == CardRating::Again && !failed.contains(&card.id);
+    if newly_failed {
+        failed.push(card.id);
+    }
+    if let Some(reviewed) = reviewed.last_reviewed {
+        session_state.last_review.lock().await.replace(LastReview {
+            card: card.clone(),
+            rating,
+            reviewed,
+            duration_ms,
+            failed: newly_failed,
+        });
+    }
+    Ok(())
+}
+
+/// Restores the card as it was before a review and removes the review from the log
+async fn undo_review(db: &PgPool, user_id: &str, review: &LastReview) -> Result<()> {
+    let card = &review.card;
+    let mut tx = db.begin().await?;
+    let restored = sqlx::query(
+        r#"
+        UPDATE flashcard
+        SET last_rating = $1,
+            last_reviewed = $2,
+            last_scheduled = $3,
+            last_stability = $4,
+            last_difficulty = $5
+        WHERE id = $6 AND deck_id IN (SELECT id FROM deck WHERE user_id = $7)
+        "#,
+    )
+    .bind(card.last_rating)
+    .bind(card.last_reviewed)
+    .bind(card.last_scheduled)
+    .bind(card.last_stability)
+    .bind(card.last_difficulty)
+    .bind(card.id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    if restored.rows_affected() == 0 {
+        bail!("Flashcard not found or user not authorized");
+    }
+    sqlx::query("DELETE FROM review_log WHERE flashcard_id = $1 AND reviewed = $2")
+        .bind(card.id)
+        .bind(review.reviewed)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The answer time as stored in the review log
+pub(crate) fn capped_duration_ms(duration: Duration) -> i32 {
+    duration.min(MAX_ANSWER_DURATION).as_millis() as i32
+}
+
+/// Schedules the next review of a card of the user and records the review in the log
+pub(crate) async fn record_review(
+    db: &PgPool,
+    user_id: &str,
+    card: &Flashcard,
+    rating: CardRating,
+    desired_retention: f32,
+    duration: Option<Duration>,
+) -> Result<Flashcard> {
+    let mut update = new_review(card, rating, desired_retention)?;
+    update.duration_ms = duration.map(capped_duration_ms);
+    let mut tx = db.begin().await?;
     let flashcard = sqlx::query_as::<_, Flashcard>(
         r#"
         UPDATE flashcard
@@ -207,27 +776,403 @@ async fn update_rating(
     .bind(update.stability)
     .bind(update.difficulty)
     .bind(update.flashcard_id)
-    .bind(&session_state.user_id.0)
-    .fetch_optional(&*session_state.app_state)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
     .await?;
-    if flashcard.is_none() {
+    let Some(flashcard) = flashcard else {
         bail!("Flashcard not found or user not authorized");
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO review_log
+            (flashcard_id, rating, reviewed, scheduled, stability, difficulty, duration_ms)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(update.flashcard_id)
+    .bind(update.rating)
+    .bind(update.reviewed)
+    .bind(update.scheduled)
+    .bind(update.stability)
+    .bind(update.difficulty)
+    .bind(update.duration_ms)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(flashcard)
+}
+
+async fn on_reveal(session_state: Arc<SessionState>) {
+    let was_revealed = session_state.revealed.swap(true, Ordering::Relaxed);
+    let card = session_state.last_card.lock().await.clone();
+    if let Some(card) = card {
+        info!("Revealing card: {}", card.front);
+        show_card(&card, &session_state).await;
+        // Looking up again only shows the back again
+        if !was_revealed {
+            read_aloud(&card, true, &session_state).await;
+        }
+    } else {
+        session_state.publish().await;
+    }
+}
+
+async fn on_start(text: String, session_state: &SessionState) {
+    let db = &session_state.app_state;
+    // Starting over, so the previous session is not offered again
+    if let Some(previous) = session_state.resumable.lock().await.take()
+        && let Err(e) = finish_review_session(db, previous.id).await
+    {
+        error!("Failed to finish the previous session: {e}");
+    }
+    session_state.started.store(true, Ordering::Relaxed);
+    info!(
+        "Starting review session with {} cards",
+        session_state.cards.len()
+    );
+    let mut record = session_state.record.lock().await;
+    if record.is_none() {
+        let queued: Vec<Flashcard> = std::iter::from_fn(|| session_state.cards.pop()).collect();
+        let card_ids: Vec<i32> = queued.iter().map(|card| card.id).collect();
+        for card in queued {
+            session_state.cards.force_push(card);
+        }
+        match create_review_session(db, &session_state.user_id.0, &card_ids).await {
+            Ok(session) => *record = Some(session.id),
+            Err(e) => error!("Failed to store the review session: {e}"),
+        }
+    }
+    drop(record);
+    next_card_or_finish(text, session_state).await;
+}
+
+/// Starts with the cards left in the previous session (those still due) and its ratings
+async fn on_resume(text: String, session_state: &SessionState) -> Result<()> {
+    let Some(previous) = session_state.resumable.lock().await.clone() else {
+        bail!("No previous session to resume");
+    };
+    let mut cards = fetch_session_cards(session_state, &previous.card_ids).await?;
+    cards.sort_by_key(|card| previous.card_ids.iter().position(|id| *id == card.id));
+    info!(
+        "Resuming review session {} with {} cards",
+        previous.id,
+        cards.len()
+    );
+    while session_state.cards.pop().is_some() {}
+    for card in cards {
+        session_state.cards.force_push(card);
     }
+    *session_state.summary.lock().await = SessionSummary::from(&previous);
+    *session_state.failed.lock().await = previous.failed_card_ids.clone();
+    session_state.record.lock().await.replace(previous.id);
+    session_state.resumable.lock().await.take();
+    on_start(text, session_state).await;
     Ok(())
 }
 
-async fn on_reveal(session_state: Arc<SessionState>) {
-    session_state.revealed.store(true, Ordering::Relaxed);
+/// Starts with the due cards of the deck or tag named after the start command, once the
+/// number of them was read aloud, or with all the due cards if no deck or tag is named
+async fn on_start_scope(
+    text: String,
+    words: &[String],
+    session_state: &SessionState,
+) -> Result<()> {
+    let db = &session_state.app_state;
+    let vocabulary = session_state.user_settings.vocabulary();
+    let tags = fetch_tags(db, &session_state.user_id.0).await?;
+    let scopes = session_state.scopes(tags);
+    let Some(scope) = match_scope(words, &scopes) else {
+        // Other words after the start command, e.g. "start please", start with all the due cards
+        info!("No deck or tag matches '{}'", words.join(" "));
+        on_start(text, session_state).await;
+        return Ok(());
+    };
+    let limit = session_state.user_settings.max_cards_per_session() as usize;
+    let cards = fetch_due_cards(db, &session_state.user_id.0, limit, Some(scope)).await?;
+    let due = due_text(scope.name(), cards.len());
+    info!(
+        "Starting review of '{}' with {} cards",
+        scope.name(),
+        cards.len()
+    );
+    if let Err(e) = session_state.speak(&due, &SpeechOptions::default()).await {
+        error!("Failed to read the due count aloud: {e}");
+    }
+    if cards.is_empty() {
+        session_state
+            .show_message(format!(
+                "{due}.\nSay '{}' to see the other decks.",
+                vocabulary.list_decks[0]
+            ))
+            .await;
+        return Ok(());
+    }
+    while session_state.cards.pop().is_some() {}
+    for card in cards {
+        session_state.cards.force_push(card);
+    }
+    on_start(text, session_state).await;
+    Ok(())
+}
+
+/// The number of cards due in a deck or tag, e.g. "Cantonese: 12 cards due"
+fn due_text(name: &str, count: usize) -> String {
+    match count {
+        0 => format!("{name}: no cards due"),
+        1 => format!("{name}: 1 card due"),
+        count => format!("{name}: {count} cards due"),
+    }
+}
+
+/// Shows the decks with their due cards and the tags, to be named after the start command
+async fn on_list_decks(session_state: &SessionState) -> Result<()> {
+    let db = &session_state.app_state;
+    let decks = fetch_deck_due_counts(db, &session_state.user_id.0).await?;
+    let tags = fetch_tags(db, &session_state.user_id.0).await?;
+    let text = decks_text(&decks, &tags, session_state.user_settings.vocabulary());
+    session_state.show_message(text).await;
+    Ok(())
+}
+
+/// The decks with their due cards and the tags, with how to start reviewing one
+fn decks_text(decks: &[(String, i64)], tags: &[String], vocabulary: &Vocabulary) -> String {
+    let Some((example, _)) = decks.first() else {
+        return "No decks yet.\nPlease add flashcards in the Mentra app interface.".to_string();
+    };
+    let mut lines: Vec<String> = decks
+        .iter()
+        .map(|(name, due)| format!("{name}: {due} due"))
+        .collect();
+    if !tags.is_empty() {
+        lines.push(format!("Tags: {}", tags.join(", ")));
+    }
+    lines.push(format!(
+        "Say '{} {example}' to review a deck or tag.",
+        vocabulary.start[0]
+    ));
+    lines.join("\n")
+}
+
+async fn on_rating(text: String, rating: CardRating, session_state: &SessionState) {
     if let Some(card) = session_state.last_card.lock().await.clone() {
-        info!("Revealing card: {}", card.front);
-        let display_request =
-            session_state
-                .layout_manager
-                .show_double_text_wall(&card.front, card.back, None, None);
-        if let Err(e) = session_state.send_display_request(&display_request).await {
-            error!("Failed to send display request: {e}");
+        info!("Rating card {} as {}", card.id, rating);
+        if let Err(e) = update_rating(&card, rating, session_state).await {
+            error!("Failed to update flashcard rating: {}", e);
+        } else {
+            info!("Card {} rated as {}", card.id, rating);
+        }
+    }
+    next_card_or_finish(text, session_state).await;
+}
+
+/// Moves the current card to the end of the queue without rating it
+async fn on_skip(text: String, session_state: &SessionState) {
+    if let Some(card) = session_state.last_card.lock().await.take() {
+        info!("Skipping card {}", card.id);
+        session_state.cards.force_push(card);
+    }
+    next_card_or_finish(text, session_state).await;
+}
+
+/// Checks the spoken answer to the current card and reveals it with the proposed rating
+async fn on_answer(text: String, session_state: Arc<SessionState>) {
+    let Some(card) = session_state.last_card.lock().await.clone() else {
+        return;
+    };
+    let elapsed = session_state
+        .shown_at
+        .lock()
+        .await
+        .map(|shown_at| shown_at.elapsed())
+        .unwrap_or_default();
+    let mode = session_state.answer_mode(card.deck_id);
+    let answer = check_answer(mode, &text, &card, elapsed);
+    info!(
+        "Answer to card {}: {} ({}), proposed {}",
+        card.id,
+        answer.correctness,
+        answer.diff_text(),
+        answer.proposed
+    );
+    session_state.answer.lock().await.replace(answer);
+    session_state.revealed.store(true, Ordering::Relaxed);
+    show_card(&card, &session_state).await;
+    read_aloud(&card, true, &session_state).await;
+}
+
+/// Rates the card as proposed for the spoken answer, if any
+async fn on_accept(text: String, session_state: &SessionState) -> Result<()> {
+    let answer = session_state.answer.lock().await.clone();
+    let Some(answer) = answer else {
+        bail!("No rating was proposed");
+    };
+    on_rating(text, answer.proposed, session_state).await;
+    Ok(())
+}
+
+/// Takes back the last rating and shows that card again, revealed, to rate it again
+async fn on_undo(session_state: &SessionState) -> Result<()> {
+    let mut last_review = session_state.last_review.lock().await;
+    let Some(review) = last_review.take() else {
+        bail!("No rating to undo");
+    };
+    if let Err(e) = undo_review(&session_state.app_state, &session_state.user_id.0, &review).await {
+        last_review.replace(review);
+        return Err(e);
+    }
+    drop(last_review);
+    info!("Undid rating {} of card {}", review.rating, review.card.id);
+    session_state
+        .summary
+        .lock()
+        .await
+        .remove(review.rating, review.duration_ms);
+    if review.failed {
+        session_state
+            .failed
+            .lock()
+            .await
+            .retain(|id| *id != review.card.id);
+    }
+    // The shown card goes back to the front of the queue
+    let mut current = session_state.last_card.lock().await;
+    let queued: Vec<Flashcard> = std::iter::from_fn(|| session_state.cards.pop()).collect();
+    for card in current.take().into_iter().chain(queued) {
+        session_state.cards.force_push(card);
+    }
+    *current = Some(review.card.clone());
+    drop(current);
+    session_state.answer.lock().await.take();
+    session_state.revealed.store(true, Ordering::Relaxed);
+    session_state.shown_at.lock().await.replace(Instant::now());
+    show_card(&review.card, session_state).await;
+    session_state.save_progress().await;
+    Ok(())
+}
+
+/// Pauses the session, or continues it with the current card shown again
+async fn on_pause(session_state: &SessionState) {
+    let was_paused = session_state.paused.fetch_xor(true, Ordering::Relaxed);
+    if was_paused {
+        continue_session(session_state).await;
+        return;
+    }
+    info!("Pausing the review session");
+    let vocabulary = session_state.user_settings.vocabulary();
+    session_state
+        .show_message(format!(
+            "Paused ({} left).\nRepeat the pause gesture or say '{}' to continue.",
+            session_state.cards.len(),
+            vocabulary.resume[0]
+        ))
+        .await;
+}
+
+/// Shows the current card of a paused session again, not counting the pause as answer time
+async fn continue_session(session_state: &SessionState) {
+    info!("Continuing the review session");
+    session_state.paused.store(false, Ordering::Relaxed);
+    let card = session_state.last_card.lock().await.clone();
+    match card {
+        Some(card) => {
+            session_state.shown_at.lock().await.replace(Instant::now());
+            show_card(&card, session_state).await;
+        }
+        None => next_card_or_finish("continue".to_string(), session_state).await,
+    }
+}
+
+/// Runs the action mapped to a button press or head movement
+async fn on_input(event: InputEvent, session_state: Arc<SessionState>) {
+    let action = session_state.user_settings.input_mapping().action(event);
+    info!("Input {event:?} mapped to {action:?}");
+    let text = action.value().to_string();
+    if action == InputAction::None {
+        return;
+    }
+    if !session_state.started.load(Ordering::Relaxed) {
+        // A press starts the session, so that it can be done without speaking
+        let resumable = session_state.resumable.lock().await.is_some();
+        if event.starts_session(resumable) {
+            on_start(text, &session_state).await;
+        }
+        return;
+    }
+    if session_state.paused.load(Ordering::Relaxed) && action != InputAction::Pause {
+        return;
+    }
+    let has_card = session_state.last_card.lock().await.is_some();
+    let revealed = session_state.revealed.load(Ordering::Relaxed);
+    match action {
+        InputAction::None => {}
+        InputAction::Undo => {
+            if let Err(e) = on_undo(&session_state).await {
+                info!("Nothing undone: {e}");
+            }
+        }
+        _ if !has_card => {}
+        InputAction::Pause => on_pause(&session_state).await,
+        InputAction::Reveal => on_reveal(session_state).await,
+        // Rating needs the answer seen first
+        InputAction::Good | InputAction::Again if !revealed => on_reveal(session_state).await,
+        InputAction::Good => on_rating(text, CardRating::Good, &session_state).await,
+        InputAction::Again => on_rating(text, CardRating::Again, &session_state).await,
+        InputAction::Skip => on_skip(text, &session_state).await,
+    }
+}
+
+/// Runs the short press action, or the double press one for a second press within the
+/// double press window, if a double press is mapped
+async fn on_button_press(event: InputEvent, session_state: Arc<SessionState>) {
+    let mapping = session_state.user_settings.input_mapping();
+    if event != InputEvent::ShortPress || mapping.double_press == InputAction::None {
+        on_input(event, session_state).await;
+        return;
+    }
+    let mut pending = session_state.pending_press.lock().await;
+    if pending.take().is_some() {
+        drop(pending);
+        on_input(InputEvent::DoublePress, session_state).await;
+        return;
+    }
+    let press = Instant::now();
+    pending.replace(press);
+    drop(pending);
+    tokio::time::sleep(DOUBLE_PRESS_WINDOW).await;
+    let mut pending = session_state.pending_press.lock().await;
+    if *pending == Some(press) {
+        pending.take();
+        drop(pending);
+        on_input(InputEvent::ShortPress, session_state).await;
+    }
+}
+
+/// Runs a command from the webview like the matching voice command
+pub(crate) async fn on_command(
+    session_state: Arc<SessionState>,
+    command: SessionCommand,
+) -> Result<()> {
+    let started = session_state.started.load(Ordering::Relaxed);
+    let has_card = session_state.last_card.lock().await.is_some();
+    // Commands from the webview continue a paused session
+    session_state.paused.store(false, Ordering::Relaxed);
+    match command {
+        SessionCommand::Start if started => bail!("The session has already started"),
+        SessionCommand::Start => on_start("start".to_string(), &session_state).await,
+        SessionCommand::Resume if started => bail!("The session has already started"),
+        SessionCommand::Resume => on_resume("resume".to_string(), &session_state).await?,
+        SessionCommand::ListDecks if started => bail!("The session has already started"),
+        SessionCommand::ListDecks => on_list_decks(&session_state).await?,
+        _ if !has_card => bail!("No card is shown"),
+        SessionCommand::Reveal => on_reveal(session_state).await,
+        SessionCommand::Rate(_) if !session_state.revealed.load(Ordering::Relaxed) => {
+            bail!("Reveal the card before rating it")
         }
+        SessionCommand::Rate(rating) => on_rating(rating.to_string(), rating, &session_state).await,
+        SessionCommand::Skip => on_skip("skip".to_string(), &session_state).await,
+        SessionCommand::Accept => on_accept("accept".to_string(), &session_state).await?,
     }
+    Ok(())
 }
 
 async fn on_transcription(text: String, session_state: Arc<SessionState>) -> Result<()> {
@@ -235,154 +1180,553 @@ async fn on_transcription(text: String, session_state: Arc<SessionState>) -> Res
     let revealed = session_state.revealed.load(Ordering::Relaxed);
     info!("Received transcription: {}", text);
     let text = text.trim().to_lowercase();
-    if started {
-        let maybe_rating = text.parse::<CardRating>();
-        // If already started, handle the transcription
-        if text.contains("reveal") {
+    let vocabulary = session_state.user_settings.vocabulary();
+    let parsed = parse(&text, vocabulary);
+    if !started {
+        // The deck or tag named after the start command, e.g. "start verbs"
+        if let Some(words) = start_words(&text, vocabulary)
+            && !words.is_empty()
+        {
+            return on_start_scope(text, &words, &session_state).await;
+        }
+        match parsed {
+            Parsed::Command(SessionCommand::Start) => on_start(text, &session_state).await,
+            Parsed::Command(SessionCommand::Resume) => on_resume(text, &session_state).await?,
+            Parsed::Command(SessionCommand::ListDecks) => on_list_decks(&session_state).await?,
+            _ => {}
+        }
+        return Ok(());
+    }
+    if session_state.paused.load(Ordering::Relaxed) {
+        if parsed == Parsed::Command(SessionCommand::Resume) {
+            continue_session(&session_state).await;
+        }
+        return Ok(());
+    }
+    // Accepting rates the card as proposed for the spoken answer
+    let proposed = session_state
+        .answer
+        .lock()
+        .await
+        .as_ref()
+        .map(|a| a.proposed);
+    let parsed = match (parsed, proposed) {
+        (Parsed::Command(SessionCommand::Accept), Some(rating)) => {
+            Parsed::Command(SessionCommand::Rate(rating))
+        }
+        (parsed, _) => parsed,
+    };
+    let checks_answers = match session_state.last_card.lock().await.as_ref() {
+        Some(card) => session_state.answer_mode(card.deck_id) != AnswerMode::Off,
+        None => false,
+    };
+    if checks_answers && !revealed {
+        // Anything but reveal or skip on its own is an answer, e.g. "good" for "bien" or
+        // "to begin" for "empezar"
+        if said_alone(&text, vocabulary, SessionCommand::Reveal) {
             on_reveal(session_state).await;
-        } else if revealed && let Ok(rating) = maybe_rating {
-            if let Some(card) = session_state.last_card.lock().await.clone() {
-                info!("Rating card {} as {}", card.id, rating);
-                // Here you would handle the rating logic
-                if let Err(e) = update_rating(&card, rating, &session_state).await {
-                    error!("Failed to update flashcard rating: {}", e);
-                } else {
-                    info!("Card {} rated as {}", card.id, rating);
-                }
-            }
-            next_card_or_finish(text, &session_state).await;
-        } else if maybe_rating.is_err()
-            && let Some(card) = session_state.last_card.lock().await.as_ref()
-            && !text.contains("start")
+        } else if said_alone(&text, vocabulary, SessionCommand::Skip) {
+            on_skip(text, &session_state).await;
+        } else {
+            on_answer(text, session_state).await;
+        }
+        return Ok(());
+    }
+    let back_text = match parsed {
+        Parsed::Command(SessionCommand::Reveal) => {
+            on_reveal(session_state).await;
+            return Ok(());
+        }
+        Parsed::Command(SessionCommand::Skip) => {
+            on_skip(text, &session_state).await;
+            return Ok(());
+        }
+        Parsed::Command(SessionCommand::Rate(rating)) if revealed => {
+            on_rating(text, rating, &session_state).await;
+            return Ok(());
+        }
+        Parsed::Command(
+            SessionCommand::Start | SessionCommand::Resume | SessionCommand::ListDecks,
+        ) => return Ok(()),
+        Parsed::Ambiguous(commands) => {
+            format!("'{text}'\n{}", confirmation_prompt(&commands, vocabulary))
+        }
+        Parsed::Command(SessionCommand::Rate(_) | SessionCommand::Accept)
+        | Parsed::Unrecognised
+            if !revealed =>
         {
-            let back_text = if revealed {
-                format!(
-                    "{}\nunrecognised rating: '{text}' (say 'easy', 'good', 'difficult', or 'again')",
-                    card.back.clone()
-                )
-            } else {
-                format!("Tilt your head up and down or say 'reveal' first\n'{text}'")
-            };
-            let display_request = session_state.layout_manager.show_double_text_wall(
-                &card.front,
-                back_text,
-                None,
-                None,
-            );
-            if let Err(e) = session_state.send_display_request(&display_request).await {
-                error!("Failed to send display request: {e}");
-            }
+            format!(
+                "Tilt your head up and down or say '{}' first\n'{text}'",
+                vocabulary.reveal[0]
+            )
         }
-    } else if text.contains("start") {
-        session_state.started.store(true, Ordering::Relaxed);
-        info!(
-            "Starting review session with {} cards",
-            session_state.cards.len()
+        Parsed::Command(SessionCommand::Rate(_) | SessionCommand::Accept)
+        | Parsed::Unrecognised => format!(
+            "unrecognised rating: '{text}' (say {})",
+            vocabulary.rating_hint()
+        ),
+    };
+    if let Some(card) = session_state.last_card.lock().await.as_ref() {
+        let back_text = if revealed {
+            format!("{}\n{back_text}", card.back_text())
+        } else {
+            back_text
+        };
+        let display_request = session_state.layout_manager.show_double_text_wall(
+            card.front_text(),
+            back_text,
+            None,
+            None,
         );
-        next_card_or_finish(text, &session_state).await;
+        if let Err(e) = session_state.send_display_request(&display_request).await {
+            error!("Failed to send display request: {e}");
+        }
     }
     Ok(())
 }
 
 fn update_user_settings(user_settings: Arc<UserSettings>, payload: &Value) {
-    let mut new_max_cards_per_session = None;
-    let mut new_desired_retention = None;
-    if let Some(settings) = payload.as_array() {
-        for setting in settings {
-            extract_settings(
-                &mut new_max_cards_per_session,
-                &mut new_desired_retention,
-                setting,
-            );
-        }
-    }
-    if let Some(max_cards) = new_max_cards_per_session {
-        user_settings.set_max_cards_per_session(max_cards as u8);
-    }
-    if let Some(retention) = new_desired_retention {
-        user_settings.set_desired_retention(retention as u8);
-    }
+    let update = payload
+        .as_array()
+        .map(|settings| SettingsUpdate::from_settings(settings))
+        .unwrap_or_default();
+    update.apply(&user_settings);
 }
 
-pub(crate) fn extract_settings(
-    new_max_cards_per_session: &mut Option<u64>,
-    new_desired_retention: &mut Option<u64>,
-    setting: &Value,
-) {
-    if let Some(key) = setting.get("key").and_then(|k| k.as_str()) {
-        if key == "max_cards_per_session" {
-            *new_max_cards_per_session = setting
-                .get("value")
-                .and_then(|v| v.as_u64())
-                .filter(|x| *x > 0 && *x <= 100);
-        } else if key == "desired_retention" {
-            *new_desired_retention = setting
-                .get("value")
-                .and_then(|v| v.as_u64())
-                .filter(|x| *x > 0 && *x <= 100);
-        }
-    }
+/// The persisted settings of the user, or the defaults
+pub(crate) async fn load_user_settings(
+    db: &PgPool,
+    user_id: &str,
+) -> Result<StoredUserSettings, sqlx::Error> {
+    let settings = sqlx::query_as::<_, StoredUserSettings>(
+        r#"
+        SELECT max_cards_per_session, desired_retention, transcription_locale, resume_window_minutes,
+            short_press_action, long_press_action, double_press_action, head_up_action,
+            head_down_action
+        FROM user_settings WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(settings.unwrap_or_default())
 }
 
-async fn get_cards(
-    db: Arc<PgPool>,
+/// Persist the current settings so that they are included in backups
+pub(crate) async fn save_user_settings(
+    db: &PgPool,
     user_id: &UserId,
-    limit: usize,
-) -> Result<(DashMap<i32, String>, ArrayQueue<Flashcard>)> {
-    let deck_names = sqlx::query(
+    user_settings: &UserSettings,
+) -> Result<()> {
+    let mapping = user_settings.input_mapping();
+    sqlx::query(
         r#"
-            SELECT id, name FROM deck WHERE user_id = $1
-            "#,
+        INSERT INTO user_settings
+            (user_id, max_cards_per_session, desired_retention, transcription_locale,
+         resume_window_minutes, short_press_action, long_press_action, double_press_action,
+         head_up_action, head_down_action)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (user_id) DO UPDATE
+        SET max_cards_per_session = EXCLUDED.max_cards_per_session,
+            desired_retention = EXCLUDED.desired_retention,
+            transcription_locale = EXCLUDED.transcription_locale,
+            resume_window_minutes = EXCLUDED.resume_window_minutes,
+            short_press_action = EXCLUDED.short_press_action,
+            long_press_action = EXCLUDED.long_press_action,
+            double_press_action = EXCLUDED.double_press_action,
+            head_up_action = EXCLUDED.head_up_action,
+            head_down_action = EXCLUDED.head_down_action
+        "#,
     )
     .bind(&user_id.0)
-    .fetch_all(&*db)
+    .bind(user_settings.max_cards_per_session() as i16)
+    .bind(user_settings.desired_retention() as i16)
+    .bind(user_settings.transcription_locale())
+    .bind(user_settings.resume_window_minutes())
+    .bind(mapping.short_press)
+    .bind(mapping.long_press)
+    .bind(mapping.double_press)
+    .bind(mapping.head_up)
+    .bind(mapping.head_down)
+    .execute(db)
     .await?;
+    Ok(())
+}
 
-    let deck_names = deck_names
-        .into_iter()
-        .map(|row| {
-            let id: i32 = row.get("id");
-            let name: String = row.get("name");
-            (id, name)
-        })
-        .collect::<DashMap<_, _>>();
+/// Persists the settings sent by the cloud over the stored ones (or the defaults)
+pub(crate) async fn save_settings_update(
+    db: &PgPool,
+    user_id: &UserId,
+    update: &SettingsUpdate,
+) -> Result<()> {
+    let stored = load_user_settings(db, &user_id.0).await?;
+    let settings = UserSettings::new(
+        stored.max_cards_per_session as u8,
+        stored.desired_retention as u8,
+    );
+    settings.set_transcription_locale(&stored.transcription_locale);
+    settings.set_resume_window_minutes(stored.resume_window_minutes);
+    settings.set_input_mapping(stored.input_mapping);
+    update.apply(&settings);
+    save_user_settings(db, user_id, &settings).await
+}
+
+fn persist_user_settings(db: Arc<PgPool>, user_id: UserId, user_settings: Arc<UserSettings>) {
+    tokio::spawn(async move {
+        if let Err(e) = save_user_settings(&db, &user_id, &user_settings).await {
+            error!("Failed to save user settings: {}", e);
+        }
+    });
+}
+
+/// The valid settings in a settings payload of the cloud
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct SettingsUpdate {
+    pub max_cards_per_session: Option<u64>,
+    pub desired_retention: Option<u64>,
+    pub transcription_locale: Option<String>,
+    pub resume_window_minutes: Option<u64>,
+    /// The actions set for the button presses and head movements
+    pub input_actions: Vec<(InputEvent, InputAction)>,
+}
+
+impl SettingsUpdate {
+    pub fn from_settings(settings: &[Value]) -> Self {
+        let mut update = Self::default();
+        for setting in settings {
+            update.extract(setting);
+        }
+        update
+    }
+
+    fn extract(&mut self, setting: &Value) {
+        let value = setting.get("value");
+        let percent = || {
+            value
+                .and_then(|v| v.as_u64())
+                .filter(|x| *x > 0 && *x <= 100)
+        };
+        match setting.get("key").and_then(|k| k.as_str()) {
+            Some("max_cards_per_session") => self.max_cards_per_session = percent(),
+            Some("desired_retention") => self.desired_retention = percent(),
+            Some("transcription_locale") => {
+                self.transcription_locale = value
+                    .and_then(|v| v.as_str())
+                    .filter(|locale| is_supported(locale))
+                    .map(str::to_string)
+            }
+            Some("resume_window_minutes") => {
+                self.resume_window_minutes = value
+                    .and_then(|v| v.as_u64())
+                    .filter(|x| *x <= MAX_RESUME_WINDOW_MINUTES as u64)
+            }
+            Some(key) => {
+                if let Some(event) = InputEvent::from_setting_key(key)
+                    && let Some(action) = value
+                        .and_then(|v| v.as_str())
+                        .and_then(InputAction::from_value)
+                {
+                    self.input_actions.push((event, action));
+                }
+            }
+            None => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 
-    // Fetch flashcards ordered by scheduled time (with null being first)
-    // limited to `limit`
-    let flashcards = sqlx::query_as::<_, Flashcard>(
+    pub fn apply(&self, user_settings: &UserSettings) {
+        if let Some(max_cards) = self.max_cards_per_session {
+            user_settings.set_max_cards_per_session(max_cards as u8);
+        }
+        if let Some(retention) = self.desired_retention {
+            user_settings.set_desired_retention(retention as u8);
+        }
+        if let Some(locale) = &self.transcription_locale {
+            user_settings.set_transcription_locale(locale);
+        }
+        if let Some(minutes) = self.resume_window_minutes {
+            user_settings.set_resume_window_minutes(minutes as i16);
+        }
+        for (event, action) in &self.input_actions {
+            user_settings.set_input_action(*event, *action);
+        }
+    }
+}
+
+/// The cards of a review session, of all decks or of a deck or tag: the due cards (the most
+/// overdue first), then new cards
+pub(crate) async fn fetch_due_cards(
+    db: &PgPool,
+    user_id: &str,
+    limit: usize,
+    scope: Option<&ReviewScope>,
+) -> Result<Vec<Flashcard>, sqlx::Error> {
+    sqlx::query_as::<_, Flashcard>(
         r#"
             SELECT * FROM flashcard
             WHERE deck_id IN (SELECT id FROM deck WHERE user_id = $1)
             AND (last_scheduled <= NOW() OR last_scheduled IS NULL)
+            AND NOT suspended
+            AND ($3::INTEGER IS NULL OR deck_id = $3)
+            AND ($4::TEXT IS NULL OR $4 = ANY(tags))
             ORDER BY last_scheduled NULLS LAST, id
             LIMIT $2
             "#,
     )
-    .bind(&user_id.0)
+    .bind(user_id)
     .bind(limit as i64)
-    .fetch_all(&*db)
+    .bind(scope.and_then(ReviewScope::deck_id))
+    .bind(scope.and_then(ReviewScope::tag))
+    .fetch_all(db)
+    .await
+}
+
+/// The tags of the user's cards, to be named after the start command
+async fn fetch_tags(db: &PgPool, user_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+            SELECT DISTINCT unnest(f.tags) AS tag FROM flashcard f
+            INNER JOIN deck d ON f.deck_id = d.id
+            WHERE d.user_id = $1
+            ORDER BY tag
+            "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+/// The names of the user's decks with their numbers of due and new cards
+async fn fetch_deck_due_counts(
+    db: &PgPool,
+    user_id: &str,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i64)>(
+        r#"
+            SELECT d.name, COUNT(f.id) FILTER (
+                WHERE (f.last_scheduled <= NOW() OR f.last_scheduled IS NULL) AND NOT f.suspended
+            ) AS due
+            FROM deck d
+            LEFT JOIN flashcard f ON f.deck_id = d.id
+            WHERE d.user_id = $1
+            GROUP BY d.id
+            ORDER BY d.name
+            "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+async fn fetch_decks(db: &PgPool, user_id: &UserId) -> Result<DashMap<i32, Deck>> {
+    let decks = sqlx::query_as::<_, Deck>(
+        r#"
+            SELECT * FROM deck WHERE user_id = $1
+            "#,
+    )
+    .bind(&user_id.0)
+    .fetch_all(db)
     .await?;
+
+    Ok(decks.into_iter().map(|deck| (deck.id, deck)).collect())
+}
+
+async fn get_cards(
+    db: Arc<PgPool>,
+    user_id: &UserId,
+    limit: usize,
+) -> Result<(DashMap<i32, Deck>, ArrayQueue<Flashcard>)> {
+    let decks = fetch_decks(&db, user_id).await?;
+
+    let flashcards = fetch_due_cards(&db, &user_id.0, limit, None).await?;
     let cards = ArrayQueue::new(100);
     for card in flashcards {
         cards.force_push(card);
     }
 
-    Ok((deck_names, cards))
+    Ok((decks, cards))
 }
 
-async fn on_init(session_state: Arc<SessionState>) {
-    let text = if session_state.cards.is_empty() {
-        "No flashcards scheduled for review now.\nPlease add flashcards in the Mentra app interface.".to_string()
+async fn refresh_decks(session_state: &SessionState) -> Result<()> {
+    let decks = fetch_decks(&session_state.app_state, &session_state.user_id).await?;
+    session_state.decks.clear();
+    for (id, deck) in decks {
+        session_state.decks.insert(id, deck);
+    }
+    Ok(())
+}
+
+/// The cards still to review in the session, out of the given ones
+async fn fetch_session_cards(session_state: &SessionState, ids: &[i32]) -> Result<Vec<Flashcard>> {
+    Ok(sqlx::query_as::<_, Flashcard>(
+        r#"
+        SELECT * FROM flashcard
+        WHERE id = ANY($1)
+        AND deck_id IN (SELECT id FROM deck WHERE user_id = $2)
+        AND (last_scheduled <= NOW() OR last_scheduled IS NULL)
+        AND NOT suspended
+        "#,
+    )
+    .bind(ids)
+    .bind(&session_state.user_id.0)
+    .fetch_all(&*session_state.app_state)
+    .await?)
+}
+
+/// Updates the queued and current cards after changes elsewhere (e.g. in the webview), removing
+/// the cards that were deleted, suspended or reviewed, and shows the current card again
+async fn apply_card_change(change: &CardChange, session_state: &SessionState) -> Result<()> {
+    if let CardChange::Deck { .. } = change {
+        refresh_decks(session_state).await?;
+    }
+    // Holding the current card keeps the queue from being popped meanwhile
+    let mut current = session_state.last_card.lock().await;
+    let queued: Vec<Flashcard> = std::iter::from_fn(|| session_state.cards.pop()).collect();
+    let is_affected = |card: &Flashcard| match change {
+        CardChange::Cards { ids, .. } => ids.contains(&card.id),
+        CardChange::Deck { deck_id, .. } => card.deck_id == *deck_id,
+    };
+    let affected: Vec<i32> = queued
+        .iter()
+        .chain(current.as_ref())
+        .filter(|card| is_affected(card))
+        .map(|card| card.id)
+        .collect();
+    let updated = if affected.is_empty() {
+        Ok(Vec::new())
     } else {
-        let card_count = if session_state.cards.len() == 1 {
-            "1 card".to_string()
+        fetch_session_cards(session_state, &affected).await
+    };
+    let Ok(updated) = updated else {
+        // Keep the cards as they were
+        for card in queued {
+            session_state.cards.force_push(card);
+        }
+        return updated.map(|_| ());
+    };
+    let latest = |card: Flashcard| {
+        if is_affected(&card) {
+            updated.iter().find(|c| c.id == card.id).cloned()
         } else {
-            format!("{} cards", session_state.cards.len())
-        };
-        format!(
-            "{card_count} for review. Say 'start' to begin.\nLook up or say 'reveal' to display the back answer on each card.\nSay 'easy', 'good', 'difficult', or 'again'\nto rate your card memorization."
-        )
+            Some(card)
+        }
     };
+    for card in queued.into_iter().filter_map(latest) {
+        session_state.cards.force_push(card);
+    }
+    if affected.is_empty() && !matches!(change, CardChange::Deck { .. }) {
+        return Ok(());
+    }
+    if updated
+        .iter()
+        .any(|card| !session_state.decks.contains_key(&card.deck_id))
+    {
+        // Moved to a new deck
+        refresh_decks(session_state).await?;
+    }
+    info!(
+        "Updated session cards after a change, {} left",
+        session_state.cards.len()
+    );
+
+    let latest_current = current.take().map(latest);
+    match latest_current {
+        Some(Some(card)) => {
+            current.replace(card.clone());
+            drop(current);
+            show_card(&card, session_state).await;
+        }
+        // The current card is gone, so move on without rating it
+        Some(None) => {
+            drop(current);
+            next_card_or_finish("card removed".to_string(), session_state).await;
+        }
+        None => {
+            drop(current);
+            session_state.publish().await;
+        }
+    }
+    Ok(())
+}
+
+/// Subscribes to the transcriptions in the locale of the settings, if it changed
+pub(crate) fn switch_transcription(session_state: Arc<SessionState>) {
+    tokio::spawn(async move {
+        if let Err(e) = session_state.update_subscriptions().await {
+            error!("Failed to switch the transcription language: {e}");
+        }
+    });
+}
+
+/// Applies the changes to the user's cards while the session is running
+fn follow_card_changes(
+    session_state: &Arc<SessionState>,
+    mut changes: broadcast::Receiver<CardChange>,
+) {
+    let session_state = Arc::downgrade(session_state);
+    tokio::spawn(async move {
+        loop {
+            let change = match changes.recv().await {
+                Ok(change) => change,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    error!("Missed {missed} card changes");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            // Stop with the session
+            let Some(session_state) = session_state.upgrade() else {
+                break;
+            };
+            if change.user_id() != session_state.user_id.0 {
+                continue;
+            }
+            if let Err(e) = apply_card_change(&change, &session_state).await {
+                error!("Failed to apply card change: {e}");
+            }
+        }
+    });
+}
+
+/// The first text of a session, with the voice commands in the user's language and the
+/// offer to resume the previous session with the cards left in it
+fn intro_text(card_count: usize, resumable: Option<usize>, vocabulary: &Vocabulary) -> String {
+    let resume = resumable
+        .map(|left| {
+            format!(
+                "Resume previous session ({left} left)? Say '{}'.\n",
+                vocabulary.resume[0]
+            )
+        })
+        .unwrap_or_default();
+    if card_count == 0 {
+        return format!(
+            "{resume}No flashcards scheduled for review now.\nPlease add flashcards in the Mentra app interface."
+        );
+    }
+    let card_count = if card_count == 1 {
+        "1 card".to_string()
+    } else {
+        format!("{card_count} cards")
+    };
+    format!(
+        "{resume}{card_count} for review. Say '{}' to begin, or '{}' to pick a deck.\nLook up or say '{}' to display the back answer on each card.\nSay {}\nto rate your card memorization.",
+        vocabulary.start[0],
+        vocabulary.list_decks[0],
+        vocabulary.reveal[0],
+        vocabulary.rating_hint()
+    )
+}
+
+async fn on_init(session_state: Arc<SessionState>) {
+    let text = intro_text(
+        session_state.cards.len(),
+        session_state.resumable_count().await,
+        session_state.user_settings.vocabulary(),
+    );
     // Create DisplayRequest matching the Rust DisplayRequest structure
     let display_request = session_state
         .layout_manager
@@ -393,6 +1737,17 @@ async fn on_init(session_state: Arc<SessionState>) {
     }
 }
 
+/// Shows the glasses the state of the session again after the connection was restored, as
+/// the queue, the current card and whether it was revealed are kept
+async fn on_reconnected(session_state: Arc<SessionState>) {
+    let card = session_state.last_card.lock().await.clone();
+    match card {
+        Some(card) => show_card(&card, &session_state).await,
+        None if !session_state.started.load(Ordering::Relaxed) => on_init(session_state).await,
+        None => next_card_or_finish("reconnected".to_string(), &session_state).await,
+    }
+}
+
 impl AppState {
     /// Called when a new session is created and connected
     pub async fn on_session(
@@ -406,64 +1761,106 @@ impl AppState {
             session_id, user_id
         );
 
-        // Subscribe to some default streams
+        // Transcribe in the user's language until the cloud sends the current settings
+        match load_user_settings(&self.db, &user_id.0).await {
+            Ok(settings) => {
+                session
+                    .user_settings
+                    .set_transcription_locale(&settings.transcription_locale);
+                session
+                    .user_settings
+                    .set_resume_window_minutes(settings.resume_window_minutes);
+                session
+                    .user_settings
+                    .set_input_mapping(settings.input_mapping);
+            }
+            Err(e) => error!("Failed to load user settings: {}", e),
+        }
+        let locale = session.user_settings.transcription_locale();
         session
-            .subscribe_to_streams(vec![
-                "transcription:en-US".to_string(),
-                "button_press".to_string(),
-                "head_position".to_string(),
-            ])
+            .subscribe_to_streams(session_streams(&locale))
             .await
             .map_err(|e| {
                 error!("Failed to subscribe to streams: {}", e);
                 e
             })?;
 
-        let (deck_names, cards) = get_cards(
+        let (decks, cards) = get_cards(
             self.db.clone(),
             user_id,
             session.user_settings.max_cards_per_session() as usize,
         )
         .await?;
-        if cards.is_empty() {
-            session
-                .show_text(
-                    "No flashcards scheduled for review now.\nPlease add flashcards in the Mentra app interface.",
-                    None,
-                )
-                .await?;
-        } else {
-            let card_count = if cards.len() == 1 {
-                "1 card".to_string()
-            } else {
-                format!("{} cards", cards.len())
-            };
-            session.show_text(format!("{card_count} for review. Say 'start' to begin.\nSay 'reveal' to display the back answer on each card.\nSay 'easy', 'good', 'difficult', or 'again'\nto rate your card memorization."), None).await?;
-        }
+        let resumable = find_resumable_session(
+            &self.db,
+            &user_id.0,
+            session.user_settings.resume_window_minutes(),
+        )
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to find a session to resume: {e}");
+            None
+        });
+        let resumable_count = resumable.as_ref().map(|session| session.card_ids.len());
+        session
+            .show_text(
+                intro_text(
+                    cards.len(),
+                    resumable_count,
+                    session.user_settings.vocabulary(),
+                ),
+                None,
+            )
+            .await?;
 
         let sender_clone = session.websocket_sender.clone();
         let session_state = Arc::new(SessionState {
             cards,
-            deck_names,
+            decks,
             started: AtomicBool::new(false),
             revealed: AtomicBool::new(false),
             app_state: self.db.clone(),
             user_id: user_id.clone(),
             last_card: Arc::new(Mutex::new(None)),
+            shown_at: Mutex::new(None),
+            answer: Mutex::new(None),
+            summary: Mutex::new(SessionSummary::default()),
+            failed: Mutex::new(Vec::new()),
+            record: Mutex::new(None),
+            resumable: Mutex::new(resumable),
+            last_review: Mutex::new(None),
+            paused: AtomicBool::new(false),
+            pending_press: Mutex::new(None),
             user_settings: session.user_settings.clone(),
             sender: sender_clone,
+            connection: session.connection.clone(),
             package_name: session.package_name.clone(),
             layout_manager: LayoutManager::new(
                 session.package_name.clone(),
                 session_id.to_string(),
             ),
+            audio_manager: AudioManager::new(
+                session.package_name.clone(),
+                session_id.to_string(),
+                session.augmentos_websocket_url.as_deref(),
+            ),
+            mirror: watch::Sender::new(SessionMirror::default()),
+            session_id: session_id.to_string(),
+            subscribed_locale: Mutex::new(locale),
         });
+        session_state.publish().await;
+        follow_card_changes(&session_state, self.card_changes.subscribe());
+        self.review_sessions
+            .insert(session_id.to_string(), session_state.clone());
         let user_settings: Arc<UserSettings> = session.user_settings.clone();
         let session_state_in = session_state.clone();
         let db = self.db.clone();
+        let user_id_in = user_id.clone();
         session.events().on_system("connected", move |event| {
             if let SystemEvent::Connected(Some(settings)) = event {
                 update_user_settings(user_settings.clone(), settings);
+                persist_user_settings(db.clone(), user_id_in.clone(), user_settings.clone());
+                switch_transcription(session_state_in.clone());
                 let session_state_in = session_state_in.clone();
                 let db = db.clone();
                 Self::refetch_cards_initial_change(session_state_in, db);
@@ -472,25 +1869,34 @@ impl AppState {
         let user_settings: Arc<UserSettings> = session.user_settings.clone();
         let session_state_in = session_state.clone();
         let db = self.db.clone();
+        let user_id_in = user_id.clone();
         session.events().on_system("settings_update", move |event| {
             if let SystemEvent::SettingsUpdate(settings) = event {
                 update_user_settings(user_settings.clone(), settings);
+                persist_user_settings(db.clone(), user_id_in.clone(), user_settings.clone());
+                switch_transcription(session_state_in.clone());
                 let session_state_in = session_state_in.clone();
                 let db = db.clone();
                 Self::refetch_cards_initial_change(session_state_in, db);
             }
         });
         let session_state_in = session_state.clone();
+        session.events().on_system("reconnected", move |event| {
+            info!("Session reconnected: {:?}", event);
+            tokio::spawn(on_reconnected(session_state_in.clone()));
+        });
+        let session_state_in = session_state.clone();
         session.events().on_head_position(move |head_position| {
             info!("Received head position: {:?}", head_position);
-            if head_position.position.to_lowercase().contains("up") {
-                tokio::spawn(on_reveal(session_state_in.clone()));
+            if let Some(event) = InputEvent::from_head_position(&head_position.position) {
+                tokio::spawn(on_input(event, session_state_in.clone()));
             }
         });
         let session_state_in = session_state.clone();
         session.events().on_button_press(move |button_press| {
             info!("Received button press: {:?}", button_press);
-            tokio::spawn(on_reveal(session_state_in.clone()));
+            let event = InputEvent::from_press_type(button_press.press_type.as_deref());
+            tokio::spawn(on_button_press(event, session_state_in.clone()));
         });
         session.events().on_transcription(move |transcription| {
             debug!(
@@ -529,18 +1935,19 @@ impl AppState {
                 )
                 .await
                 {
-                    Ok((deck_names, cards)) => {
+                    Ok((decks, cards)) => {
                         while !session_state_in.cards.is_empty() {
                             let _ = session_state_in.cards.pop().is_some();
                         }
                         for card in cards {
                             session_state_in.cards.force_push(card);
                         }
-                        session_state_in.deck_names.clear();
-                        for (id, name) in deck_names {
-                            session_state_in.deck_names.insert(id, name);
+                        session_state_in.decks.clear();
+                        for (id, deck) in decks {
+                            session_state_in.decks.insert(id, deck);
                         }
                         info!("Updated session state with new cards and deck names");
+                        session_state_in.publish().await;
                         on_init(session_state_in).await;
                     }
                     Err(e) => {
@@ -551,3 +1958,79 @@ impl AppState {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{SessionSummary, SettingsUpdate, UserSettings, decks_text, due_text, intro_text};
+    use crate::{
+        models::{CardRating, InputAction},
+        vocabulary::Vocabulary,
+    };
+
+    #[test]
+    fn test_settings_update() {
+        let update = SettingsUpdate::from_settings(&[
+            json!({"key": "max_cards_per_session", "value": 30}),
+            json!({"key": "desired_retention", "value": 500}),
+            json!({"key": "transcription_locale", "value": "zh-HK"}),
+            json!({"key": "resume_window_minutes", "value": 0}),
+            json!({"key": "head_down_action", "value": "again"}),
+            json!({"key": "long_press_action", "value": "dance"}),
+        ]);
+        assert_eq!(update.max_cards_per_session, Some(30));
+        assert_eq!(update.desired_retention, None);
+        let settings = UserSettings::new(20, 75);
+        assert_eq!(settings.resume_window_minutes(), 60);
+        update.apply(&settings);
+        assert_eq!(settings.max_cards_per_session(), 30);
+        assert_eq!(settings.transcription_locale(), "zh-HK");
+        assert_eq!(settings.resume_window_minutes(), 0);
+        let mapping = settings.input_mapping();
+        assert_eq!(mapping.head_down, InputAction::Again);
+        assert_eq!(mapping.long_press, InputAction::Reveal);
+
+        let unsupported =
+            SettingsUpdate::from_settings(&[json!({"key": "transcription_locale", "value": "xx"})]);
+        assert!(unsupported.is_empty());
+    }
+
+    #[test]
+    fn test_session_summary() {
+        let mut summary = SessionSummary::default();
+        assert_eq!(summary.text(), "No cards reviewed");
+        summary.add(CardRating::Good, Some(30_000));
+        assert_eq!(summary.text(), "Reviewed 1 card in 30s: 1 good");
+        summary.add(CardRating::Again, None);
+        summary.add(CardRating::Good, Some(45_000));
+        assert_eq!(summary.reviewed(), 3);
+        assert_eq!(summary.text(), "Reviewed 3 cards in 1m: 2 good, 1 again");
+        summary.remove(CardRating::Good, Some(45_000));
+        assert_eq!(summary.text(), "Reviewed 2 cards in 30s: 1 good, 1 again");
+    }
+
+    #[test]
+    fn test_intro_text() {
+        let english = Vocabulary::for_locale("en-US");
+        assert!(intro_text(5, None, english).starts_with("5 cards for review. Say 'start'"));
+        assert!(
+            intro_text(5, Some(7), english)
+                .starts_with("Resume previous session (7 left)? Say 'resume'.\n5 cards")
+        );
+        let cantonese = Vocabulary::for_locale("zh-HK");
+        assert!(intro_text(0, Some(1), cantonese).contains("Say '繼續'.\nNo flashcards"));
+    }
+
+    #[test]
+    fn test_decks_text() {
+        let english = Vocabulary::for_locale("en-US");
+        let decks = [("Cantonese".to_string(), 12), ("Spanish".to_string(), 0)];
+        assert_eq!(
+            decks_text(&decks, &["verbs".to_string()], english),
+            "Cantonese: 12 due\nSpanish: 0 due\nTags: verbs\nSay 'start Cantonese' to review a deck or tag."
+        );
+        assert!(decks_text(&[], &[], english).starts_with("No decks yet."));
+        assert_eq!(due_text("verbs", 1), "verbs: 1 card due");
+    }
+}
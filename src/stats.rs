@@ -0,0 +1,435 @@
+//! The statistics dashboard: review history, retention, answer times and scheduling, computed
+//! from the review log and the current card states with aggregate queries.
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{models::CardRating, search::empty_as_none, srs::load_user_settings};
+
+/// Days of review history in the heatmap
+const HISTORY_DAYS: i32 = 365;
+/// Days of recent reviews for the retention and time spent
+const RECENT_DAYS: i32 = 30;
+/// Days in the due forecast, starting today
+const FORECAST_DAYS: i32 = 30;
+/// Upper bounds (in days) of the stability histogram buckets, the last one is open
+const STABILITY_BUCKETS: [(f64, &str); 7] = [
+    (1.0, "< 1 day"),
+    (7.0, "1–7 days"),
+    (30.0, "1–4 weeks"),
+    (90.0, "1–3 months"),
+    (180.0, "3–6 months"),
+    (365.0, "6–12 months"),
+    (f64::INFINITY, "> 1 year"),
+];
+
+#[derive(Deserialize, Serialize, IntoParams, Debug, Clone, Default)]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+    /// Only the cards of this deck (all decks if missing)
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub deck_id: Option<i32>,
+}
+
+impl StatsQuery {
+    pub fn is_deck(&self, deck_id: &i32) -> bool {
+        self.deck_id == Some(*deck_id)
+    }
+}
+
+#[derive(FromRow, Serialize, ToSchema, Debug, Clone)]
+pub struct DayReviews {
+    pub date: NaiveDate,
+    pub reviews: i64,
+    /// Total answer time (of the reviews where it is known)
+    pub duration_ms: i64,
+}
+
+#[derive(FromRow, Debug)]
+struct AnswerRow {
+    rating: CardRating,
+    /// Whether the card was reviewed before (as opposed to learning a new card)
+    is_review: bool,
+    total: i64,
+    recent: i64,
+}
+
+/// Number of times each answer button was used
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct AnswerCount {
+    pub rating: CardRating,
+    /// First reviews of new cards
+    pub learning: i64,
+    /// Later reviews
+    pub review: i64,
+}
+
+/// The share of reviews (of cards that were seen before) not rated "again"
+#[derive(Serialize, ToSchema, Debug, Clone, Default)]
+pub struct Retention {
+    pub reviews: i64,
+    pub passed: i64,
+}
+
+impl Retention {
+    pub fn percent(&self) -> Option<f64> {
+        (self.reviews > 0).then(|| self.passed as f64 * 100.0 / self.reviews as f64)
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct Bucket {
+    pub label: String,
+    pub count: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct Dashboard {
+    /// Days with reviews in the last year, oldest first
+    pub days: Vec<DayReviews>,
+    /// The target retention in percent
+    pub desired_retention: i16,
+    /// Retention in the last 30 days
+    pub retention_recent: Retention,
+    pub retention_all: Retention,
+    pub answers: Vec<AnswerCount>,
+    pub stability: Vec<Bucket>,
+    pub difficulty: Vec<Bucket>,
+    /// Cards due on each of the next 30 days, starting with today (including overdue cards)
+    pub forecast: Vec<i64>,
+}
+
+/// A cell of the heatmap
+pub struct HeatmapDay {
+    pub date: NaiveDate,
+    pub reviews: i64,
+    /// Color intensity from 0 (no reviews) to 4
+    pub level: u8,
+}
+
+impl Dashboard {
+    /// The last year as weeks (Monday first), with `None` for the days outside of it
+    pub fn heatmap(&self) -> Vec<Vec<Option<HeatmapDay>>> {
+        heatmap_weeks(&self.days, Utc::now().date_naive())
+    }
+
+    pub fn total_reviews(&self) -> i64 {
+        self.days.iter().map(|day| day.reviews).sum()
+    }
+
+    /// Number of days in the last year with reviews
+    pub fn active_days(&self) -> usize {
+        self.days.len()
+    }
+
+    /// Total answer time in the last year, formatted
+    pub fn time_total(&self) -> String {
+        format_duration(self.days.iter().map(|day| day.duration_ms).sum())
+    }
+
+    /// Total answer time in the last 30 days, formatted
+    pub fn time_recent(&self) -> String {
+        let since = Utc::now().date_naive() - Duration::days(i64::from(RECENT_DAYS));
+        format_duration(
+            self.days
+                .iter()
+                .filter(|day| day.date > since)
+                .map(|day| day.duration_ms)
+                .sum(),
+        )
+    }
+
+    /// Whether a retention in percent is below the target
+    pub fn is_below_target(&self, percent: &f64) -> bool {
+        *percent < f64::from(self.desired_retention)
+    }
+
+    pub fn max_answers(&self) -> i64 {
+        self.answers
+            .iter()
+            .map(|answer| answer.learning + answer.review)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn max_forecast(&self) -> i64 {
+        self.forecast.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn max_stability(&self) -> i64 {
+        max_bucket(&self.stability)
+    }
+
+    pub fn max_difficulty(&self) -> i64 {
+        max_bucket(&self.difficulty)
+    }
+
+    /// The date of a day of the forecast
+    pub fn forecast_date(&self, day: &usize) -> NaiveDate {
+        Utc::now().date_naive() + Duration::days(*day as i64)
+    }
+}
+
+/// Height or width of a bar in percent of the largest value
+pub fn bar_percent(value: &i64, max: &i64) -> i64 {
+    if *max > 0 { value * 100 / max } else { 0 }
+}
+
+fn max_bucket(buckets: &[Bucket]) -> i64 {
+    buckets.iter().map(|bucket| bucket.count).max().unwrap_or(0)
+}
+
+/// Formats milliseconds as hours and minutes (or seconds, for short times)
+pub fn format_duration(ms: i64) -> String {
+    let seconds = ms / 1000;
+    match (seconds / 3600, seconds / 60 % 60) {
+        (0, 0) => format!("{seconds}s"),
+        (0, minutes) => format!("{minutes}m"),
+        (hours, minutes) => format!("{hours}h {minutes}m"),
+    }
+}
+
+fn heatmap_weeks(days: &[DayReviews], today: NaiveDate) -> Vec<Vec<Option<HeatmapDay>>> {
+    let first = today - Duration::days(i64::from(HISTORY_DAYS) - 1);
+    let reviews: HashMap<NaiveDate, i64> = days.iter().map(|day| (day.date, day.reviews)).collect();
+    let max = reviews.values().copied().max().unwrap_or(0);
+
+    let mut weeks = Vec::new();
+    let mut date = first - Duration::days(i64::from(first.weekday().num_days_from_monday()));
+    while date <= today {
+        let week = (0..7)
+            .map(|offset| {
+                let date = date + Duration::days(offset);
+                (first..=today).contains(&date).then(|| {
+                    let reviews = reviews.get(&date).copied().unwrap_or(0);
+                    let level = match reviews {
+                        0 => 0,
+                        // Quartiles of the busiest day
+                        _ => ((reviews * 4 + max - 1) / max).clamp(1, 4) as u8,
+                    };
+                    HeatmapDay {
+                        date,
+                        reviews,
+                        level,
+                    }
+                })
+            })
+            .collect();
+        weeks.push(week);
+        date += Duration::days(7);
+    }
+    weeks
+}
+
+/// Computes the dashboard for all decks of the user, or one deck
+pub async fn fetch_dashboard(
+    pool: &PgPool,
+    user_id: &str,
+    deck_id: Option<i32>,
+) -> Result<Dashboard, sqlx::Error> {
+    let days = sqlx::query_as::<_, DayReviews>(
+        r#"
+        SELECT r.reviewed::DATE AS date, COUNT(*) AS reviews,
+            COALESCE(SUM(r.duration_ms), 0)::BIGINT AS duration_ms
+        FROM review_log r
+        INNER JOIN flashcard f ON r.flashcard_id = f.id
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1 AND ($2::INTEGER IS NULL OR f.deck_id = $2)
+            AND r.reviewed >= CURRENT_DATE - ($3 - 1)
+        GROUP BY 1
+        ORDER BY 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(deck_id)
+    .bind(HISTORY_DAYS)
+    .fetch_all(pool)
+    .await?;
+
+    let answer_rows = sqlx::query_as::<_, AnswerRow>(
+        r#"
+        WITH reviews AS (
+            SELECT r.rating, r.reviewed,
+                LAG(r.id) OVER (PARTITION BY r.flashcard_id ORDER BY r.reviewed) IS NOT NULL
+                    AS is_review
+            FROM review_log r
+            INNER JOIN flashcard f ON r.flashcard_id = f.id
+            INNER JOIN deck d ON f.deck_id = d.id
+            WHERE d.user_id = $1 AND ($2::INTEGER IS NULL OR f.deck_id = $2)
+        )
+        SELECT rating, is_review, COUNT(*) AS total,
+            COUNT(*) FILTER (WHERE reviewed >= NOW() - make_interval(days => $3)) AS recent
+        FROM reviews
+        GROUP BY rating, is_review
+        "#,
+    )
+    .bind(user_id)
+    .bind(deck_id)
+    .bind(RECENT_DAYS)
+    .fetch_all(pool)
+    .await?;
+
+    let mut answers: Vec<AnswerCount> = CardRating::ALL
+        .iter()
+        .map(|rating| AnswerCount {
+            rating: *rating,
+            learning: 0,
+            review: 0,
+        })
+        .collect();
+    let mut retention_recent = Retention::default();
+    let mut retention_all = Retention::default();
+    for row in answer_rows {
+        let Some(answer) = answers.iter_mut().find(|a| a.rating == row.rating) else {
+            continue;
+        };
+        if row.is_review {
+            answer.review += row.total;
+            let passed = row.rating != CardRating::Again;
+            for (retention, count) in [
+                (&mut retention_all, row.total),
+                (&mut retention_recent, row.recent),
+            ] {
+                retention.reviews += count;
+                if passed {
+                    retention.passed += count;
+                }
+            }
+        } else {
+            answer.learning += row.total;
+        }
+    }
+
+    let buckets: Vec<(String, i32, i64)> = sqlx::query_as(
+        r#"
+        SELECT 'stability' AS kind, width_bucket(f.last_stability::FLOAT8, $3) AS bucket,
+            COUNT(*) AS count
+        FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1 AND ($2::INTEGER IS NULL OR f.deck_id = $2)
+            AND f.last_stability IS NOT NULL
+        GROUP BY 1, 2
+        UNION ALL
+        SELECT 'difficulty', LEAST(GREATEST(width_bucket(f.last_difficulty::FLOAT8, 1, 10, 9), 1), 9),
+            COUNT(*)
+        FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1 AND ($2::INTEGER IS NULL OR f.deck_id = $2)
+            AND f.last_difficulty IS NOT NULL
+        GROUP BY 1, 2
+        "#,
+    )
+    .bind(user_id)
+    .bind(deck_id)
+    .bind(
+        STABILITY_BUCKETS[..STABILITY_BUCKETS.len() - 1]
+            .iter()
+            .map(|(bound, _)| *bound)
+            .collect::<Vec<_>>(),
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut stability: Vec<Bucket> = STABILITY_BUCKETS
+        .iter()
+        .map(|(_, label)| Bucket {
+            label: label.to_string(),
+            count: 0,
+        })
+        .collect();
+    let mut difficulty: Vec<Bucket> = (1..10)
+        .map(|low| Bucket {
+            label: format!("{low}–{}", low + 1),
+            count: 0,
+        })
+        .collect();
+    for (kind, bucket, count) in buckets {
+        // Stability buckets start at 0, difficulty buckets at 1
+        let target = match kind.as_str() {
+            "stability" => stability.get_mut(bucket as usize),
+            _ => difficulty.get_mut((bucket - 1) as usize),
+        };
+        if let Some(target) = target {
+            target.count += count;
+        }
+    }
+
+    let due: Vec<(i32, i64)> = sqlx::query_as(
+        r#"
+        SELECT GREATEST(f.last_scheduled::DATE - CURRENT_DATE, 0) AS day, COUNT(*) AS count
+        FROM flashcard f
+        INNER JOIN deck d ON f.deck_id = d.id
+        WHERE d.user_id = $1 AND ($2::INTEGER IS NULL OR f.deck_id = $2)
+            AND NOT f.suspended AND f.last_scheduled < CURRENT_DATE + $3
+        GROUP BY 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(deck_id)
+    .bind(FORECAST_DAYS)
+    .fetch_all(pool)
+    .await?;
+    let mut forecast = vec![0; FORECAST_DAYS as usize];
+    for (day, count) in due {
+        if let Some(target) = forecast.get_mut(day as usize) {
+            *target = count;
+        }
+    }
+
+    let settings = load_user_settings(pool, user_id).await?;
+
+    Ok(Dashboard {
+        days,
+        desired_retention: settings.desired_retention,
+        retention_recent,
+        retention_all,
+        answers,
+        stability,
+        difficulty,
+        forecast,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Datelike, NaiveDate, Weekday};
+
+    use super::{DayReviews, format_duration, heatmap_weeks};
+
+    #[test]
+    fn test_heatmap() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let days = [(today, 8), (today.pred_opt().unwrap(), 1)].map(|(date, reviews)| DayReviews {
+            date,
+            reviews,
+            duration_ms: 0,
+        });
+        let weeks = heatmap_weeks(&days, today);
+        assert_eq!(weeks.iter().flatten().flatten().count(), 365);
+        // Weeks start on Monday, and the days before the first one are empty
+        let first_week = &weeks[0];
+        assert!(first_week[0].is_none());
+        let first = first_week.iter().flatten().next().unwrap();
+        assert_eq!(first.date, NaiveDate::from_ymd_opt(2025, 10, 19).unwrap());
+
+        let last_week = weeks.last().unwrap();
+        let today_cell = last_week[today.weekday().num_days_from_monday() as usize]
+            .as_ref()
+            .unwrap();
+        assert_eq!(today.weekday(), Weekday::Sun);
+        assert_eq!((today_cell.reviews, today_cell.level), (8, 4));
+        let yesterday = last_week[5].as_ref().unwrap();
+        assert_eq!((yesterday.reviews, yesterday.level), (1, 1));
+        assert_eq!(last_week[0].as_ref().unwrap().level, 0);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42_000), "42s");
+        assert_eq!(format_duration(125_000), "2m");
+        assert_eq!(format_duration(3_720_000), "1h 2m");
+    }
+}
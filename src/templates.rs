@@ -1,5 +1,9 @@
 //! Templates for rendering HTML pages using Askama.
-use crate::models;
+use crate::{
+    browse::{BrowsePage, BrowseQuery, SortColumn},
+    models::{self, CardRating},
+    search::CardState,
+};
 use askama::Template;
 
 #[derive(Template)]
@@ -8,26 +12,41 @@ pub struct WebViewTemplate {
     pub is_authenticated: bool,
 }
 
+#[derive(Template)]
+#[template(path = "api_tokens.html")]
+pub struct ApiTokensTemplate {
+    pub tokens: Vec<models::ApiToken>,
+    /// A token that was just created, shown once
+    pub new_token: Option<String>,
+}
+
 #[derive(Template)]
 #[template(path = "decks.html")]
 pub struct Decks {
-    pub decks: Vec<models::Deck>,
+    pub decks: Vec<models::DeckSummary>,
     pub stats: models::FlashcardStats,
 }
 
 #[derive(Template)]
 #[template(path = "deck.html")]
 pub struct DeckNewTemplate {
-    pub deck: models::Deck,
+    pub deck: models::DeckSummary,
 }
 
 #[derive(Template)]
 #[template(path = "deck_import.html")]
 pub struct DeckImportTemplate {
     pub deck: models::Deck,
+    pub jobs: Vec<models::ImportJob>,
     pub is_authenticated: bool,
 }
 
+#[derive(Template)]
+#[template(path = "import_job.html")]
+pub struct ImportJobTemplate {
+    pub job: models::ImportJob,
+}
+
 #[derive(Template)]
 #[template(path = "flashcards.html")]
 pub struct FlashcardsTemplate {
@@ -49,3 +68,82 @@ pub struct FlashcardListTemplate {
     pub page: u32,
     pub has_more: bool,
 }
+
+#[derive(Template)]
+#[template(path = "search.html")]
+pub struct SearchTemplate {
+    pub is_authenticated: bool,
+    pub query: crate::search::SearchQuery,
+    pub decks: Vec<models::Deck>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Template)]
+#[template(path = "search_results.html")]
+pub struct SearchResultsTemplate {
+    pub results: crate::search::SearchPage,
+}
+
+#[derive(Template)]
+#[template(path = "browse.html")]
+pub struct BrowseTemplate {
+    pub is_authenticated: bool,
+    pub query: BrowseQuery,
+    pub decks: Vec<models::Deck>,
+}
+
+#[derive(Template)]
+#[template(path = "browse_rows.html")]
+pub struct BrowseRowsTemplate {
+    pub query: BrowseQuery,
+    pub results: BrowsePage,
+}
+
+#[derive(Template)]
+#[template(path = "restore_summary.html")]
+pub struct RestoreSummaryTemplate {
+    pub summary: crate::backup::RestoreSummary,
+}
+
+#[derive(Template)]
+#[template(path = "bulk_summary.html")]
+pub struct BulkSummaryTemplate {
+    pub summary: crate::bulk::BulkSummary,
+}
+
+#[derive(Template)]
+#[template(path = "stats.html")]
+pub struct StatsTemplate {
+    pub is_authenticated: bool,
+    pub query: crate::stats::StatsQuery,
+    pub decks: Vec<models::Deck>,
+    pub dashboard: crate::stats::Dashboard,
+    /// The latest sessions on the glasses
+    pub sessions: Vec<models::ReviewSession>,
+}
+
+#[derive(Template)]
+#[template(path = "review.html")]
+pub struct ReviewTemplate {
+    pub is_authenticated: bool,
+    pub card: ReviewCardTemplate,
+}
+
+/// The current card of a review in the webview, or the summary when no cards are left
+#[derive(Template)]
+#[template(path = "review_card.html")]
+pub struct ReviewCardTemplate {
+    pub card: Option<models::Flashcard>,
+    pub deck_name: String,
+    /// Number of cards after this one
+    pub remaining: usize,
+    /// The ids of the remaining cards, comma-separated
+    pub queue: String,
+    pub summary: crate::srs::SessionSummary,
+}
+
+#[derive(Template)]
+#[template(path = "session.html")]
+pub struct SessionTemplate {
+    pub is_authenticated: bool,
+}
@@ -0,0 +1,212 @@
+//! Localized voice commands, by transcription locale
+use crate::{models::CardRating, srs::SessionCommand};
+
+/// The transcription locales with a command vocabulary, with their labels for the settings
+pub const LOCALES: [(&str, &str); 5] = [
+    ("en-US", "English (US)"),
+    ("en-GB", "English (UK)"),
+    ("es-ES", "Spanish (Spain)"),
+    ("es-MX", "Spanish (Mexico)"),
+    ("zh-HK", "Cantonese (Hong Kong)"),
+];
+
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// The words and phrases of each voice command, lowercase, with synonyms; the first one is
+/// shown in the hints
+#[derive(Debug, PartialEq)]
+pub struct Vocabulary {
+    pub start: &'static [&'static str],
+    pub reveal: &'static [&'static str],
+    pub skip: &'static [&'static str],
+    pub easy: &'static [&'static str],
+    pub good: &'static [&'static str],
+    pub difficult: &'static [&'static str],
+    pub again: &'static [&'static str],
+    /// Accepts the rating proposed for a spoken answer
+    pub accept: &'static [&'static str],
+    /// Resumes the previous session instead of starting a new one
+    pub resume: &'static [&'static str],
+    /// Lists the decks and tags that can be named after the start command
+    pub list_decks: &'static [&'static str],
+    /// The numbers one to four, for the ratings again, difficult, good and easy (as in Anki)
+    pub numbers: [&'static str; 4],
+    /// Words that negate the command words after them, e.g. "not easy"
+    pub negations: &'static [&'static str],
+}
+
+const ENGLISH: Vocabulary = Vocabulary {
+    start: &["start", "begin", "let's go"],
+    reveal: &["reveal", "show", "answer", "flip"],
+    skip: &["skip", "next", "pass", "later"],
+    easy: &["easy", "simple", "trivial", "perfect"],
+    good: &["good", "okay", "ok", "fine", "correct", "right"],
+    difficult: &["difficult", "hard", "tough", "tricky"],
+    again: &[
+        "again",
+        "fail",
+        "failed",
+        "wrong",
+        "forgot",
+        "forgotten",
+        "repeat",
+    ],
+    accept: &["yes", "accept", "confirm", "agree"],
+    resume: &["resume", "continue", "carry on"],
+    list_decks: &["list decks", "list", "decks", "which decks"],
+    numbers: ["one", "two", "three", "four"],
+    negations: &["not", "no", "don't", "didn't", "isn't", "wasn't", "never"],
+};
+
+const SPANISH: Vocabulary = Vocabulary {
+    start: &["empezar", "empieza", "comenzar", "comienza", "iniciar"],
+    reveal: &["revelar", "revela", "mostrar", "muestra", "respuesta"],
+    skip: &["saltar", "salta", "pasar", "pasa", "siguiente"],
+    easy: &["fácil", "facil", "sencillo"],
+    good: &["bien", "bueno", "vale", "correcto"],
+    difficult: &["difícil", "dificil", "duro", "complicado"],
+    again: &[
+        "otra vez", "de nuevo", "repetir", "mal", "olvidé", "olvide", "fallé", "falle",
+    ],
+    accept: &["sí", "si", "acepto", "aceptar", "confirmar"],
+    resume: &[
+        "continuar",
+        "continúa",
+        "continua",
+        "reanudar",
+        "seguir",
+        "sigue",
+    ],
+    list_decks: &["lista de mazos", "listar mazos", "lista", "mazos"],
+    numbers: ["uno", "dos", "tres", "cuatro"],
+    negations: &["no", "nunca"],
+};
+
+/// Transcriptions are in traditional characters, simplified ones are accepted too
+const CANTONESE: Vocabulary = Vocabulary {
+    start: &["開始", "开始"],
+    reveal: &["答案", "顯示", "显示"],
+    skip: &["跳過", "跳过", "下一張", "下一张"],
+    // "好" before a rating is "very" ("好難", very hard), so those phrases outmatch "好" (good)
+    easy: &[
+        "容易",
+        "簡單",
+        "简单",
+        "易",
+        "好易",
+        "好容易",
+        "好簡單",
+        "好简单",
+    ],
+    good: &["好", "記得", "记得", "啱"],
+    difficult: &["難", "难", "好難", "好难"],
+    again: &["再嚟", "再來", "再来", "唔記得", "唔记得", "忘記", "忘记"],
+    accept: &["係", "接受", "確認", "确认"],
+    resume: &["繼續", "继续"],
+    list_decks: &["卡組", "卡组", "有咩卡組"],
+    numbers: ["一", "二", "三", "四"],
+    negations: &["唔", "不", "冇"],
+};
+
+/// Whether there is a vocabulary for the locale
+pub fn is_supported(locale: &str) -> bool {
+    LOCALES.iter().any(|(code, _)| *code == locale)
+}
+
+impl Vocabulary {
+    /// The vocabulary of the language of the locale, English if unsupported
+    pub fn for_locale(locale: &str) -> &'static Vocabulary {
+        match locale.split('-').next().unwrap_or_default() {
+            "es" => &SPANISH,
+            "zh" | "yue" => &CANTONESE,
+            _ => &ENGLISH,
+        }
+    }
+
+    /// The command words and phrases with their commands
+    pub fn phrases(&self) -> impl Iterator<Item = (&'static str, SessionCommand)> {
+        [
+            (self.start, SessionCommand::Start),
+            (self.reveal, SessionCommand::Reveal),
+            (self.skip, SessionCommand::Skip),
+            (self.easy, SessionCommand::Rate(CardRating::Easy)),
+            (self.good, SessionCommand::Rate(CardRating::Good)),
+            (self.difficult, SessionCommand::Rate(CardRating::Difficult)),
+            (self.again, SessionCommand::Rate(CardRating::Again)),
+            (self.accept, SessionCommand::Accept),
+            (self.resume, SessionCommand::Resume),
+            (self.list_decks, SessionCommand::ListDecks),
+        ]
+        .into_iter()
+        .flat_map(|(words, command)| words.iter().map(move |word| (*word, command)))
+    }
+
+    /// The numbers (words and digits) with their ratings
+    pub fn numbers(&self) -> impl Iterator<Item = (&'static str, SessionCommand)> {
+        let ratings = [
+            CardRating::Again,
+            CardRating::Difficult,
+            CardRating::Good,
+            CardRating::Easy,
+        ];
+        self.numbers
+            .into_iter()
+            .zip(["1", "2", "3", "4"])
+            .zip(ratings)
+            .flat_map(|((word, digit), rating)| {
+                [word, digit].map(|word| (word, SessionCommand::Rate(rating)))
+            })
+    }
+
+    /// The word shown for a command in the hints and prompts
+    pub fn word(&self, command: SessionCommand) -> &'static str {
+        match command {
+            SessionCommand::Start => self.start[0],
+            SessionCommand::Reveal => self.reveal[0],
+            SessionCommand::Skip => self.skip[0],
+            SessionCommand::Rate(CardRating::Easy) => self.easy[0],
+            SessionCommand::Rate(CardRating::Good) => self.good[0],
+            SessionCommand::Rate(CardRating::Difficult) => self.difficult[0],
+            SessionCommand::Rate(CardRating::Again) => self.again[0],
+            SessionCommand::Accept => self.accept[0],
+            SessionCommand::Resume => self.resume[0],
+            SessionCommand::ListDecks => self.list_decks[0],
+        }
+    }
+
+    /// The words to say for the ratings, e.g. "'easy', 'good', 'difficult', or 'again'"
+    pub fn rating_hint(&self) -> String {
+        format!(
+            "'{}', '{}', '{}', or '{}'",
+            self.easy[0], self.good[0], self.difficult[0], self.again[0]
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LOCALES, Vocabulary, is_supported};
+    use crate::{models::CardRating, srs::SessionCommand};
+
+    #[test]
+    fn test_vocabulary() {
+        let english = Vocabulary::for_locale("en-US");
+        assert_eq!(Vocabulary::for_locale("fr-FR"), english);
+        assert_eq!(
+            english.rating_hint(),
+            "'easy', 'good', 'difficult', or 'again'"
+        );
+        assert!(
+            english
+                .numbers()
+                .any(|number| number == ("3", SessionCommand::Rate(CardRating::Good)))
+        );
+
+        let cantonese = Vocabulary::for_locale("zh-HK");
+        assert_eq!(cantonese.rating_hint(), "'容易', '好', '難', or '再嚟'");
+        assert_eq!(cantonese.word(SessionCommand::Reveal), "答案");
+
+        assert!(LOCALES.iter().all(|(locale, _)| is_supported(locale)));
+        assert!(!is_supported("fr-FR"));
+    }
+}
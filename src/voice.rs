@@ -0,0 +1,444 @@
+//! Parser for the voice commands in final transcriptions.
+//!
+//! The transcription is split into words (and single CJK characters), in which the command
+//! words and phrases of the vocabulary are looked up: first exactly, then the numbers one to
+//! four, then within a small edit distance (e.g. "goods"). Negated words ("not easy") are
+//! ignored, and an utterance with different commands (e.g. "good or easy") is ambiguous.
+//! The words after the start command name a deck or tag to review (e.g. "start verbs").
+use crate::{
+    srs::{ReviewScope, SessionCommand},
+    vocabulary::Vocabulary,
+};
+
+/// Separates clauses, which end the scope of a negation
+pub(crate) const BOUNDARY: &str = ",";
+
+/// Number of words after a negation that it applies to, e.g. "not very easy"
+const NEGATION_SCOPE: usize = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parsed {
+    Command(SessionCommand),
+    /// Different commands, in the order they were said
+    Ambiguous(Vec<SessionCommand>),
+    Unrecognised,
+}
+
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}')
+}
+
+/// Splits a transcription into lowercase words, with each CJK character as a word of its own
+/// and punctuation as clause boundaries
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let end_word = |word: &mut String, tokens: &mut Vec<String>| {
+        if !word.is_empty() {
+            tokens.push(std::mem::take(word));
+        }
+    };
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if is_cjk(c) {
+            end_word(&mut word, &mut tokens);
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.push(c);
+        } else if c == '\'' || c == '’' {
+            // "don't" is one word
+        } else {
+            end_word(&mut word, &mut tokens);
+            if matches!(c, ',' | '.' | ';' | '!' | '?' | '，' | '。' | '！' | '？')
+                && tokens.last().is_some_and(|token| token != BOUNDARY)
+            {
+                tokens.push(BOUNDARY.to_string());
+            }
+        }
+    }
+    end_word(&mut word, &mut tokens);
+    tokens
+}
+
+/// The Levenshtein distance between two words, in characters
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Whether a heard word is close enough to a command word (e.g. "goods" or "revealed"), as
+/// short words like "hard" and "card" must match exactly
+fn is_similar(heard: &str, word: &str) -> bool {
+    let (heard_len, word_len) = (heard.chars().count(), word.chars().count());
+    let tolerance = match (heard_len.min(word_len), heard_len.max(word_len)) {
+        (..4, _) => 0,
+        (_, 8..) => 2,
+        (_, 5..) => 1,
+        _ => 0,
+    };
+    tolerance > 0 && edit_distance(heard, word) <= tolerance
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Matching {
+    Exact,
+    Fuzzy,
+}
+
+/// The commands in the words, with whether they were negated
+fn find_commands(
+    tokens: &[String],
+    phrases: &[(Vec<String>, SessionCommand)],
+    negations: &[Vec<String>],
+    matching: Matching,
+) -> Vec<(SessionCommand, bool)> {
+    let matches_at = |i: usize, phrase: &[String]| {
+        tokens.len() >= i + phrase.len()
+            && phrase.iter().zip(&tokens[i..]).all(|(word, token)| {
+                word == token || (matching == Matching::Fuzzy && is_similar(token, word))
+            })
+    };
+    let mut commands = Vec::new();
+    let mut negated_until = None;
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i] == BOUNDARY {
+            negated_until = None;
+            i += 1;
+            continue;
+        }
+        // The longest phrase, as "唔記得" (again) starts with a negation
+        let phrase = phrases
+            .iter()
+            .filter(|(phrase, _)| matches_at(i, phrase))
+            .max_by_key(|(phrase, _)| phrase.len());
+        if let Some((phrase, command)) = phrase {
+            commands.push((*command, negated_until.is_some_and(|until| i <= until)));
+            i += phrase.len();
+        } else if let Some(negation) = negations.iter().find(|negation| matches_at(i, negation)) {
+            negated_until = Some(i + negation.len() - 1 + NEGATION_SCOPE);
+            i += negation.len();
+        } else {
+            i += 1;
+        }
+    }
+    commands
+}
+
+fn tokenize_all<'a>(
+    phrases: impl Iterator<Item = (&'a str, SessionCommand)>,
+) -> Vec<(Vec<String>, SessionCommand)> {
+    phrases
+        .map(|(phrase, command)| (tokenize(phrase), command))
+        .collect()
+}
+
+/// The command in a transcription
+pub fn parse(text: &str, vocabulary: &Vocabulary) -> Parsed {
+    let tokens = tokenize(text);
+    let phrases = tokenize_all(vocabulary.phrases());
+    let numbers = tokenize_all(vocabulary.numbers());
+    let negations: Vec<Vec<String>> = vocabulary.negations.iter().map(|n| tokenize(n)).collect();
+
+    let passes = [
+        (&phrases, Matching::Exact),
+        (&numbers, Matching::Exact),
+        (&phrases, Matching::Fuzzy),
+    ];
+    for (phrases, matching) in passes {
+        let found = find_commands(&tokens, phrases, &negations, matching);
+        if found.is_empty() {
+            continue;
+        }
+        let mut commands: Vec<SessionCommand> = Vec::new();
+        for (command, negated) in found {
+            if !negated && !commands.contains(&command) {
+                commands.push(command);
+            }
+        }
+        return match commands.len() {
+            // Only negated, e.g. "not easy"
+            0 => Parsed::Unrecognised,
+            1 => Parsed::Command(commands[0]),
+            _ => Parsed::Ambiguous(commands),
+        };
+    }
+    Parsed::Unrecognised
+}
+
+/// Whether the utterance is only the command, e.g. "Show, answer." for reveal but not "the
+/// answer" or "to begin", as when an answer is expected anything else is part of the answer
+pub fn said_alone(text: &str, vocabulary: &Vocabulary, command: SessionCommand) -> bool {
+    let tokens: Vec<String> = tokenize(text)
+        .into_iter()
+        .filter(|token| token != BOUNDARY)
+        .collect();
+    let phrases: Vec<Vec<String>> = vocabulary
+        .phrases()
+        .filter(|(_, c)| *c == command)
+        .map(|(phrase, _)| tokenize(phrase))
+        .collect();
+    let mut i = 0;
+    while i < tokens.len() {
+        let Some(phrase) = phrases
+            .iter()
+            .filter(|phrase| tokens[i..].starts_with(phrase))
+            .max_by_key(|phrase| phrase.len())
+        else {
+            return false;
+        };
+        i += phrase.len();
+    }
+    !tokens.is_empty()
+}
+
+/// The words said after the start command, e.g. `["verbs"]` for "Start verbs.", or `None`
+/// without a start command
+pub fn start_words(text: &str, vocabulary: &Vocabulary) -> Option<Vec<String>> {
+    let tokens = tokenize(text);
+    let starts: Vec<Vec<String>> = vocabulary.start.iter().map(|s| tokenize(s)).collect();
+    (0..tokens.len()).find_map(|i| {
+        let start = starts
+            .iter()
+            .filter(|start| tokens[i..].starts_with(start))
+            .max_by_key(|start| start.len())?;
+        Some(
+            tokens[i + start.len()..]
+                .iter()
+                .filter(|token| *token != BOUNDARY)
+                .cloned()
+                .collect(),
+        )
+    })
+}
+
+/// The deck or tag named by the words: one with all the words of its name said (e.g. "the
+/// verbs deck" for "verbs"), the longest first, or else one with all the words said in its
+/// name (e.g. "spanish" for "Spanish verbs"); decks come before tags with the same name
+pub fn match_scope<'a>(words: &[String], scopes: &'a [ReviewScope]) -> Option<&'a ReviewScope> {
+    let contains = |words: &[String], word: &String| {
+        words
+            .iter()
+            .any(|other| other == word || is_similar(other, word))
+    };
+    let names: Vec<Vec<String>> = scopes.iter().map(|scope| tokenize(scope.name())).collect();
+    let named = scopes
+        .iter()
+        .zip(&names)
+        .filter(|(_, name)| !name.is_empty() && name.iter().all(|word| contains(words, word)))
+        .min_by_key(|(_, name)| std::cmp::Reverse(name.len()));
+    if let Some((scope, _)) = named {
+        return Some(scope);
+    }
+    if words.is_empty() {
+        return None;
+    }
+    scopes
+        .iter()
+        .zip(&names)
+        .find(|(_, name)| words.iter().all(|word| contains(name, word)))
+        .map(|(scope, _)| scope)
+}
+
+/// The question for an ambiguous utterance, e.g. "Did you mean 'good' or 'easy'?"
+pub fn confirmation_prompt(commands: &[SessionCommand], vocabulary: &Vocabulary) -> String {
+    let words: Vec<String> = commands
+        .iter()
+        .map(|command| format!("'{}'", vocabulary.word(*command)))
+        .collect();
+    format!("Did you mean {}? Please say it again.", words.join(" or "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Parsed, confirmation_prompt, edit_distance, match_scope, parse, said_alone, start_words,
+        tokenize,
+    };
+    use crate::{
+        models::CardRating,
+        srs::{ReviewScope, SessionCommand},
+        vocabulary::Vocabulary,
+    };
+
+    const EASY: SessionCommand = SessionCommand::Rate(CardRating::Easy);
+    const GOOD: SessionCommand = SessionCommand::Rate(CardRating::Good);
+    const DIFFICULT: SessionCommand = SessionCommand::Rate(CardRating::Difficult);
+    const AGAIN: SessionCommand = SessionCommand::Rate(CardRating::Again);
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Don't know, Good."),
+            ["dont", "know", ",", "good", ","]
+        );
+        assert_eq!(tokenize("好難呀"), ["好", "難", "呀"]);
+        assert_eq!(tokenize("ok 3"), ["ok", "3"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("goods", "good"), 1);
+        assert_eq!(edit_distance("dificult", "difficult"), 1);
+        assert_eq!(edit_distance("fácil", "facil"), 1);
+        assert_eq!(edit_distance("", "again"), 5);
+    }
+
+    /// Final transcriptions as they come from the cloud, with the expected command
+    #[test]
+    fn test_corpus() {
+        let corpus: &[(&str, &str, Option<SessionCommand>)] = &[
+            ("en-US", "Start.", Some(SessionCommand::Start)),
+            ("en-US", "Let's go.", Some(SessionCommand::Start)),
+            ("en-US", "Reveal.", Some(SessionCommand::Reveal)),
+            ("en-US", "Show me the answer.", Some(SessionCommand::Reveal)),
+            ("en-US", "Revealed.", Some(SessionCommand::Reveal)),
+            ("en-US", "Skip this one.", Some(SessionCommand::Skip)),
+            ("en-US", "Next card.", Some(SessionCommand::Skip)),
+            ("en-US", "Good.", Some(GOOD)),
+            ("en-US", "Goods.", Some(GOOD)),
+            ("en-US", "Okay.", Some(GOOD)),
+            ("en-US", "It was good, not easy.", Some(GOOD)),
+            ("en-US", "it was good not easy", Some(GOOD)),
+            ("en-US", "Not very easy, but good.", Some(GOOD)),
+            ("en-US", "Easy.", Some(EASY)),
+            ("en-US", "That was easy!", Some(EASY)),
+            ("en-US", "Easy peasy.", Some(EASY)),
+            ("en-US", "Hard.", Some(DIFFICULT)),
+            ("en-US", "Too difficult.", Some(DIFFICULT)),
+            ("en-US", "Dificult.", Some(DIFFICULT)),
+            ("en-US", "Again.", Some(AGAIN)),
+            ("en-US", "Fail.", Some(AGAIN)),
+            ("en-US", "I forgot.", Some(AGAIN)),
+            ("en-US", "Not sure, again.", Some(AGAIN)),
+            ("en-US", "Three.", Some(GOOD)),
+            ("en-US", "3", Some(GOOD)),
+            ("en-US", "Number one.", Some(AGAIN)),
+            ("en-US", "Yes.", Some(SessionCommand::Accept)),
+            ("en-US", "Continue.", Some(SessionCommand::Resume)),
+            ("en-US", "Let's carry on.", Some(SessionCommand::Resume)),
+            ("en-US", "List decks.", Some(SessionCommand::ListDecks)),
+            ("en-US", "Not easy.", None),
+            ("en-US", "What's the weather like?", None),
+            ("en-US", "Uh-huh.", None),
+            ("es-ES", "Empezar.", Some(SessionCommand::Start)),
+            (
+                "es-ES",
+                "Muestra la respuesta.",
+                Some(SessionCommand::Reveal),
+            ),
+            ("es-ES", "Fácil.", Some(EASY)),
+            ("es-ES", "Muy bien.", Some(GOOD)),
+            ("es-ES", "No fue fácil, bien.", Some(GOOD)),
+            ("es-ES", "Otra vez.", Some(AGAIN)),
+            ("es-ES", "Dos.", Some(DIFFICULT)),
+            ("es-ES", "Sí, acepto.", Some(SessionCommand::Accept)),
+            ("es-ES", "Continuar.", Some(SessionCommand::Resume)),
+            ("zh-HK", "開始", Some(SessionCommand::Start)),
+            ("zh-HK", "睇答案", Some(SessionCommand::Reveal)),
+            ("zh-HK", "好", Some(GOOD)),
+            ("zh-HK", "唔記得", Some(AGAIN)),
+            ("zh-HK", "唔難", None),
+            ("zh-HK", "好難", Some(DIFFICULT)),
+            ("zh-HK", "好難呀", Some(DIFFICULT)),
+            ("zh-HK", "好易", Some(EASY)),
+            ("zh-HK", "好簡單", Some(EASY)),
+            ("zh-HK", "好好", Some(GOOD)),
+            ("zh-HK", "四", Some(EASY)),
+            ("zh-HK", "繼續", Some(SessionCommand::Resume)),
+            ("zh-HK", "有咩卡組", Some(SessionCommand::ListDecks)),
+        ];
+        for (locale, text, expected) in corpus {
+            let parsed = parse(text, Vocabulary::for_locale(locale));
+            let expected = expected.map_or(Parsed::Unrecognised, Parsed::Command);
+            assert_eq!(parsed, expected, "{locale}: {text}");
+        }
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let english = Vocabulary::for_locale("en-US");
+        let parsed = parse("Good or easy?", english);
+        assert_eq!(parsed, Parsed::Ambiguous(vec![GOOD, EASY]));
+        let Parsed::Ambiguous(commands) = parsed else {
+            unreachable!()
+        };
+        assert_eq!(
+            confirmation_prompt(&commands, english),
+            "Did you mean 'good' or 'easy'? Please say it again."
+        );
+        assert_eq!(
+            parse("Reveal, it's easy.", english),
+            Parsed::Ambiguous(vec![SessionCommand::Reveal, EASY])
+        );
+        // The same command twice is not ambiguous
+        assert_eq!(parse("Good, good.", english), Parsed::Command(GOOD));
+    }
+
+    #[test]
+    fn test_said_alone() {
+        let english = Vocabulary::for_locale("en-US");
+        let reveal = SessionCommand::Reveal;
+        assert!(said_alone("Show answer.", english, reveal));
+        assert!(said_alone("Flip", english, reveal));
+        assert!(!said_alone("The answer is house.", english, reveal));
+        assert!(!said_alone("", english, reveal));
+        assert!(said_alone("Next.", english, SessionCommand::Skip));
+        assert!(!said_alone("Next to.", english, SessionCommand::Skip));
+        // Answers that are other commands, e.g. for a card "empezar" with the back "to begin"
+        assert!(!said_alone("To begin.", english, SessionCommand::Start));
+        let spanish = Vocabulary::for_locale("es-ES");
+        assert!(!said_alone("La respuesta", spanish, reveal));
+    }
+
+    #[test]
+    fn test_start_scope() {
+        let english = Vocabulary::for_locale("en-US");
+        assert_eq!(start_words("Start.", english), Some(Vec::new()));
+        assert_eq!(
+            start_words("Let's go, Spanish verbs!", english),
+            Some(vec!["spanish".to_string(), "verbs".to_string()])
+        );
+        assert_eq!(start_words("Good.", english), None);
+
+        let deck = |id, name: &str| ReviewScope::Deck {
+            id,
+            name: name.to_string(),
+        };
+        let scopes = [
+            deck(1, "Cantonese"),
+            deck(2, "Spanish verbs"),
+            deck(3, "廣東話"),
+            ReviewScope::Tag("verbs".to_string()),
+        ];
+        let scope = |text: &str, vocabulary| {
+            let words = start_words(text, vocabulary).unwrap();
+            match_scope(&words, &scopes).map(ReviewScope::name)
+        };
+        assert_eq!(scope("Start Cantonese.", english), Some("Cantonese"));
+        assert_eq!(scope("Start cantonise", english), Some("Cantonese"));
+        assert_eq!(scope("Start verbs.", english), Some("verbs"));
+        assert_eq!(
+            scope("Start the Spanish verbs.", english),
+            Some("Spanish verbs")
+        );
+        assert_eq!(scope("Start Spanish.", english), Some("Spanish verbs"));
+        assert_eq!(scope("Start French.", english), None);
+        assert_eq!(scope("Start please.", english), None);
+        assert_eq!(scope("Okay, start the review.", english), None);
+        assert_eq!(scope("Let's go now.", english), None);
+        assert_eq!(
+            scope("開始廣東話", Vocabulary::for_locale("zh-HK")),
+            Some("廣東話")
+        );
+    }
+}